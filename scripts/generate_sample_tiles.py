@@ -0,0 +1,442 @@
+#!/usr/bin/env python3
+"""Generates `assets/sample.pmtiles`, the vector tiles bundled with the app, from an
+OpenStreetMap extract.
+
+OSM data is converted into the OpenMapTiles layers used by the styles: water, waterway, park,
+landcover, landuse, transportation, transportation_name, building, place and poi. Only ways and
+the outer rings of multipolygon relations are converted. The data is licensed under the ODbL,
+so the archive is attributed to the OpenStreetMap contributors.
+
+Usage:
+    curl -o extract.osm "https://overpass-api.de/api/map?bbox=37.58,55.725,37.66,55.775"
+    python3 scripts/generate_sample_tiles.py extract.osm
+
+The bookmarks of the app expect the extract around 55.75 N, 37.62 E.
+"""
+
+import gzip
+import json
+import math
+import os
+import struct
+import sys
+import xml.etree.ElementTree as ElementTree
+
+EXTENT = 4096
+MIN_ZOOM = 0
+MAX_ZOOM = 14
+NAME = "Sample City"
+ATTRIBUTION = "© OpenStreetMap contributors"
+
+OUTPUT = os.path.join(os.path.dirname(__file__), "..", "assets", "sample.pmtiles")
+
+# OSM highway values as OpenMapTiles transportation classes with the first zoom level
+HIGHWAYS = {
+    "motorway": ("motorway", 4),
+    "motorway_link": ("motorway", 10),
+    "trunk": ("trunk", 5),
+    "trunk_link": ("trunk", 10),
+    "primary": ("primary", 7),
+    "primary_link": ("primary", 11),
+    "secondary": ("secondary", 9),
+    "secondary_link": ("secondary", 11),
+    "tertiary": ("tertiary", 11),
+    "tertiary_link": ("tertiary", 12),
+    "residential": ("minor", 12),
+    "unclassified": ("minor", 12),
+    "living_street": ("minor", 13),
+    "road": ("minor", 13),
+    "service": ("service", 13),
+    "pedestrian": ("path", 13),
+    "track": ("track", 14),
+    "footway": ("path", 14),
+    "path": ("path", 14),
+    "cycleway": ("path", 14),
+    "steps": ("path", 14),
+}
+PLACES = {"city": 4, "town": 8, "village": 10, "suburb": 11, "quarter": 13, "neighbourhood": 13}
+WATERWAYS = {"river": 8, "canal": 10, "stream": 13, "ditch": 14, "drain": 14}
+LANDCOVER = {
+    ("natural", "wood"): "wood",
+    ("landuse", "forest"): "wood",
+    ("landuse", "grass"): "grass",
+    ("landuse", "meadow"): "grass",
+    ("leisure", "garden"): "grass",
+    ("natural", "scrub"): "grass",
+    ("natural", "sand"): "sand",
+    ("natural", "beach"): "sand",
+}
+LANDUSE = {"residential", "commercial", "industrial", "retail", "railway", "cemetery"}
+POI_KEYS = ["amenity", "shop", "tourism", "leisure"]
+
+
+# --- Features ---------------------------------------------------------------------------------
+
+
+def feature(layer, geometry_type, coordinates, min_zoom, **properties):
+    return {
+        "layer": layer,
+        "type": geometry_type,
+        "coordinates": coordinates,
+        "min_zoom": min_zoom,
+        "properties": {key: value for key, value in properties.items() if value is not None},
+        "bbox": (
+            min(lon for lon, _ in coordinates),
+            min(lat for _, lat in coordinates),
+            max(lon for lon, _ in coordinates),
+            max(lat for _, lat in coordinates),
+        ),
+    }
+
+
+def render_height(tags):
+    for key, factor in [("height", 1.0), ("building:levels", 3.0)]:
+        try:
+            return max(1, round(float(tags[key].split()[0]) * factor))
+        except (KeyError, ValueError, IndexError):
+            continue
+    return None
+
+
+def area_features(tags, ring):
+    """Features of a closed way or of an outer ring of a multipolygon."""
+    name = tags.get("name")
+    if tags.get("natural") == "water" or tags.get("waterway") == "riverbank":
+        water_class = "river" if tags.get("water") in ("river", "canal") or "waterway" in tags else "lake"
+        return [feature("water", "polygon", ring, 6, **{"class": water_class})]
+    if tags.get("landuse") in ("reservoir", "basin"):
+        return [feature("water", "polygon", ring, 6, **{"class": "lake"})]
+    if tags.get("leisure") == "park":
+        return [feature("park", "polygon", ring, 10, **{"class": "park", "name": name})]
+    if "building" in tags and tags["building"] != "no":
+        return [feature("building", "polygon", ring, 13, render_height=render_height(tags))]
+    for (key, value), landcover_class in LANDCOVER.items():
+        if tags.get(key) == value:
+            return [feature("landcover", "polygon", ring, 10, **{"class": landcover_class, "subclass": value})]
+    if tags.get("landuse") in LANDUSE:
+        return [feature("landuse", "polygon", ring, 10, **{"class": tags["landuse"]})]
+    return []
+
+
+def line_features(tags, line):
+    name = tags.get("name")
+    if tags.get("highway") in HIGHWAYS:
+        highway_class, min_zoom = HIGHWAYS[tags["highway"]]
+        brunnel = "bridge" if tags.get("bridge") == "yes" else "tunnel" if tags.get("tunnel") == "yes" else None
+        features = [feature("transportation", "line", line, min_zoom, **{"class": highway_class, "brunnel": brunnel})]
+        if name:
+            features.append(
+                feature("transportation_name", "line", line, max(min_zoom, 12), **{"class": highway_class, "name": name, "ref": tags.get("ref")})
+            )
+        return features
+    if tags.get("railway") == "rail":
+        return [feature("transportation", "line", line, 10, **{"class": "rail"})]
+    if tags.get("waterway") in WATERWAYS:
+        return [feature("waterway", "line", line, WATERWAYS[tags["waterway"]], **{"class": tags["waterway"], "name": name})]
+    return []
+
+
+def point_features(tags, point):
+    name = tags.get("name")
+    if tags.get("place") in PLACES and name:
+        return [feature("place", "point", [point], PLACES[tags["place"]], **{"class": tags["place"], "name": name})]
+    for key in POI_KEYS:
+        if key in tags and name:
+            return [feature("poi", "point", [point], 14, **{"class": tags[key], "subclass": tags[key], "name": name})]
+    return []
+
+
+def read_extract(path):
+    """Reads an OSM XML file. Returns the features and the bounds of the extract."""
+    nodes, ways, features = {}, {}, []
+    bounds = None
+    for _, element in ElementTree.iterparse(path):
+        tags = {tag.get("k"): tag.get("v") for tag in element.findall("tag")}
+        if element.tag == "bounds":
+            bounds = tuple(float(element.get(key)) for key in ("minlon", "minlat", "maxlon", "maxlat"))
+        elif element.tag == "node":
+            point = (float(element.get("lon")), float(element.get("lat")))
+            nodes[element.get("id")] = point
+            features += point_features(tags, point)
+        elif element.tag == "way":
+            refs = [nd.get("ref") for nd in element.findall("nd")]
+            points = [nodes[ref] for ref in refs if ref in nodes]
+            ways[element.get("id")] = points
+            if len(points) < 2:
+                continue
+            if len(points) >= 4 and points[0] == points[-1]:
+                features += area_features(tags, points[:-1])
+            features += line_features(tags, points)
+        elif element.tag == "relation" and tags.get("type") == "multipolygon":
+            for member in element.findall("member"):
+                ring = ways.get(member.get("ref"), [])
+                if member.get("type") == "way" and member.get("role") == "outer" and len(ring) >= 4 and ring[0] == ring[-1]:
+                    features += area_features(tags, ring[:-1])
+        if element.tag in ("node", "way", "relation"):
+            element.clear()
+
+    if bounds is None:
+        lons = [lon for lon, _ in nodes.values()]
+        lats = [lat for _, lat in nodes.values()]
+        bounds = (min(lons), min(lats), max(lons), max(lats))
+    return features, bounds
+
+
+# --- Vector tile encoding ---------------------------------------------------------------------
+
+
+def varint(value):
+    out = bytearray()
+    while value >= 0x80:
+        out.append((value & 0x7F) | 0x80)
+        value >>= 7
+    out.append(value)
+    return bytes(out)
+
+
+def field(number, wire_type, payload):
+    key = varint((number << 3) | wire_type)
+    if wire_type == 2:
+        return key + varint(len(payload)) + payload
+    return key + payload
+
+
+def packed(number, values):
+    return field(number, 2, b"".join(varint(v) for v in values))
+
+
+def zigzag(value):
+    return (value << 1) ^ (value >> 31)
+
+
+def command(command_id, count):
+    return (command_id & 0x7) | (count << 3)
+
+
+def project(lon, lat, z, x, y):
+    """Converts degrees to the coordinates of the tile (z, x, y)."""
+    n = 2**z
+    world_x = (lon + 180.0) / 360.0 * n
+    lat_rad = math.radians(lat)
+    world_y = (1.0 - math.asinh(math.tan(lat_rad)) / math.pi) / 2.0 * n
+    return round((world_x - x) * EXTENT), round((world_y - y) * EXTENT)
+
+
+def encode_geometry(geometry_type, points):
+    commands = []
+    cursor = (0, 0)
+
+    def move(point):
+        nonlocal cursor
+        dx, dy = point[0] - cursor[0], point[1] - cursor[1]
+        cursor = point
+        return [zigzag(dx), zigzag(dy)]
+
+    if geometry_type == "point":
+        commands.append(command(1, len(points)))
+        for point in points:
+            commands += move(point)
+        return commands
+
+    if geometry_type == "polygon":
+        # Exterior rings have a positive area in the tile coordinates
+        area = sum(a[0] * b[1] - b[0] * a[1] for a, b in zip(points, points[1:] + points[:1]))
+        if area < 0:
+            points = list(reversed(points))
+
+    commands.append(command(1, 1))
+    commands += move(points[0])
+    commands.append(command(2, len(points) - 1))
+    for point in points[1:]:
+        commands += move(point)
+    if geometry_type == "polygon":
+        commands.append(command(7, 1))
+    return commands
+
+
+def encode_value(value):
+    if isinstance(value, str):
+        return field(1, 2, value.encode())
+    return field(4, 0, varint(value))
+
+
+def encode_layer(name, features):
+    keys, values, encoded = [], [], []
+    for geometry_type, points, properties in features:
+        tags = []
+        for key, value in properties.items():
+            if key not in keys:
+                keys.append(key)
+            if value not in values:
+                values.append(value)
+            tags += [keys.index(key), values.index(value)]
+        type_id = {"point": 1, "line": 2, "polygon": 3}[geometry_type]
+        body = packed(2, tags) + field(3, 0, varint(type_id)) + packed(4, encode_geometry(geometry_type, points))
+        encoded.append(field(2, 2, body))
+
+    layer = field(15, 0, varint(2)) + field(1, 2, name.encode())
+    layer += b"".join(encoded)
+    layer += b"".join(field(3, 2, key.encode()) for key in keys)
+    layer += b"".join(field(4, 2, encode_value(value)) for value in values)
+    layer += field(5, 0, varint(EXTENT))
+    return field(3, 2, layer)
+
+
+def tile_bounds(z, x, y):
+    """Returns the bounds of the tile in degrees as (west, south, east, north)."""
+    n = 2**z
+
+    def lat(tile_y):
+        return math.degrees(math.atan(math.sinh(math.pi * (1 - 2 * tile_y / n))))
+
+    return x / n * 360.0 - 180.0, lat(y + 1), (x + 1) / n * 360.0 - 180.0, lat(y)
+
+
+def encode_tile(features, z, x, y):
+    west, south, east, north = tile_bounds(z, x, y)
+    layers = {}
+    for f in features:
+        if z < f["min_zoom"]:
+            continue
+        f_west, f_south, f_east, f_north = f["bbox"]
+        if f_east < west or f_west > east or f_north < south or f_south > north:
+            continue
+        points = []
+        for lon, lat in f["coordinates"]:
+            point = project(lon, lat, z, x, y)
+            if not points or points[-1] != point:
+                points.append(point)
+        if len(points) < {"point": 1, "line": 2, "polygon": 3}[f["type"]]:
+            continue
+        layers.setdefault(f["layer"], []).append((f["type"], points, f["properties"]))
+
+    return b"".join(encode_layer(name, layer_features) for name, layer_features in sorted(layers.items()))
+
+
+def tile_range(z, bounds):
+    n = 2**z
+    west, south, east, north = bounds
+    x0 = int((west + 180.0) / 360.0 * n)
+    x1 = min(int((east + 180.0) / 360.0 * n), n - 1)
+    y0 = int((1.0 - math.asinh(math.tan(math.radians(north))) / math.pi) / 2.0 * n)
+    y1 = min(int((1.0 - math.asinh(math.tan(math.radians(south))) / math.pi) / 2.0 * n), n - 1)
+    for x in range(x0, x1 + 1):
+        for y in range(y0, y1 + 1):
+            yield x, y
+
+
+# --- PMTiles archive --------------------------------------------------------------------------
+
+
+def rotate(n, x, y, rx, ry):
+    if ry == 0:
+        if rx == 1:
+            x, y = n - 1 - x, n - 1 - y
+        x, y = y, x
+    return x, y
+
+
+def tile_id(z, x, y):
+    n = 2**z
+    d = 0
+    s = n // 2
+    while s > 0:
+        rx = 1 if x & s else 0
+        ry = 1 if y & s else 0
+        d += s * s * ((3 * rx) ^ ry)
+        x, y = rotate(n, x, y, rx, ry)
+        s //= 2
+    return (4**z - 1) // 3 + d
+
+
+def encode_directory(entries):
+    out = varint(len(entries))
+    last_id = 0
+    for entry_id, _, _ in entries:
+        out += varint(entry_id - last_id)
+        last_id = entry_id
+    out += b"".join(varint(1) for _ in entries)
+    out += b"".join(varint(length) for _, _, length in entries)
+    for i, (_, offset, _) in enumerate(entries):
+        previous = entries[i - 1] if i > 0 else None
+        if previous and offset == previous[1] + previous[2]:
+            out += varint(0)
+        else:
+            out += varint(offset + 1)
+    return out
+
+
+def compress(data):
+    return gzip.compress(data, mtime=0)
+
+
+def main():
+    if len(sys.argv) != 2:
+        sys.exit(__doc__)
+    features, bounds = read_extract(sys.argv[1])
+    west, south, east, north = bounds
+    center = ((west + east) / 2, (south + north) / 2)
+
+    tiles = []
+    for z in range(MIN_ZOOM, MAX_ZOOM + 1):
+        for x, y in tile_range(z, bounds):
+            data = encode_tile(features, z, x, y)
+            if data:
+                tiles.append((tile_id(z, x, y), compress(data)))
+    tiles.sort()
+
+    entries, data, offset = [], b"", 0
+    for entry_id, tile in tiles:
+        entries.append((entry_id, offset, len(tile)))
+        data += tile
+        offset += len(tile)
+
+    layer_fields = {}
+    for f in features:
+        layer_fields.setdefault(f["layer"], {}).update(
+            {key: "Number" if isinstance(value, int) else "String" for key, value in f["properties"].items()}
+        )
+    metadata = {
+        "name": NAME,
+        "description": "OpenStreetMap extract for trying out styles without a tile server",
+        "attribution": ATTRIBUTION,
+        "vector_layers": [{"id": name, "fields": fields} for name, fields in sorted(layer_fields.items())],
+    }
+
+    root = compress(encode_directory(entries))
+    metadata = compress(json.dumps(metadata).encode())
+    root_offset = 127
+    metadata_offset = root_offset + len(root)
+    data_offset = metadata_offset + len(metadata)
+
+    def e7(value):
+        return round(value * 1e7)
+
+    header = b"PMTiles" + bytes([3])
+    header += struct.pack(
+        "<QQQQQQQQQQQ",
+        root_offset,
+        len(root),
+        metadata_offset,
+        len(metadata),
+        data_offset,
+        0,
+        data_offset,
+        len(data),
+        len(tiles),
+        len(tiles),
+        len(tiles),
+    )
+    header += bytes([1, 2, 2, 1, MIN_ZOOM, MAX_ZOOM])
+    header += struct.pack("<iiii", e7(west), e7(south), e7(east), e7(north))
+    header += bytes([13])
+    header += struct.pack("<ii", e7(center[0]), e7(center[1]))
+    assert len(header) == 127
+
+    with open(OUTPUT, "wb") as file:
+        file.write(header + root + metadata + data)
+    print(f"Wrote {len(tiles)} tiles, {data_offset + len(data)} bytes to {os.path.normpath(OUTPUT)}")
+
+
+if __name__ == "__main__":
+    main()
@@ -1,32 +1,112 @@
-use std::sync::Arc;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
 
-use eframe::Frame;
+#[cfg(not(target_arch = "wasm32"))]
+use cache_window::{CacheAction, CacheSettings, CacheWindow, MAX_PREFETCH_TILES};
+use catalog::TileCatalog;
+use compare::{Compare, CompareMap};
+use coverage::UnstyledReport;
+use eframe::{egui_wgpu::RenderState, Frame};
 use galileo::{
-    Lod, Map, MapView, TileSchema, control::{EventPropagation, MouseButton, UserEvent, UserEventHandler}, layer::{
-        VectorTileLayer, vector_tile_layer::{VectorTileLayerBuilder, style::VectorTileStyle}
-    }, render::text::{RustybuzzRasterizer, text_service::TextService}, tile_schema::{TileIndex, VerticalDirection}
-};
-use galileo_egui::{EguiMap, EguiMapState};
-use galileo_types::{
-    cartesian::{Point2, Rect},
-    geo::Crs,
-    latlon,
+    control::{EventPropagation, MouseButton, UserEvent, UserEventHandler},
+    layer::{
+        vector_tile_layer::{style::VectorTileStyle, VectorTileLayerBuilder},
+        Layer, VectorTileLayer,
+    },
+    render::text::{text_service::TextService, RustybuzzRasterizer},
+    Map, MapView, Messenger, TileSchema,
 };
+use galileo_egui::EguiMapState;
+use galileo_types::{cartesian::Point2, latlon};
+use highlight::Highlighter;
+use inspector::{FeatureInspector, InspectedFeature, InspectorAction};
 use parking_lot::RwLock;
 use serde::{Deserialize, Serialize};
+use sources::{SourceSettings, TileSourceProfile, TileSourceWindow};
+use stats::{FeatureCounter, StatsUpdater};
+use status_bar::{MapStatus, StatusBar};
 use style::StyleWindow;
+use tile_debug::TileDebugOverlay;
+use tiles::{TileLoader, TileSetInfo, TileSource, TileStore};
+use viewport::{Bookmark, BookmarksWindow, Viewport};
 
+#[cfg(not(target_arch = "wasm32"))]
+mod cache_window;
+mod catalog;
+mod compare;
+mod coverage;
+mod highlight;
+mod inspector;
+mod sources;
+mod stats;
+mod status_bar;
 mod style;
+mod tile_debug;
+mod tiles;
+mod viewport;
+mod xray;
 
 pub struct GalileoApp {
     map_state: EguiMapState,
     vt_layer: Arc<RwLock<VectorTileLayer>>,
     style_window: StyleWindow,
+    inspector: FeatureInspector,
+    clicked_features: Arc<RwLock<Option<Vec<InspectedFeature>>>>,
+    catalog: Arc<RwLock<TileCatalog>>,
+    show_catalog: bool,
+    tile_store: Arc<RwLock<TileStore>>,
+    unstyled: UnstyledReport,
+    xray: bool,
+    /// Number of catalog layers with features the current x-ray style was generated for.
+    xray_layer_count: usize,
+    stats_updater: StatsUpdater,
+    feature_counter: FeatureCounter,
+    highlighter: Highlighter,
+    tile_debug: TileDebugOverlay,
+    show_tile_debug: bool,
+    compare: Compare,
+    render_state: RenderState,
+    sources: SourceSettings,
+    source_window: TileSourceWindow,
+    /// Set when a layer created after the start of the app asks for the map to be redrawn.
+    redraw_requested: Arc<AtomicBool>,
+    tile_schema: TileSchema,
+    /// Source of the tiles of the layer.
+    tile_source: Arc<dyn TileSource>,
+    #[cfg(not(target_arch = "wasm32"))]
+    cache: CacheSettings,
+    #[cfg(not(target_arch = "wasm32"))]
+    cache_window: CacheWindow,
+    /// Tile source opened in the background, with the number of the request it was opened for.
+    opened_source: Arc<RwLock<Option<(u64, Result<Box<dyn TileSource>, String>)>>>,
+    source_request: u64,
+    /// Set while the map shows the viewport saved in the app state, which the first opened
+    /// tile source must not replace with its own view.
+    keep_view: bool,
+    bookmarks: Vec<Bookmark>,
+    bookmarks_window: BookmarksWindow,
+    status_bar: StatusBar,
+    /// Position of the pointer over the map in map coordinates.
+    cursor_position: Arc<RwLock<Option<Point2>>>,
+    /// Area of the screen taken by the map.
+    map_rect: egui::Rect,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct AppState {
     style_window: StyleWindow,
+    #[serde(default)]
+    sources: SourceSettings,
+    #[cfg(not(target_arch = "wasm32"))]
+    #[serde(default)]
+    cache: CacheSettings,
+    /// Viewport of the map when the app was closed.
+    #[serde(default)]
+    viewport: Option<Viewport>,
+    #[serde(default = "viewport::default_bookmarks")]
+    bookmarks: Vec<Bookmark>,
 }
 
 impl GalileoApp {
@@ -41,39 +121,60 @@ impl GalileoApp {
         let state: Option<AppState> = cc
             .storage
             .and_then(|storage| eframe::get_value(storage, eframe::APP_KEY));
-        let style_window = match state {
-            Some(v) => v.style_window,
-            None => StyleWindow::new(get_layer_style().unwrap_or_default()),
+        #[cfg(not(target_arch = "wasm32"))]
+        let cache = state
+            .as_ref()
+            .map(|state| state.cache.clone())
+            .unwrap_or_default();
+        let viewport = state.as_ref().and_then(|state| state.viewport);
+        let bookmarks = state
+            .as_ref()
+            .map_or_else(viewport::default_bookmarks, |state| state.bookmarks.clone());
+        let (style_window, sources) = match state {
+            Some(v) => (v.style_window, v.sources.validated()),
+            None => (
+                StyleWindow::new(get_layer_style().unwrap_or_default()),
+                SourceSettings::default(),
+            ),
         };
 
-        let map_view = MapView::new(&latlon!(55.0, 37.0), 20_000.0);
-
-        let Ok(api_key) = std::env::var("VT_API_KEY") else {
-            panic!("MapTiler VT_API_KEY environment variable not set");
+        let map_view = match viewport {
+            Some(viewport) => viewport.to_view(),
+            None => MapView::new(&latlon!(55.0, 37.0), 20_000.0),
         };
 
-        let layer = VectorTileLayerBuilder::new_rest(move |&index: &TileIndex| {
-            format!(
-                //"https://api.maptiler.com/tiles/v3-openmaptiles/{z}/{x}/{y}.pbf?key={api_key}",
-                "https://api.maptiler.com/tiles/v3/{z}/{x}/{y}.pbf?key={api_key}",
-                z = index.z,
-                x = index.x,
-                y = index.y
-            )
-        })
-        .with_style(style_window.style())
-        .with_tile_schema(tile_scheme())
-        .with_file_cache_checked(".tile_cache")
-        .with_attribution(
-            "© MapTiler© OpenStreetMap contributors".to_string(),
-            "https://www.maptiler.com/copyright/".to_string(),
-        )
-        .build()
-        .expect("failed to create layer");
+        // The tile source is opened in the background, the map stays empty until then
+        let tile_schema = sources.active().tile_schema(&TileSetInfo::default());
+        let catalog = Arc::new(RwLock::new(TileCatalog::default()));
+        let tile_store = Arc::new(RwLock::new(TileStore::default()));
+        let tile_source: Arc<dyn TileSource> = Arc::new(tiles::EmptySource);
+        let layer = create_layer(
+            tile_source.clone(),
+            sources.active(),
+            style_window.style(),
+            catalog.clone(),
+            tile_store.clone(),
+            tile_schema.clone(),
+        );
 
         let layer = Arc::new(RwLock::new(layer));
         let layer_copy = layer.clone();
-        let map = Map::new(map_view, vec![Box::new(layer.clone())], None);
+        let highlighter = Highlighter::default();
+        let tile_debug = TileDebugOverlay::default();
+        let map = Map::new(
+            map_view,
+            vec![
+                Box::new(layer.clone()),
+                Box::new(highlighter.layer()),
+                Box::new(tile_debug.layer()),
+            ],
+            None,
+        );
+
+        let clicked_features = Arc::new(RwLock::new(None));
+        let clicked_features_copy = clicked_features.clone();
+        let cursor_position = Arc::new(RwLock::new(None));
+        let cursor_position_copy = cursor_position.clone();
 
         let handler = move |ev: &UserEvent, map: &mut Map| match ev {
             UserEvent::Click(MouseButton::Left, mouse_event) => {
@@ -82,16 +183,24 @@ impl GalileoApp {
                     .view()
                     .screen_to_map(mouse_event.screen_pointer_position)
                 {
-                    let features = layer_copy.read().get_features_at(&position, &view);
+                    let features = layer_copy
+                        .read()
+                        .get_features_at(&position, &view)
+                        .into_iter()
+                        .map(|(layer, feature)| InspectedFeature::new(layer, feature))
+                        .collect();
 
-                    println!("Clicked at {} objects:", features.len());
-                    for (layer, feature) in features {
-                        println!("{layer}, {:?}", feature.properties);
-                    }
+                    *clicked_features_copy.write() = Some(features);
                 }
 
                 EventPropagation::Stop
             }
+            UserEvent::PointerMoved(mouse_event) => {
+                *cursor_position_copy.write() = map
+                    .view()
+                    .screen_to_map(mouse_event.screen_pointer_position);
+                EventPropagation::Propagate
+            }
             _ => EventPropagation::Propagate,
         };
 
@@ -102,54 +211,372 @@ impl GalileoApp {
             .expect("failed to get wgpu context");
 
         let handler: Box<dyn UserEventHandler> = Box::new(handler);
-        GalileoApp {
+        let mut app = GalileoApp {
             map_state: EguiMapState::new(
                 map,
                 ctx,
-                render_state,
+                render_state.clone(),
                 [handler],
                 galileo_egui::EguiMapOptions::default(),
             ),
             vt_layer: layer,
             style_window,
+            inspector: FeatureInspector::default(),
+            clicked_features,
+            catalog,
+            show_catalog: false,
+            tile_store,
+            unstyled: UnstyledReport::default(),
+            xray: false,
+            xray_layer_count: 0,
+            stats_updater: StatsUpdater::default(),
+            feature_counter: FeatureCounter::default(),
+            highlighter,
+            tile_debug,
+            show_tile_debug: false,
+            compare: Compare::default(),
+            render_state,
+            sources,
+            source_window: TileSourceWindow::default(),
+            redraw_requested: Arc::new(AtomicBool::new(false)),
+            tile_schema,
+            tile_source,
+            #[cfg(not(target_arch = "wasm32"))]
+            cache,
+            #[cfg(not(target_arch = "wasm32"))]
+            cache_window: CacheWindow::default(),
+            opened_source: Arc::new(RwLock::new(None)),
+            source_request: 0,
+            keep_view: viewport.is_some(),
+            bookmarks,
+            bookmarks_window: BookmarksWindow::default(),
+            status_bar: StatusBar::default(),
+            cursor_position,
+            map_rect: egui::Rect::NOTHING,
+        };
+        app.open_tile_source(&cc.egui_ctx);
+
+        app
+    }
+
+    /// Starts opening the active tile source. The map switches to the source once it is open.
+    fn open_tile_source(&mut self, ctx: &egui::Context) {
+        self.source_request += 1;
+        let request = self.source_request;
+        let profile = self.sources.active().clone();
+        let offline = self.sources.offline;
+        let opened_source = self.opened_source.clone();
+        let ctx = ctx.clone();
+        spawn(async move {
+            let result = profile.open_source(offline).await;
+            *opened_source.write() = Some((request, result));
+            ctx.request_repaint();
+        });
+    }
+
+    /// Replaces the tile layer with one loading tiles from the active source. The catalog and
+    /// the decoded tiles of the previous source are dropped, and the map is moved to the default
+    /// view of the new tile set if it has one.
+    fn switch_tile_source(
+        &mut self,
+        source: Result<Box<dyn TileSource>, String>,
+        ctx: &egui::Context,
+    ) {
+        let source: Arc<dyn TileSource> = match source {
+            Ok(source) => {
+                self.source_window.set_source_error(None);
+                Arc::from(source)
+            }
+            Err(err) => {
+                log::error!(
+                    "Failed to open tile source \"{}\": {err}",
+                    self.sources.active().name
+                );
+                self.source_window.set_source_error(Some(err));
+                Arc::new(tiles::EmptySource)
+            }
+        };
+        self.tile_source = source.clone();
+        let info = source.info();
+        self.tile_schema = self.sources.active().tile_schema(&info);
+        let keep_view = std::mem::take(&mut self.keep_view);
+        if let Some(view) = initial_view(&info).filter(|_| !keep_view) {
+            self.map_state.map_mut().set_view(view);
+        }
+
+        let mut catalog = TileCatalog::default();
+        catalog.add_vector_layers(&info.vector_layers);
+        self.catalog = Arc::new(RwLock::new(catalog));
+        self.tile_store = Arc::new(RwLock::new(TileStore::default()));
+        let mut layer = create_layer(
+            source,
+            self.sources.active(),
+            self.style_window.layer_style(),
+            self.catalog.clone(),
+            self.tile_store.clone(),
+            self.tile_schema.clone(),
+        );
+        // The map only gives its messenger to the layers it was created with
+        layer.set_messenger(Box::new(RedrawMessenger {
+            ctx: ctx.clone(),
+            requested: self.redraw_requested.clone(),
+        }));
+        *self.vt_layer.write() = layer;
+
+        if let Some(style) = self.compare.map_style().cloned() {
+            let mut layer = self.create_compare_layer(style);
+            layer.set_messenger(Box::new(RedrawMessenger {
+                ctx: ctx.clone(),
+                requested: self.redraw_requested.clone(),
+            }));
+            if let Some(map) = self.compare.map_mut() {
+                map.set_layer(layer);
+            }
+        }
+
+        self.update_layer_style();
+        self.stats_updater.style_changed();
+        self.unstyled.request_update();
+    }
+
+    /// Creates the map drawing the compared style, or updates its style, when another style is
+    /// chosen to compare with.
+    fn update_compare_map(&mut self, ctx: &egui::Context) {
+        let Some(style) = self.compare.take_changed_style() else {
+            return;
+        };
+        if let Some(map) = self.compare.map_mut() {
+            map.set_style(style);
+            return;
+        }
+
+        let layer = self.create_compare_layer(style);
+        let view = self.map_state.map().view().clone();
+        self.compare.set_map(CompareMap::new(
+            layer,
+            view,
+            ctx.clone(),
+            self.render_state.clone(),
+        ));
+    }
+
+    /// Creates a layer drawing the tiles of the current source with the style. It has its own
+    /// catalog and tile store, so only the tiles of the main map are analyzed.
+    fn create_compare_layer(&self, style: VectorTileStyle) -> VectorTileLayer {
+        create_layer(
+            self.tile_source.clone(),
+            self.sources.active(),
+            style,
+            Arc::new(RwLock::new(TileCatalog::default())),
+            Arc::new(RwLock::new(TileStore::default())),
+            self.tile_schema.clone(),
+        )
+    }
+
+    /// Sets the style of the map layer: the edited style (with only the soloed rules in the solo
+    /// mode), or the generated x-ray style while the x-ray mode is on.
+    fn update_layer_style(&mut self) {
+        let style = if self.xray {
+            let catalog = self.catalog.read();
+            self.xray_layer_count = catalog.layers_with_features();
+            xray::xray_style(&catalog)
+        } else {
+            self.style_window.layer_style()
+        };
+
+        self.vt_layer.write().update_style(style);
+        self.map_state.request_redraw();
+    }
+
+    fn handle_inspector_action(&mut self, action: InspectorAction, ctx: &egui::Context) {
+        match action {
+            InspectorAction::RevealRule(rule_id) => {
+                self.style_window.reveal_rule(rule_id);
+            }
+            InspectorAction::CreateRule {
+                layer,
+                geometry_type,
+                filter,
+                before_rule,
+            } => {
+                self.style_window.add_feature_rule(
+                    &layer,
+                    geometry_type,
+                    &filter,
+                    before_rule,
+                    ctx,
+                );
+            }
+        }
+    }
+
+    /// Recalculates the number of features drawn with each rule in the background when the
+    /// view, the style or the loaded tiles change, and shows the counts once they are ready.
+    fn update_feature_counts(&mut self, ctx: &egui::Context) {
+        if let Some(counts) = self.feature_counter.take_counts() {
+            self.style_window.set_feature_counts(counts);
+        }
+
+        let view = self.map_state.map().view().clone();
+        let tile_generation = self.tile_store.read().generation();
+        if !self.stats_updater.needs_update(&view, tile_generation, ctx) {
+            return;
+        }
+
+        self.feature_counter.start(
+            &self.tile_store.read(),
+            &view,
+            &self.tile_schema,
+            self.style_window.rule_matcher(),
+            ctx,
+        );
+        self.unstyled.request_update();
+    }
+
+    /// Outlines the features of the selected rules and of the rule under the mouse cursor.
+    fn update_highlight(&mut self) {
+        let rule_ids = self.style_window.highlighted_rule_ids();
+        let matcher = self.style_window.matcher_for(&rule_ids);
+        let changed = self.highlighter.update(
+            rule_ids,
+            matcher,
+            &self.tile_store.read(),
+            self.map_state.map().view(),
+            &self.tile_schema,
+        );
+        if changed {
+            self.map_state.request_redraw();
+        }
+    }
+
+    /// Outlines the tiles in the view while the tile debug overlay is on.
+    fn update_tile_debug(&mut self) {
+        let changed = self.tile_debug.update(
+            self.show_tile_debug,
+            &self.tile_store.read(),
+            self.map_state.map().view(),
+            &self.tile_schema,
+        );
+        if changed {
+            self.map_state.request_redraw();
         }
     }
 
+    fn update_unstyled_report(&mut self) {
+        let tiles = tiles::visible_tiles(self.map_state.map().view(), &self.tile_schema);
+        self.unstyled.update(
+            self.tile_store.read().features(&tiles),
+            &self.style_window.rule_matcher(),
+        );
+    }
+
     fn state(&self) -> AppState {
         AppState {
             style_window: self.style_window.clone(),
+            sources: self.sources.clone(),
+            #[cfg(not(target_arch = "wasm32"))]
+            cache: self.cache.clone(),
+            viewport: Viewport::from_view(self.map_state.map().view()),
+            bookmarks: self.bookmarks.clone(),
+        }
+    }
+
+    /// Shows the cache window and keeps the cache within its size limit.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn update_cache_window(&mut self, ctx: &egui::Context) {
+        self.cache_window.maintain(ctx, &self.cache);
+
+        let profile = self.sources.active();
+        let caches_tiles = profile.kind == sources::SourceKind::Rest;
+        let can_prefetch = if !caches_tiles {
+            Err("Only the tiles of tile servers are cached")
+        } else if self.sources.offline {
+            Err("Tiles are not loaded in the offline mode")
+        } else {
+            Ok(())
+        };
+        let active_cache = caches_tiles.then(|| profile.cache_dir_name());
+        let action =
+            self.cache_window
+                .show(ctx, &mut self.cache, active_cache.as_deref(), can_prefetch);
+
+        if let Some(CacheAction::Prefetch(max_zoom)) = action {
+            let view = self.map_state.map().view();
+            match tiles::area_tiles(view, &self.tile_schema, max_zoom, MAX_PREFETCH_TILES) {
+                Some(indices) => {
+                    self.cache_window
+                        .start_prefetch(ctx, self.tile_source.clone(), indices)
+                }
+                None => self.cache_window.set_prefetch_error(format!(
+                    "The view has more than {MAX_PREFETCH_TILES} tiles up to zoom {max_zoom}, \
+                     zoom in or choose a lower zoom level"
+                )),
+            }
         }
     }
 }
 
+/// Passes the redraw requests of a layer to the app.
+struct RedrawMessenger {
+    ctx: egui::Context,
+    requested: Arc<AtomicBool>,
+}
+
+impl Messenger for RedrawMessenger {
+    fn request_redraw(&self) {
+        self.requested.store(true, Ordering::Relaxed);
+        self.ctx.request_repaint();
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn spawn(future: impl std::future::Future<Output = ()> + Send + 'static) {
+    tokio::spawn(future);
+}
+
+#[cfg(target_arch = "wasm32")]
+fn spawn(future: impl std::future::Future<Output = ()> + 'static) {
+    wasm_bindgen_futures::spawn_local(future);
+}
+
+fn create_layer(
+    source: Arc<dyn TileSource>,
+    profile: &TileSourceProfile,
+    style: VectorTileStyle,
+    catalog: Arc<RwLock<TileCatalog>>,
+    tile_store: Arc<RwLock<TileStore>>,
+    tile_schema: TileSchema,
+) -> VectorTileLayer {
+    // Tile sets can name their own attribution, which is used if the profile has none
+    let attribution = if profile.attribution.is_empty() {
+        source.info().attribution.unwrap_or_default()
+    } else {
+        profile.attribution.clone()
+    };
+    let loader = TileLoader::new(source, catalog, tile_store);
+    VectorTileLayerBuilder::new_with_loader(loader)
+        .with_style(style)
+        .with_tile_schema(tile_schema)
+        .with_attribution(attribution, profile.attribution_url.clone())
+        .build()
+        .expect("failed to create layer")
+}
+
 fn get_layer_style() -> Option<VectorTileStyle> {
     const STYLE: &str = "../galileo/galileo/examples/data/vt_style.json";
     serde_json::from_reader(std::fs::File::open(STYLE).ok()?).ok()
 }
 
-pub fn tile_scheme() -> TileSchema {
-    const ORIGIN: Point2 = Point2::new(-20037508.342787, 20037508.342787);
-    const TOP_RESOLUTION: f64 = 156543.03392800014 / 4.0;
-
-    let mut lods = vec![Lod::new(TOP_RESOLUTION, 0).unwrap()];
-    for i in 1..16 {
-        lods.push(Lod::new(lods[(i - 1) as usize].resolution() / 2.0, i).unwrap());
-    }
-
-    TileSchema {
-        origin: ORIGIN,
-        bounds: Rect::new(
-            -20037508.342787,
-            -20037508.342787,
-            20037508.342787,
-            20037508.342787,
-        ),
-        lods: lods.into_iter().collect(),
-        tile_width: 1024,
-        tile_height: 1024,
-        y_direction: VerticalDirection::TopToBottom,
-        crs: Crs::EPSG3857,
-    }
+/// Returns the default view of a tile set: its center or the center of its bounds.
+fn initial_view(info: &TileSetInfo) -> Option<MapView> {
+    let [lon, lat, zoom] = info.center.or_else(|| {
+        let [west, south, east, north] = info.bounds?;
+        let zoom = info.min_zoom.unwrap_or(0) as f64;
+        Some([(west + east) / 2.0, (south + north) / 2.0, zoom])
+    })?;
+
+    // Tile set zoom levels count 256 pixel tiles, which is one level above the style zoom
+    Some(Viewport::at(lat, lon, zoom - 1.0).to_view())
 }
 
 impl eframe::App for GalileoApp {
@@ -160,6 +587,9 @@ impl eframe::App for GalileoApp {
 
     /// Called each time the UI needs repainting, which may be many times per second.
     fn update(&mut self, ctx: &egui::Context, _frame: &mut Frame) {
+        let mut xray_toggled = false;
+        let visible_tiles = tiles::visible_tiles(self.map_state.map().view(), &self.tile_schema);
+        let missing_tiles = self.tile_store.read().missing_count(&visible_tiles);
         egui::TopBottomPanel::top("top_panel").show(ctx, |ui| {
             egui::MenuBar::new().ui(ui, |ui| {
                 let is_web = cfg!(target_arch = "wasm32");
@@ -172,24 +602,152 @@ impl eframe::App for GalileoApp {
                     ui.add_space(16.0);
                 }
 
+                ui.menu_button("View", |ui| {
+                    ui.checkbox(&mut self.source_window.open, "Tile sources");
+                    ui.checkbox(&mut self.bookmarks_window.open, "Bookmarks");
+                    #[cfg(not(target_arch = "wasm32"))]
+                    ui.checkbox(&mut self.cache_window.open, "Tile cache");
+                    ui.checkbox(&mut self.inspector.open, "Feature inspector");
+                    ui.checkbox(&mut self.compare.open, "Compare styles");
+                    ui.checkbox(&mut self.show_catalog, "Tile catalog");
+                    if ui
+                        .checkbox(&mut self.unstyled.open, "Unstyled features")
+                        .changed()
+                    {
+                        self.unstyled.request_update();
+                    }
+                    xray_toggled = ui
+                        .checkbox(&mut self.xray, "X-ray mode")
+                        .on_hover_text("Draw every source layer in its own color")
+                        .changed();
+                    ui.checkbox(&mut self.show_tile_debug, "Tile debug overlay")
+                        .on_hover_text("Outline the tiles with their indices and load state");
+                });
+                ui.add_space(16.0);
+
                 egui::widgets::global_theme_preference_buttons(ui);
+
+                if self.sources.offline {
+                    ui.add_space(16.0);
+                    ui.colored_label(ui.visuals().warn_fg_color, "Offline");
+                }
+                if missing_tiles > 0 {
+                    ui.add_space(16.0);
+                    ui.colored_label(
+                        ui.visuals().warn_fg_color,
+                        format!("{missing_tiles} tiles missing in the view"),
+                    )
+                    .on_hover_text("The tile source does not have these tiles");
+                }
             });
         });
 
-        egui::CentralPanel::default().show(ctx, |ui| {
-            EguiMap::new(&mut self.map_state).show_ui(ui);
+        let view = self.map_state.map().view();
+        let map_hovered = ctx
+            .pointer_hover_pos()
+            .is_some_and(|pos| self.map_rect.contains(pos));
+        let status = MapStatus {
+            resolution: view.resolution(),
+            tile_zoom: visible_tiles.first().map(|index| index.z),
+            cursor: (*self.cursor_position.read()).filter(|_| map_hovered),
+            center_lat: Viewport::from_view(view).map(|viewport| viewport.lat),
+        };
+        if let Some(go_to) = self.status_bar.show(ctx, status) {
+            let resolution = go_to
+                .zoom
+                .map(viewport::zoom_resolution)
+                .unwrap_or(view.resolution());
+            let view = MapView::new(&latlon!(go_to.lat, go_to.lon), resolution);
+            self.map_state.map_mut().set_view(view);
+        }
+
+        if self.source_window.show(ctx, &mut self.sources) {
+            self.open_tile_source(ctx);
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        self.update_cache_window(ctx);
+
+        let current_viewport = Viewport::from_view(self.map_state.map().view());
+        let bookmark = self
+            .bookmarks_window
+            .show(ctx, &mut self.bookmarks, current_viewport);
+        if let Some(viewport) = bookmark {
+            self.map_state.map_mut().set_view(viewport.to_view());
+        }
+        let opened_source = self.opened_source.write().take();
+        if let Some((request, source)) = opened_source {
+            // Sources opened for earlier requests are dropped
+            if request == self.source_request {
+                self.switch_tile_source(source, ctx);
+            }
+        }
+        self.compare.show(ctx, &self.style_window);
+        self.update_compare_map(ctx);
+        if self.redraw_requested.swap(false, Ordering::Relaxed) {
+            self.map_state.request_redraw();
+            if let Some(map) = self.compare.map_mut() {
+                map.request_redraw();
+            }
+        }
+
+        if let Some(features) = self.clicked_features.write().take() {
+            self.inspector.set_features(features);
+        }
+        if let Some(action) = self.inspector.show(ctx, &self.style_window) {
+            self.handle_inspector_action(action, ctx);
+        }
+
+        self.update_feature_counts(ctx);
+        if self.unstyled.needs_update() {
+            self.update_unstyled_report();
+        }
+        if let Some(action) = self.unstyled.show(ctx) {
+            self.handle_inspector_action(action, ctx);
+        }
+
+        if self.xray {
+            egui::Window::new("X-ray layers")
+                .open(&mut self.xray)
+                .default_width(200.0)
+                .show(ctx, |ui| xray::legend_ui(ui, &self.catalog.read()));
+            xray_toggled |= !self.xray;
+        }
+        if xray_toggled
+            || (self.xray && self.catalog.read().layers_with_features() != self.xray_layer_count)
+        {
+            self.update_layer_style();
+        }
+
+        egui::Window::new("Tile catalog")
+            .open(&mut self.show_catalog)
+            .default_width(350.0)
+            .show(ctx, |ui| self.catalog.read().ui(ui));
+
+        let map_panel = egui::CentralPanel::default().show(ctx, |ui| {
+            self.compare.maps_ui(ui, &mut self.map_state);
 
             ui.with_layout(egui::Layout::bottom_up(egui::Align::RIGHT), |ui| {
                 egui::warn_if_debug_build(ui);
             });
 
-            if self.style_window.show(ctx).is_changed() {
-                self.vt_layer
-                    .write()
-                    .update_style(self.style_window.style().clone());
-                self.map_state.request_redraw();
+            if self
+                .style_window
+                .show(ctx, &self.catalog.read())
+                .is_changed()
+            {
                 self.style_window.mark_unchanged();
+                self.update_layer_style();
+                self.stats_updater.style_changed();
+            }
+            if let Some(view) = self.style_window.take_style_view() {
+                self.map_state
+                    .map_mut()
+                    .set_view(Viewport::from_style(&view).to_view());
             }
         });
+        self.map_rect = map_panel.response.rect;
+
+        self.update_highlight();
+        self.update_tile_debug();
     }
 }
@@ -0,0 +1,394 @@
+//! Window to inspect and manage the disk cache of the tiles.
+
+use std::{
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use egui::{Grid, RichText};
+use galileo::tile_schema::TileIndex;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+
+use super::tiles::{
+    clear_cache, evict_least_used, format_size, written_cache_bytes, CacheUsage, TileCount,
+    TileSource, TileSourceError, CACHE_DIR, MAX_ZOOM,
+};
+
+/// Maximum number of tiles loaded by one prefetch.
+pub const MAX_PREFETCH_TILES: usize = 50_000;
+/// Number of tiles the prefetch loads at the same time.
+const PREFETCH_TASKS: usize = 8;
+/// Number of bytes written to the cache after which its size is checked again.
+const SIZE_CHECK_INTERVAL: u64 = 16 * 1024 * 1024;
+
+/// Cache settings saved with the app state.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CacheSettings {
+    /// Size limit of the cache in megabytes. The least recently used tiles are removed when
+    /// the cache grows over it.
+    pub max_size_mb: Option<u64>,
+    /// Deepest zoom level loaded by the prefetch.
+    pub prefetch_zoom: u32,
+}
+
+impl Default for CacheSettings {
+    fn default() -> Self {
+        Self {
+            max_size_mb: None,
+            prefetch_zoom: 14,
+        }
+    }
+}
+
+/// Action requested in the cache window.
+pub enum CacheAction {
+    /// Load all tiles of the view up to the zoom level.
+    Prefetch(u32),
+}
+
+/// Progress of a prefetch.
+#[derive(Default)]
+struct Prefetch {
+    total: usize,
+    loaded: AtomicUsize,
+    failed: AtomicUsize,
+    cancelled: AtomicBool,
+    /// Number of tasks still loading tiles.
+    running: AtomicUsize,
+}
+
+impl Prefetch {
+    fn is_finished(&self) -> bool {
+        self.running.load(Ordering::Relaxed) == 0
+    }
+}
+
+#[derive(Default)]
+pub struct CacheWindow {
+    pub open: bool,
+    /// Result of the last scan of the cache directory.
+    usage: Arc<RwLock<Option<CacheUsage>>>,
+    /// Set while the cache directory is scanned or changed in the background.
+    busy: Arc<AtomicBool>,
+    prefetch: Option<Arc<Prefetch>>,
+    /// Whether the cache was scanned after the last prefetch finished.
+    prefetch_scanned: bool,
+    /// Value of [`written_cache_bytes`] when the size limit was last checked.
+    size_checked_at: Option<u64>,
+    /// Error shown instead of starting a prefetch.
+    prefetch_error: Option<String>,
+}
+
+impl CacheWindow {
+    /// Keeps the cache within its size limit and the shown usage up to date. Must be called
+    /// every frame, also when the window is closed.
+    pub fn maintain(&mut self, ctx: &egui::Context, settings: &CacheSettings) {
+        if let Some(prefetch) = &self.prefetch {
+            if prefetch.is_finished() && !self.prefetch_scanned && !self.is_busy() {
+                self.prefetch_scanned = true;
+                self.run_in_background(ctx, || {});
+            }
+        }
+
+        let Some(max_size_mb) = settings.max_size_mb else {
+            return;
+        };
+        let written = written_cache_bytes();
+        let check = self
+            .size_checked_at
+            .map_or(true, |checked| written - checked >= SIZE_CHECK_INTERVAL);
+        if check && !self.is_busy() {
+            self.size_checked_at = Some(written);
+            self.run_in_background(ctx, move || {
+                let removed = evict_least_used(Path::new(CACHE_DIR), max_size_mb * 1024 * 1024);
+                if removed.tiles > 0 {
+                    log::info!(
+                        "Removed {} least recently used tiles ({}) from the cache",
+                        removed.tiles,
+                        format_size(removed.bytes)
+                    );
+                }
+            });
+        }
+    }
+
+    /// Starts loading the tiles into the cache of the source.
+    pub fn start_prefetch(
+        &mut self,
+        ctx: &egui::Context,
+        source: Arc<dyn TileSource>,
+        tiles: Vec<TileIndex>,
+    ) {
+        if let Some(prefetch) = &self.prefetch {
+            prefetch.cancelled.store(true, Ordering::Relaxed);
+        }
+
+        let prefetch = Arc::new(Prefetch {
+            total: tiles.len(),
+            running: AtomicUsize::new(PREFETCH_TASKS),
+            ..Default::default()
+        });
+        let tiles = Arc::new(tiles);
+        let next = Arc::new(AtomicUsize::new(0));
+        for _ in 0..PREFETCH_TASKS {
+            let prefetch = prefetch.clone();
+            let tiles = tiles.clone();
+            let next = next.clone();
+            let source = source.clone();
+            let ctx = ctx.clone();
+            super::spawn(async move {
+                while !prefetch.cancelled.load(Ordering::Relaxed) {
+                    let Some(index) = tiles.get(next.fetch_add(1, Ordering::Relaxed)) else {
+                        break;
+                    };
+                    match source.load(*index).await {
+                        Ok(_) | Err(TileSourceError::NotFound) => {}
+                        Err(TileSourceError::Read(err)) => {
+                            log::warn!("Failed to prefetch tile {index:?}: {err}");
+                            prefetch.failed.fetch_add(1, Ordering::Relaxed);
+                        }
+                    }
+                    prefetch.loaded.fetch_add(1, Ordering::Relaxed);
+                }
+
+                prefetch.running.fetch_sub(1, Ordering::Relaxed);
+                ctx.request_repaint();
+            });
+        }
+
+        self.prefetch = Some(prefetch);
+        self.prefetch_scanned = false;
+        self.prefetch_error = None;
+    }
+
+    /// Shows the reason the prefetch was not started.
+    pub fn set_prefetch_error(&mut self, error: String) {
+        self.prefetch_error = Some(error);
+    }
+
+    fn is_busy(&self) -> bool {
+        self.busy.load(Ordering::Relaxed)
+    }
+
+    /// Runs the task in a background thread and scans the cache afterwards.
+    fn run_in_background(&mut self, ctx: &egui::Context, task: impl FnOnce() + Send + 'static) {
+        if self.busy.swap(true, Ordering::Relaxed) {
+            return;
+        }
+
+        let usage = self.usage.clone();
+        let busy = self.busy.clone();
+        let ctx = ctx.clone();
+        std::thread::spawn(move || {
+            task();
+            *usage.write() = Some(CacheUsage::scan(Path::new(CACHE_DIR)));
+            busy.store(false, Ordering::Relaxed);
+            ctx.request_repaint();
+        });
+    }
+
+    /// Shows the window. `active_cache` is the cache directory of the active source, and
+    /// `can_prefetch` tells why the tiles of the active source cannot be prefetched.
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        settings: &mut CacheSettings,
+        active_cache: Option<&str>,
+        can_prefetch: Result<(), &str>,
+    ) -> Option<CacheAction> {
+        if self.open && self.usage.read().is_none() && !self.is_busy() {
+            self.run_in_background(ctx, || {});
+        }
+
+        let mut open = self.open;
+        let mut action = None;
+        egui::Window::new("Tile cache")
+            .open(&mut open)
+            .default_width(350.0)
+            .show(ctx, |ui| {
+                action = self.ui(ui, settings, active_cache, can_prefetch);
+            });
+        self.open = open;
+
+        action
+    }
+
+    fn ui(
+        &mut self,
+        ui: &mut egui::Ui,
+        settings: &mut CacheSettings,
+        active_cache: Option<&str>,
+        can_prefetch: Result<(), &str>,
+    ) -> Option<CacheAction> {
+        self.usage_ui(ui, active_cache);
+
+        ui.separator();
+        ui.horizontal(|ui| {
+            let mut limited = settings.max_size_mb.is_some();
+            if ui.checkbox(&mut limited, "Size limit").changed() {
+                settings.max_size_mb = limited.then_some(1024);
+                self.size_checked_at = None;
+            }
+            if let Some(max_size_mb) = &mut settings.max_size_mb {
+                if ui
+                    .add(
+                        egui::DragValue::new(max_size_mb)
+                            .range(1..=1_000_000)
+                            .suffix(" MB"),
+                    )
+                    .changed()
+                {
+                    self.size_checked_at = None;
+                }
+            }
+        })
+        .response
+        .on_hover_text("The least recently used tiles are removed when the cache grows larger");
+
+        ui.separator();
+        self.prefetch_ui(ui, settings, can_prefetch)
+    }
+
+    fn usage_ui(&mut self, ui: &mut egui::Ui, active_cache: Option<&str>) {
+        let usage = self.usage.read().clone();
+        let mut clear = None;
+        ui.horizontal(|ui| {
+            match &usage {
+                Some(usage) => {
+                    ui.label(RichText::new(format_count(usage.total())).strong());
+                }
+                None => {
+                    ui.label("Reading the cache...");
+                }
+            }
+            if self.is_busy() {
+                ui.spinner();
+            }
+            if ui
+                .add_enabled(!self.is_busy(), egui::Button::new("Refresh"))
+                .clicked()
+            {
+                self.run_in_background(ui.ctx(), || {});
+            }
+            if ui
+                .add_enabled(!self.is_busy(), egui::Button::new("Clear all"))
+                .clicked()
+            {
+                clear = Some(None);
+            }
+        });
+
+        for source in usage.iter().flat_map(|usage| &usage.sources) {
+            let active = active_cache == Some(source.name.as_str());
+            let title = format!(
+                "{}{}: {}",
+                source.name,
+                if active { " (active)" } else { "" },
+                format_count(source.total())
+            );
+            egui::CollapsingHeader::new(title)
+                .id_salt(("tile cache", &source.name))
+                .show(ui, |ui| {
+                    Grid::new(("tile cache zoom levels", &source.name))
+                        .num_columns(3)
+                        .striped(true)
+                        .show(ui, |ui| {
+                            ui.label(RichText::new("Zoom").strong());
+                            ui.label(RichText::new("Tiles").strong());
+                            ui.label(RichText::new("Size").strong());
+                            ui.end_row();
+                            for (zoom, count) in &source.zoom_levels {
+                                ui.label(zoom.to_string());
+                                ui.label(count.tiles.to_string());
+                                ui.label(format_size(count.bytes));
+                                ui.end_row();
+                            }
+                        });
+                    if ui
+                        .add_enabled(!self.is_busy(), egui::Button::new("Clear").small())
+                        .clicked()
+                    {
+                        clear = Some(Some(source.name.clone()));
+                    }
+                });
+        }
+
+        if let Some(source) = clear {
+            self.run_in_background(ui.ctx(), move || {
+                clear_cache(Path::new(CACHE_DIR), source.as_deref())
+            });
+        }
+    }
+
+    fn prefetch_ui(
+        &mut self,
+        ui: &mut egui::Ui,
+        settings: &mut CacheSettings,
+        can_prefetch: Result<(), &str>,
+    ) -> Option<CacheAction> {
+        let running = self
+            .prefetch
+            .as_ref()
+            .filter(|prefetch| !prefetch.is_finished())
+            .cloned();
+        let mut action = None;
+        ui.horizontal(|ui| {
+            ui.label("Prefetch the view up to zoom");
+            ui.add(egui::DragValue::new(&mut settings.prefetch_zoom).range(0..=MAX_ZOOM));
+            let response = ui.add_enabled(
+                can_prefetch.is_ok() && running.is_none(),
+                egui::Button::new("Prefetch"),
+            );
+            if let Err(reason) = can_prefetch {
+                response.clone().on_disabled_hover_text(reason);
+            }
+            if response.clicked() {
+                action = Some(CacheAction::Prefetch(settings.prefetch_zoom));
+            }
+        });
+
+        if let Some(error) = &self.prefetch_error {
+            ui.colored_label(ui.visuals().error_fg_color, error);
+        }
+
+        let Some(prefetch) = &self.prefetch else {
+            return action;
+        };
+        let loaded = prefetch.loaded.load(Ordering::Relaxed).min(prefetch.total);
+        let failed = prefetch.failed.load(Ordering::Relaxed);
+        if running.is_some() {
+            ui.horizontal(|ui| {
+                ui.add(
+                    egui::ProgressBar::new(loaded as f32 / prefetch.total.max(1) as f32)
+                        .text(format!("{loaded} / {} tiles", prefetch.total)),
+                );
+                if ui.button("Cancel").clicked() {
+                    prefetch.cancelled.store(true, Ordering::Relaxed);
+                }
+            });
+            ui.ctx().request_repaint_after(Duration::from_millis(200));
+        } else if prefetch.cancelled.load(Ordering::Relaxed) {
+            ui.label(format!("Prefetch cancelled after {loaded} tiles"));
+        } else {
+            ui.label(format!("Prefetched {loaded} tiles"));
+        }
+        if failed > 0 {
+            ui.colored_label(
+                ui.visuals().warn_fg_color,
+                format!("{failed} tiles could not be loaded"),
+            );
+        }
+
+        action
+    }
+}
+
+fn format_count(count: TileCount) -> String {
+    format!("{} tiles, {}", count.tiles, format_size(count.bytes))
+}
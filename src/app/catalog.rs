@@ -0,0 +1,282 @@
+//! Catalog of the source layers, properties and values found in the decoded tiles.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use egui::{Grid, RichText};
+use galileo_mvt::MvtTile;
+
+use super::{inspector::GeometryType, tiles::VectorLayerInfo};
+
+/// Maximum number of distinct values remembered for a property. Properties like names or ids
+/// have too many values to list, so only the first ones seen are kept as samples.
+const MAX_VALUE_SAMPLES: usize = 32;
+
+/// Information about the data of the vector tiles collected as the tiles are decoded.
+#[derive(Debug, Default)]
+pub struct TileCatalog {
+    tile_count: usize,
+    layers: BTreeMap<String, LayerInfo>,
+    /// The tile set metadata declared its source layers, so other layers are not in the tiles.
+    has_declared_layers: bool,
+}
+
+/// Features of a source layer seen in the decoded tiles.
+#[derive(Debug, Default)]
+pub struct LayerInfo {
+    pub feature_count: u64,
+    /// Number of features of each geometry type.
+    pub geometry_types: BTreeMap<GeometryType, u64>,
+    pub properties: BTreeMap<String, PropertyInfo>,
+    /// The tile set metadata declared the properties of the layer.
+    pub has_declared_properties: bool,
+}
+
+impl LayerInfo {
+    /// Returns the geometry type most features of the layer have.
+    pub fn main_geometry_type(&self) -> Option<GeometryType> {
+        self.geometry_types
+            .iter()
+            .max_by_key(|(_, count)| **count)
+            .map(|(geometry_type, _)| *geometry_type)
+    }
+}
+
+/// Values of a feature property of a source layer.
+#[derive(Debug, Default)]
+pub struct PropertyInfo {
+    /// Number of features that have the property.
+    pub count: u64,
+    /// Sample values with the number of features having them.
+    pub values: BTreeMap<String, u64>,
+    /// The property has more distinct values than are kept in `values`.
+    pub has_more_values: bool,
+}
+
+impl TileCatalog {
+    /// Adds all features of a decoded tile to the catalog.
+    pub fn add_tile(&mut self, tile: &MvtTile) {
+        self.tile_count += 1;
+        for layer in &tile.layers {
+            for feature in &layer.features {
+                self.add_feature(
+                    &layer.name,
+                    GeometryType::of(&feature.geometry),
+                    feature
+                        .properties
+                        .iter()
+                        .map(|(key, value)| (key.as_str(), value.to_string())),
+                );
+            }
+        }
+    }
+
+    /// Adds the source layers and their properties declared in the tile set metadata, before
+    /// any of their features are seen.
+    pub fn add_vector_layers(&mut self, layers: &[VectorLayerInfo]) {
+        self.has_declared_layers |= !layers.is_empty();
+        for layer in layers {
+            let info = self.layers.entry(layer.id.clone()).or_default();
+            info.has_declared_properties |= !layer.fields.is_empty();
+            for field in &layer.fields {
+                info.properties.entry(field.clone()).or_default();
+            }
+        }
+    }
+
+    fn add_feature<'a>(
+        &mut self,
+        layer: &str,
+        geometry_type: GeometryType,
+        properties: impl Iterator<Item = (&'a str, String)>,
+    ) {
+        let layer = self.layers.entry(layer.to_string()).or_default();
+        layer.feature_count += 1;
+        *layer.geometry_types.entry(geometry_type).or_default() += 1;
+
+        for (key, value) in properties {
+            let property = layer.properties.entry(key.to_string()).or_default();
+            property.count += 1;
+
+            if let Some(count) = property.values.get_mut(&value) {
+                *count += 1;
+            } else if property.values.len() < MAX_VALUE_SAMPLES {
+                property.values.insert(value, 1);
+            } else {
+                property.has_more_values = true;
+            }
+        }
+    }
+
+    /// Returns `true` if no tiles have been decoded yet and the tile set declared no layers.
+    /// While the catalog is empty, nothing can be said about unknown layers or properties.
+    pub fn is_empty(&self) -> bool {
+        self.layers.is_empty()
+    }
+
+    /// Returns `true` if the tile set metadata listed its source layers.
+    pub fn has_declared_layers(&self) -> bool {
+        self.has_declared_layers
+    }
+
+    pub fn layer(&self, name: &str) -> Option<&LayerInfo> {
+        self.layers.get(name)
+    }
+
+    pub fn layers(&self) -> impl Iterator<Item = (&str, &LayerInfo)> {
+        self.layers.iter().map(|(name, info)| (name.as_str(), info))
+    }
+
+    /// Returns the number of layers with features seen in the decoded tiles.
+    pub fn layers_with_features(&self) -> usize {
+        self.layers
+            .values()
+            .filter(|info| info.feature_count > 0)
+            .count()
+    }
+
+    pub fn layer_names(&self) -> impl Iterator<Item = &str> {
+        self.layers.keys().map(String::as_str)
+    }
+
+    /// Returns the property names of the `layer`, or of all layers if the layer name is empty.
+    pub fn property_names(&self, layer: &str) -> BTreeSet<&str> {
+        self.layers_for(layer)
+            .flat_map(|info| info.properties.keys().map(String::as_str))
+            .collect()
+    }
+
+    /// Returns the sample values of a property of the `layer`, or of all layers if the layer
+    /// name is empty, ordered from the most frequent.
+    pub fn property_values(&self, layer: &str, property: &str) -> Vec<&str> {
+        let mut values: BTreeMap<&str, u64> = BTreeMap::new();
+        for info in self.layers_for(layer) {
+            if let Some(property) = info.properties.get(property) {
+                for (value, count) in &property.values {
+                    *values.entry(value.as_str()).or_default() += count;
+                }
+            }
+        }
+
+        let mut values: Vec<(&str, u64)> = values.into_iter().collect();
+        values.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+        values.into_iter().map(|(value, _)| value).collect()
+    }
+
+    /// Checks if any feature of the `layer` (any layer if the name is empty) has the property.
+    pub fn has_property(&self, layer: &str, property: &str) -> bool {
+        self.layers_for(layer)
+            .any(|info| info.properties.contains_key(property))
+    }
+
+    fn layers_for<'a>(&'a self, layer: &'a str) -> impl Iterator<Item = &'a LayerInfo> + 'a {
+        self.layers
+            .iter()
+            .filter(move |(name, _)| layer.is_empty() || name.as_str() == layer)
+            .map(|(_, info)| info)
+    }
+
+    /// Shows the catalog as a list of layers with their properties and sample values.
+    pub fn ui(&self, ui: &mut egui::Ui) {
+        if self.is_empty() {
+            ui.label("No tiles decoded yet.");
+            return;
+        }
+
+        ui.label(format!(
+            "{} layers in {} decoded tiles",
+            self.layers.len(),
+            self.tile_count
+        ));
+
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            for (name, layer) in &self.layers {
+                let geometry_types = layer
+                    .geometry_types
+                    .keys()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                egui::CollapsingHeader::new(RichText::new(name).strong())
+                    .id_salt(("catalog layer", name))
+                    .show(ui, |ui| {
+                        ui.label(format!(
+                            "{} features ({geometry_types})",
+                            layer.feature_count
+                        ));
+
+                        Grid::new(("catalog properties", name))
+                            .num_columns(3)
+                            .striped(true)
+                            .show(ui, |ui| {
+                                for (key, property) in &layer.properties {
+                                    ui.label(key);
+                                    ui.label(property.count.to_string());
+                                    let mut values =
+                                        property.values.keys().take(8).cloned().collect::<Vec<_>>();
+                                    if property.has_more_values || property.values.len() > 8 {
+                                        values.push("...".to_string());
+                                    }
+                                    ui.label(values.join(", "));
+                                    ui.end_row();
+                                }
+                            });
+                    });
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn add(catalog: &mut TileCatalog, layer: &str, properties: &[(&str, &str)]) {
+        catalog.add_feature(
+            layer,
+            GeometryType::Line,
+            properties.iter().map(|(k, v)| (*k, v.to_string())),
+        );
+    }
+
+    #[test]
+    fn collects_layers_and_values() {
+        let mut catalog = TileCatalog::default();
+        add(&mut catalog, "transportation", &[("class", "minor")]);
+        add(&mut catalog, "transportation", &[("class", "primary")]);
+        add(
+            &mut catalog,
+            "transportation",
+            &[("class", "minor"), ("ref", "M1")],
+        );
+        add(&mut catalog, "water", &[("class", "lake")]);
+
+        let layer = catalog.layer("transportation").unwrap();
+        assert_eq!(layer.feature_count, 3);
+        assert_eq!(layer.main_geometry_type(), Some(GeometryType::Line));
+        assert_eq!(layer.properties["class"].count, 3);
+        assert_eq!(
+            catalog.property_values("transportation", "class"),
+            vec!["minor", "primary"]
+        );
+        assert_eq!(
+            catalog.property_values("", "class"),
+            vec!["minor", "lake", "primary"]
+        );
+        assert!(catalog.has_property("transportation", "ref"));
+        assert!(!catalog.has_property("water", "ref"));
+        assert!(catalog.has_property("", "ref"));
+    }
+
+    #[test]
+    fn limits_value_samples() {
+        let mut catalog = TileCatalog::default();
+        for i in 0..MAX_VALUE_SAMPLES + 5 {
+            add(&mut catalog, "poi", &[("name", &format!("poi {i}"))]);
+        }
+
+        let property = &catalog.layer("poi").unwrap().properties["name"];
+        assert_eq!(property.count, MAX_VALUE_SAMPLES as u64 + 5);
+        assert_eq!(property.values.len(), MAX_VALUE_SAMPLES);
+        assert!(property.has_more_values);
+    }
+}
@@ -0,0 +1,324 @@
+//! Comparison of the edited style with another style: a second map draws the same tiles with the
+//! other style, either next to the main map or over it, cut by a draggable divider.
+
+use std::sync::Arc;
+
+use eframe::egui_wgpu::RenderState;
+use galileo::{
+    control::UserEventHandler,
+    layer::{vector_tile_layer::style::VectorTileStyle, VectorTileLayer},
+    Map, MapView,
+};
+use galileo_egui::{EguiMap, EguiMapOptions, EguiMapState};
+use parking_lot::RwLock;
+
+use super::{style::StyleWindow, viewport::Viewport};
+
+/// Width of the area around the swipe divider that can be dragged, in points.
+const DIVIDER_GRAB_WIDTH: f32 = 12.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CompareMode {
+    #[default]
+    SideBySide,
+    Swipe,
+}
+
+impl std::fmt::Display for CompareMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CompareMode::SideBySide => write!(f, "Side by side"),
+            CompareMode::Swipe => write!(f, "Swipe"),
+        }
+    }
+}
+
+/// Style the edited style is compared with.
+#[derive(Debug, Clone)]
+pub struct CompareStyle {
+    pub name: String,
+    pub style: VectorTileStyle,
+}
+
+/// Second map drawing the compared style, following the view of the main map.
+pub struct CompareMap {
+    state: EguiMapState,
+    layer: Arc<RwLock<VectorTileLayer>>,
+    /// Viewports of the main and the compare map right after their views were synchronized.
+    synced: Option<(Option<Viewport>, Option<Viewport>)>,
+}
+
+impl CompareMap {
+    pub fn new(
+        layer: VectorTileLayer,
+        view: MapView,
+        ctx: egui::Context,
+        render_state: RenderState,
+    ) -> Self {
+        let layer = Arc::new(RwLock::new(layer));
+        let map = Map::new(view, vec![Box::new(layer.clone())], None);
+        let handlers: Vec<Box<dyn UserEventHandler>> = vec![];
+
+        Self {
+            state: EguiMapState::new(map, ctx, render_state, handlers, EguiMapOptions::default()),
+            layer,
+            synced: None,
+        }
+    }
+
+    /// Replaces the layer, when the map switches to another tile source.
+    pub fn set_layer(&mut self, layer: VectorTileLayer) {
+        *self.layer.write() = layer;
+        self.state.request_redraw();
+    }
+
+    pub fn set_style(&mut self, style: VectorTileStyle) {
+        self.layer.write().update_style(style);
+        self.state.request_redraw();
+    }
+
+    pub fn request_redraw(&mut self) {
+        self.state.request_redraw();
+    }
+
+    /// Moves the compare map to the view of the main map, or the main map to the view of the
+    /// compare map if only the compare map was moved since the last call.
+    fn sync_view(&mut self, main: &mut EguiMapState) {
+        let main_viewport = Viewport::from_view(main.map().view());
+        let compare_viewport = Viewport::from_view(self.state.map().view());
+        let (synced_main, synced_compare) = self.synced.unwrap_or_default();
+
+        if compare_viewport != synced_compare && main_viewport == synced_main {
+            if let Some(viewport) = compare_viewport {
+                main.map_mut().set_view(viewport.to_view());
+            }
+        } else if main_viewport != synced_main {
+            if let Some(viewport) = main_viewport {
+                self.state.map_mut().set_view(viewport.to_view());
+            }
+        } else {
+            return;
+        }
+
+        self.synced = Some((
+            Viewport::from_view(main.map().view()),
+            Viewport::from_view(self.state.map().view()),
+        ));
+    }
+}
+
+/// Settings of the comparison and the second map.
+#[derive(Default)]
+pub struct Compare {
+    pub open: bool,
+    enabled: bool,
+    mode: CompareMode,
+    style: Option<CompareStyle>,
+    /// Set when the style was chosen and the compare map has to be created or updated.
+    style_changed: bool,
+    /// Position of the swipe divider as a fraction of the map width.
+    divider: Option<f32>,
+    map: Option<CompareMap>,
+}
+
+impl Compare {
+    /// Returns the style the map is compared with while the comparison is on.
+    pub fn active_style(&self) -> Option<&VectorTileStyle> {
+        self.style
+            .as_ref()
+            .filter(|_| self.enabled)
+            .map(|style| &style.style)
+    }
+
+    /// Returns the style of the compare map, if the map was created.
+    pub fn map_style(&self) -> Option<&VectorTileStyle> {
+        self.map
+            .as_ref()
+            .and(self.style.as_ref())
+            .map(|style| &style.style)
+    }
+
+    /// Returns the newly chosen style, to create or update the compare map with.
+    pub fn take_changed_style(&mut self) -> Option<VectorTileStyle> {
+        if !std::mem::take(&mut self.style_changed) {
+            return None;
+        }
+        self.active_style().cloned()
+    }
+
+    pub fn map_mut(&mut self) -> Option<&mut CompareMap> {
+        self.map.as_mut()
+    }
+
+    pub fn set_map(&mut self, map: CompareMap) {
+        self.map = Some(map);
+    }
+
+    fn set_style(&mut self, style: CompareStyle) {
+        self.style = Some(style);
+        self.enabled = true;
+        self.style_changed = true;
+    }
+
+    /// Shows the window choosing the compared style.
+    pub fn show(&mut self, ctx: &egui::Context, style_window: &StyleWindow) {
+        let mut open = self.open;
+        egui::Window::new("Compare styles")
+            .open(&mut open)
+            .default_width(250.0)
+            .show(ctx, |ui| self.ui(ui, style_window));
+        self.open = open;
+    }
+
+    fn ui(&mut self, ui: &mut egui::Ui, style_window: &StyleWindow) {
+        ui.horizontal(|ui| {
+            if ui
+                .button("Snapshot")
+                .on_hover_text("Compare with the edited style as it is now")
+                .clicked()
+            {
+                self.set_style(CompareStyle {
+                    name: "Snapshot".to_string(),
+                    style: style_window.style(),
+                });
+            }
+            let imported = style_window.imported_style();
+            if ui
+                .add_enabled(imported.is_some(), egui::Button::new("Original import"))
+                .on_hover_text("Compare with the imported MapTiler style before editing")
+                .on_disabled_hover_text("No MapTiler style was imported")
+                .clicked()
+            {
+                if let Some(style) = imported {
+                    self.set_style(CompareStyle {
+                        name: "Original import".to_string(),
+                        style: style.clone(),
+                    });
+                }
+            }
+            #[cfg(not(target_arch = "wasm32"))]
+            if ui
+                .button("Load...")
+                .on_hover_text("Compare with a MapTiler style file")
+                .clicked()
+            {
+                if let Some(converted) = super::style::pick_maptiler_style() {
+                    self.set_style(CompareStyle {
+                        name: "Loaded style".to_string(),
+                        style: converted.into(),
+                    });
+                }
+            }
+        });
+
+        let Some(style) = &self.style else {
+            ui.label("Choose a style to compare the edited style with");
+            return;
+        };
+
+        ui.separator();
+        ui.label(format!("Compared with: {}", style.name));
+        if ui.checkbox(&mut self.enabled, "Show comparison").changed() {
+            self.style_changed = self.enabled;
+        }
+        ui.horizontal(|ui| {
+            for mode in [CompareMode::SideBySide, CompareMode::Swipe] {
+                ui.radio_value(&mut self.mode, mode, mode.to_string());
+            }
+        });
+        if ui.button("Stop comparing").clicked() {
+            self.style = None;
+            self.enabled = false;
+            self.map = None;
+        }
+    }
+
+    /// Shows the main map, and the compare map while the comparison is on.
+    pub fn maps_ui(&mut self, ui: &mut egui::Ui, main: &mut EguiMapState) {
+        let Some(map) = self.map.as_mut().filter(|_| self.enabled) else {
+            EguiMap::new(main).show_ui(ui);
+            return;
+        };
+        let compare_name = self.style.as_ref().map_or("", |style| style.name.as_str());
+
+        map.sync_view(main);
+        match self.mode {
+            CompareMode::SideBySide => {
+                ui.columns(2, |columns| {
+                    let rect = columns[0].max_rect();
+                    EguiMap::new(main).show_ui(&mut columns[0]);
+                    caption(&columns[0], rect, "Edited style");
+
+                    let rect = columns[1].max_rect();
+                    EguiMap::new(&mut map.state).show_ui(&mut columns[1]);
+                    caption(&columns[1], rect, compare_name);
+                });
+            }
+            CompareMode::Swipe => {
+                let rect = ui.available_rect_before_wrap();
+                let divider_x = rect.left() + rect.width() * self.divider.unwrap_or(0.5);
+                EguiMap::new(main).show_ui(ui);
+
+                // The compare map covers the main map right of the divider
+                let compare_rect =
+                    egui::Rect::from_min_max(egui::pos2(divider_x, rect.top()), rect.max);
+                ui.scope_builder(egui::UiBuilder::new().max_rect(rect), |ui| {
+                    ui.set_clip_rect(compare_rect.intersect(ui.clip_rect()));
+                    EguiMap::new(&mut map.state).show_ui(ui);
+                });
+
+                caption(ui, rect, "Edited style");
+                caption(ui, compare_rect, compare_name);
+                self.divider_ui(ui, rect, divider_x);
+            }
+        }
+    }
+
+    /// Draws the swipe divider and moves it when it is dragged.
+    fn divider_ui(&mut self, ui: &egui::Ui, rect: egui::Rect, divider_x: f32) {
+        let grab_rect = egui::Rect::from_center_size(
+            egui::pos2(divider_x, rect.center().y),
+            egui::vec2(DIVIDER_GRAB_WIDTH, rect.height()),
+        );
+        let response = ui
+            .interact(
+                grab_rect,
+                ui.id().with("swipe_divider"),
+                egui::Sense::drag(),
+            )
+            .on_hover_cursor(egui::CursorIcon::ResizeHorizontal);
+        if response.dragged() {
+            if let Some(pos) = response.interact_pointer_pos() {
+                self.divider = Some(((pos.x - rect.left()) / rect.width()).clamp(0.0, 1.0));
+            }
+        }
+
+        let painter = ui.painter_at(rect);
+        let stroke = egui::Stroke::new(2.0, ui.visuals().strong_text_color());
+        painter.vline(divider_x, rect.y_range(), stroke);
+        painter.circle(
+            egui::pos2(divider_x, rect.center().y),
+            DIVIDER_GRAB_WIDTH / 2.0,
+            ui.visuals().window_fill,
+            stroke,
+        );
+    }
+}
+
+/// Draws the name of the style shown in the `rect` in its top left corner.
+fn caption(ui: &egui::Ui, rect: egui::Rect, text: &str) {
+    let painter = ui.painter_at(rect);
+    let galley = painter.layout_no_wrap(
+        text.to_string(),
+        egui::FontId::proportional(13.0),
+        ui.visuals().strong_text_color(),
+    );
+    let text_rect =
+        egui::Rect::from_min_size(rect.min + egui::vec2(8.0, 8.0), galley.size()).expand(4.0);
+    painter.rect_filled(text_rect, 4.0, ui.visuals().window_fill);
+    painter.galley(
+        text_rect.min + egui::vec2(4.0, 4.0),
+        galley,
+        egui::Color32::WHITE,
+    );
+}
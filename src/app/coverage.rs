@@ -0,0 +1,157 @@
+//! Report of the features in the view that no rule applies to.
+
+use std::collections::HashMap;
+
+use egui::{Grid, RichText};
+use galileo_mvt::MvtFeature;
+use itertools::Itertools;
+
+use super::{
+    inspector::{GeometryType, InspectedFeature, InspectorAction},
+    style::RuleMatcher,
+};
+
+/// Feature property the unstyled features of a layer are grouped by.
+const GROUP_PROPERTY: &str = "class";
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+struct GroupKey {
+    layer: String,
+    class: Option<String>,
+    geometry_type: GeometryType,
+}
+
+/// Unstyled features in the view, grouped by layer, class and geometry type.
+#[derive(Debug, Default)]
+pub struct UnstyledReport {
+    groups: Vec<(GroupKey, usize)>,
+    feature_count: usize,
+    unstyled_count: usize,
+    update_requested: bool,
+    pub open: bool,
+}
+
+impl UnstyledReport {
+    /// Asks for the report to be recalculated the next time it is shown.
+    pub fn request_update(&mut self) {
+        self.update_requested = true;
+    }
+
+    pub fn needs_update(&self) -> bool {
+        self.open && self.update_requested
+    }
+
+    /// Recalculates the report for the given features.
+    pub fn update<'a>(
+        &mut self,
+        features: impl Iterator<Item = (&'a str, &'a MvtFeature)>,
+        matcher: &RuleMatcher,
+    ) {
+        let mut counts: HashMap<GroupKey, usize> = HashMap::new();
+        self.feature_count = 0;
+        for (layer, feature) in features {
+            self.feature_count += 1;
+            let feature = InspectedFeature::new(layer, feature);
+            if matcher.first_match(layer, &feature.properties).is_some() {
+                continue;
+            }
+
+            let class = feature
+                .properties
+                .iter()
+                .find(|(key, _)| key == GROUP_PROPERTY)
+                .map(|(_, value)| value.clone());
+            let key = GroupKey {
+                layer: feature.layer,
+                class,
+                geometry_type: feature.geometry_type,
+            };
+            *counts.entry(key).or_default() += 1;
+        }
+
+        self.unstyled_count = counts.values().sum();
+        self.groups = counts.into_iter().collect();
+        self.groups.sort_by(|a, b| {
+            a.0.layer
+                .cmp(&b.0.layer)
+                .then(b.1.cmp(&a.1))
+                .then(a.0.cmp(&b.0))
+        });
+        self.update_requested = false;
+    }
+
+    /// Shows the report window. Returns the action requested by the user.
+    pub fn show(&mut self, ctx: &egui::Context) -> Option<InspectorAction> {
+        let mut open = self.open;
+        let mut action = None;
+        egui::Window::new("Unstyled features")
+            .open(&mut open)
+            .default_width(350.0)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label(format!(
+                        "{} of {} features in the view match no rule",
+                        self.unstyled_count, self.feature_count
+                    ));
+                    if ui.button("Refresh").clicked() {
+                        self.update_requested = true;
+                    }
+                });
+                ui.separator();
+
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    action = self.groups_ui(ui);
+                });
+            });
+
+        self.open = open;
+
+        action
+    }
+
+    fn groups_ui(&self, ui: &mut egui::Ui) -> Option<InspectorAction> {
+        let mut action = None;
+        for (layer, groups) in &self.groups.iter().chunk_by(|(key, _)| &key.layer) {
+            let groups: Vec<_> = groups.collect();
+            let count: usize = groups.iter().map(|(_, count)| count).sum();
+            egui::CollapsingHeader::new(RichText::new(format!("{layer} ({count})")).strong())
+                .id_salt(("unstyled layer", layer))
+                .show(ui, |ui| {
+                    Grid::new(("unstyled groups", layer))
+                        .num_columns(4)
+                        .striped(true)
+                        .show(ui, |ui| {
+                            for (key, count) in groups {
+                                match &key.class {
+                                    Some(class) => ui.label(class),
+                                    None => ui.weak(format!("no {GROUP_PROPERTY}")),
+                                };
+                                ui.label(key.geometry_type.to_string());
+                                ui.label(count.to_string());
+                                if ui
+                                    .small_button("Create rule")
+                                    .on_hover_text("Add a rule for these features")
+                                    .clicked()
+                                {
+                                    action = Some(InspectorAction::CreateRule {
+                                        layer: key.layer.clone(),
+                                        geometry_type: key.geometry_type,
+                                        filter: key
+                                            .class
+                                            .iter()
+                                            .map(|class| {
+                                                (GROUP_PROPERTY.to_string(), class.clone())
+                                            })
+                                            .collect(),
+                                        before_rule: None,
+                                    });
+                                }
+                                ui.end_row();
+                            }
+                        });
+                });
+        }
+
+        action
+    }
+}
@@ -0,0 +1,221 @@
+//! Overlay layer outlining the features of the rules selected or hovered in the style window.
+
+use std::sync::Arc;
+
+use galileo::{
+    layer::{feature_layer::Feature, FeatureLayer},
+    render::{render_bundle::RenderBundle, LineCap, LinePaint, PointPaint},
+    symbol::Symbol,
+    Color, MapView, TileSchema,
+};
+use galileo_mvt::{MvtFeature, MvtGeometry};
+use galileo_types::{
+    cartesian::{Point2, Point3, Rect},
+    contour::Contour as _,
+    geo::Crs,
+    geometry::Geom,
+    geometry_type::CartesianSpace2d,
+    impls::Contour,
+    polygon::Polygon as _,
+};
+use parking_lot::RwLock;
+
+use super::{
+    inspector::property_strings,
+    style::RuleMatcher,
+    tiles::{visible_tiles, TileStore, TileStoreId},
+};
+
+/// Maximum number of outlined features. Rules matching most of the map would otherwise make the
+/// overlay as heavy as the map itself.
+const MAX_FEATURES: usize = 20_000;
+
+/// Outline of a highlighted feature in map coordinates: polygons are converted to their rings.
+pub struct HighlightFeature {
+    geometry: Geom<Point2>,
+}
+
+impl Feature for HighlightFeature {
+    type Geom = Geom<Point2>;
+
+    fn geometry(&self) -> &Self::Geom {
+        &self.geometry
+    }
+}
+
+pub struct HighlightSymbol {
+    color: Color,
+}
+
+impl Symbol<HighlightFeature> for HighlightSymbol {
+    fn render(
+        &self,
+        _feature: &HighlightFeature,
+        geometry: &Geom<Point3>,
+        min_resolution: f64,
+        bundle: &mut RenderBundle,
+    ) {
+        match geometry {
+            Geom::Point(point) => {
+                bundle.add_point(point, &PointPaint::circle(self.color, 10.0), min_resolution);
+            }
+            Geom::Contour(contour) => {
+                let paint = LinePaint {
+                    color: self.color,
+                    width: 2.0,
+                    offset: 0.0,
+                    line_cap: LineCap::Butt,
+                };
+                bundle.add_line(contour, &paint, min_resolution);
+            }
+            _ => {}
+        }
+    }
+}
+
+pub type HighlightLayer = FeatureLayer<Point2, HighlightFeature, HighlightSymbol, CartesianSpace2d>;
+
+fn highlight_layer(features: Vec<HighlightFeature>) -> HighlightLayer {
+    FeatureLayer::new(
+        features,
+        HighlightSymbol {
+            color: Color::rgba(255, 0, 255, 255),
+        },
+        Crs::EPSG3857,
+    )
+}
+
+/// Converts the geometry of a feature from the tile coordinates into the map coordinates of the
+/// tile `bbox`. Tile coordinates go from 0 to `extent` with the y axis pointing down.
+fn feature_outlines(feature: &MvtFeature, bbox: &Rect, extent: u32) -> Vec<Geom<Point2>> {
+    let resolution = bbox.width() / extent as f64;
+    let to_map = |x: f32, y: f32| {
+        Point2::new(
+            bbox.x_min() + x as f64 * resolution,
+            bbox.y_max() - y as f64 * resolution,
+        )
+    };
+
+    match &feature.geometry {
+        MvtGeometry::Point(points) => points
+            .iter()
+            .map(|point| Geom::Point(to_map(point.x(), point.y())))
+            .collect(),
+        MvtGeometry::LineString(contours) => contours
+            .iter()
+            .map(|contour| {
+                let points = contour
+                    .iter_points()
+                    .map(|p| to_map(p.x(), p.y()))
+                    .collect();
+                Geom::Contour(Contour::new(points, false))
+            })
+            .collect(),
+        MvtGeometry::Polygon(polygons) => polygons
+            .iter()
+            .flat_map(|polygon| polygon.iter_contours())
+            .map(|contour| {
+                let points = contour
+                    .iter_points()
+                    .map(|p| to_map(p.x(), p.y()))
+                    .collect();
+                Geom::Contour(Contour::new(points, true))
+            })
+            .collect(),
+    }
+}
+
+type HighlightState = (Vec<u64>, u64, TileStoreId, u64, Vec<(u32, i32, i32)>);
+
+/// Keeps the overlay layer in sync with the highlighted rules.
+pub struct Highlighter {
+    layer: Arc<RwLock<HighlightLayer>>,
+    /// Rule ids, hash of the rules, tile store, its generation and visible tiles the overlay was
+    /// built for.
+    shown: Option<HighlightState>,
+}
+
+impl Default for Highlighter {
+    fn default() -> Self {
+        Self {
+            layer: Arc::new(RwLock::new(highlight_layer(vec![]))),
+            shown: None,
+        }
+    }
+}
+
+impl Highlighter {
+    /// The overlay layer to add to the map.
+    pub fn layer(&self) -> Arc<RwLock<HighlightLayer>> {
+        self.layer.clone()
+    }
+
+    /// Outlines the features in the view that the rules with `rule_ids` apply to. Returns `true`
+    /// if the overlay has changed and the map must be redrawn.
+    pub fn update(
+        &mut self,
+        rule_ids: Vec<u64>,
+        matcher: Option<RuleMatcher>,
+        store: &TileStore,
+        view: &MapView,
+        schema: &TileSchema,
+    ) -> bool {
+        let tiles = visible_tiles(view, schema);
+        let tile_keys = tiles
+            .iter()
+            .map(|index| (index.z, index.x, index.y))
+            .collect();
+        let rules_hash = matcher.as_ref().map_or(0, RuleMatcher::rules_hash);
+        let state = (
+            rule_ids,
+            rules_hash,
+            store.id(),
+            store.generation(),
+            tile_keys,
+        );
+        if self.shown.as_ref() == Some(&state) {
+            return false;
+        }
+
+        // Nothing was highlighted and nothing is highlighted now
+        let was_empty = self.shown.as_ref().map_or(true, |shown| shown.0.is_empty());
+        if state.0.is_empty() && was_empty {
+            self.shown = Some(state);
+            return false;
+        }
+
+        let mut features = vec![];
+        if let Some(matcher) = matcher {
+            'tiles: for index in &tiles {
+                let (Some(tile), Some(bbox)) = (store.get(*index), schema.tile_bbox(*index)) else {
+                    continue;
+                };
+
+                for layer in &tile.layers {
+                    for feature in &layer.features {
+                        if matcher
+                            .first_match(&layer.name, &property_strings(feature))
+                            .is_none()
+                        {
+                            continue;
+                        }
+
+                        features.extend(
+                            feature_outlines(feature, &bbox, layer.size)
+                                .into_iter()
+                                .map(|geometry| HighlightFeature { geometry }),
+                        );
+                        if features.len() >= MAX_FEATURES {
+                            log::info!("Too many features to highlight, showing {MAX_FEATURES}");
+                            break 'tiles;
+                        }
+                    }
+                }
+            }
+        }
+
+        *self.layer.write() = highlight_layer(features);
+        self.shown = Some(state);
+        true
+    }
+}
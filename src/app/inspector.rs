@@ -0,0 +1,351 @@
+//! Panel listing the vector tile features under the cursor.
+
+use std::{collections::HashSet, fmt::Formatter};
+
+use egui::{Grid, RichText};
+use galileo_mvt::{MvtFeature, MvtGeometry};
+
+use super::style::{can_filter_by, RuleMatch, StyleWindow};
+
+/// Geometry type of a vector tile feature.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum GeometryType {
+    Point,
+    Line,
+    Polygon,
+}
+
+impl GeometryType {
+    pub fn of(geometry: &MvtGeometry) -> Self {
+        match geometry {
+            MvtGeometry::Point(_) => GeometryType::Point,
+            MvtGeometry::LineString(_) => GeometryType::Line,
+            MvtGeometry::Polygon(_) => GeometryType::Polygon,
+        }
+    }
+}
+
+impl std::fmt::Display for GeometryType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GeometryType::Point => write!(f, "point"),
+            GeometryType::Line => write!(f, "line"),
+            GeometryType::Polygon => write!(f, "polygon"),
+        }
+    }
+}
+
+/// A copy of a feature found under the cursor, detached from the tile it was read from.
+#[derive(Debug, Clone)]
+pub struct InspectedFeature {
+    pub layer: String,
+    pub id: Option<u64>,
+    pub geometry_type: GeometryType,
+    pub properties: Vec<(String, String)>,
+}
+
+impl InspectedFeature {
+    pub fn new(layer: impl Into<String>, feature: &MvtFeature) -> Self {
+        Self {
+            layer: layer.into(),
+            id: feature.id,
+            geometry_type: GeometryType::of(&feature.geometry),
+            properties: property_strings(feature),
+        }
+    }
+}
+
+/// Returns the properties of a feature as strings, the way rule filters compare them.
+pub fn property_strings(feature: &MvtFeature) -> Vec<(String, String)> {
+    feature
+        .properties
+        .iter()
+        .map(|(key, value)| (key.clone(), value.to_string()))
+        .collect()
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum SortColumn {
+    Key,
+    Value,
+}
+
+/// Properties that are checked by default for the filter of a rule created from a feature.
+const DEFAULT_FILTER_PROPERTIES: &[&str] = &["class", "subclass"];
+
+/// Action requested by the user in the inspector panel.
+#[derive(Debug, Clone)]
+pub enum InspectorAction {
+    /// Show the rule with the given id in the style window.
+    RevealRule(u64),
+    /// Create a new rule for the features of the same layer.
+    CreateRule {
+        layer: String,
+        geometry_type: GeometryType,
+        /// Properties of the feature the rule filter should check for equality.
+        filter: Vec<(String, String)>,
+        /// Id of the rule currently used for the feature. The new rule is inserted above it.
+        before_rule: Option<u64>,
+    },
+}
+
+/// State of the feature inspector panel.
+pub struct FeatureInspector {
+    features: Vec<InspectedFeature>,
+    /// Properties of each feature checked to be used in the filter of a new rule.
+    filter_properties: Vec<HashSet<String>>,
+    sort_column: SortColumn,
+    sort_ascending: bool,
+    pub open: bool,
+}
+
+impl Default for FeatureInspector {
+    fn default() -> Self {
+        Self {
+            features: vec![],
+            filter_properties: vec![],
+            sort_column: SortColumn::Key,
+            sort_ascending: true,
+            open: true,
+        }
+    }
+}
+
+impl FeatureInspector {
+    /// Replaces the inspected features with the ones found by the last click.
+    pub fn set_features(&mut self, features: Vec<InspectedFeature>) {
+        self.filter_properties = features
+            .iter()
+            .map(|feature| {
+                feature
+                    .properties
+                    .iter()
+                    .map(|(key, _)| key)
+                    .filter(|key| DEFAULT_FILTER_PROPERTIES.contains(&key.as_str()))
+                    .cloned()
+                    .collect()
+            })
+            .collect();
+        self.features = features;
+        self.open = true;
+    }
+
+    /// Shows the inspector panel. Returns the action requested by the user.
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        style_window: &StyleWindow,
+    ) -> Option<InspectorAction> {
+        if !self.open {
+            return None;
+        }
+
+        let mut action = None;
+        egui::SidePanel::right("feature_inspector")
+            .resizable(true)
+            .default_width(320.0)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.heading("Features");
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        if ui.small_button("x").clicked() {
+                            self.open = false;
+                        }
+                    });
+                });
+
+                if self.features.is_empty() {
+                    ui.label("Click on the map to list the features under the cursor.");
+                    return;
+                }
+
+                ui.label(format!("{} features under the cursor", self.features.len()));
+                ui.separator();
+
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    action = self.features_ui(ui, style_window);
+                });
+            });
+
+        action
+    }
+
+    fn features_ui(
+        &mut self,
+        ui: &mut egui::Ui,
+        style_window: &StyleWindow,
+    ) -> Option<InspectorAction> {
+        let mut sort_clicked = None;
+        let mut action = None;
+        let features = self.features.iter().zip(self.filter_properties.iter_mut());
+        for (index, (feature, filter_properties)) in features.enumerate() {
+            let title = format!("{} ({})", feature.layer, feature.geometry_type);
+            egui::CollapsingHeader::new(RichText::new(title).strong())
+                .id_salt(("inspected feature", index))
+                .default_open(index == 0)
+                .show(ui, |ui| {
+                    if let Some(id) = feature.id {
+                        ui.label(format!("id: {id}"));
+                    }
+
+                    let matches = style_window.matching_rules(&feature.layer, &feature.properties);
+                    if let Some(rule_id) = matching_rules_ui(ui, &matches) {
+                        action = Some(InspectorAction::RevealRule(rule_id));
+                    }
+
+                    sort_clicked = sort_clicked.or(property_table(
+                        ui,
+                        index,
+                        &feature.properties,
+                        filter_properties,
+                        self.sort_column,
+                        self.sort_ascending,
+                    ));
+
+                    if ui
+                        .button("Create rule for this feature")
+                        .on_hover_text(
+                            "Add a rule for this layer filtered by the checked properties",
+                        )
+                        .clicked()
+                    {
+                        action = Some(InspectorAction::CreateRule {
+                            layer: feature.layer.clone(),
+                            geometry_type: feature.geometry_type,
+                            filter: feature
+                                .properties
+                                .iter()
+                                .filter(|(key, _)| filter_properties.contains(key))
+                                .cloned()
+                                .collect(),
+                            before_rule: matches
+                                .iter()
+                                .find(|rule_match| rule_match.is_used)
+                                .map(|rule_match| rule_match.rule_id),
+                        });
+                    }
+                });
+        }
+
+        if let Some(column) = sort_clicked {
+            if column == self.sort_column {
+                self.sort_ascending = !self.sort_ascending;
+            } else {
+                self.sort_column = column;
+                self.sort_ascending = true;
+            }
+        }
+
+        action
+    }
+}
+
+/// Lists the rules matching a feature. Returns the id of the clicked rule.
+fn matching_rules_ui(ui: &mut egui::Ui, matches: &[RuleMatch]) -> Option<u64> {
+    if matches.is_empty() {
+        ui.colored_label(ui.visuals().warn_fg_color, "No rule matches this feature");
+        return None;
+    }
+
+    let mut clicked = None;
+    ui.label("Rules:");
+    for rule_match in matches {
+        let text = if rule_match.is_used {
+            RichText::new(format!("{} (used)", rule_match.title)).strong()
+        } else {
+            RichText::new(format!("{} (shadowed)", rule_match.title)).weak()
+        };
+
+        if ui
+            .selectable_label(rule_match.is_used, text)
+            .on_hover_text("Show in the style window")
+            .clicked()
+        {
+            clicked = Some(rule_match.rule_id);
+        }
+    }
+
+    clicked
+}
+
+/// Shows a table of feature properties sorted by the given column. Properties can be checked to
+/// be used in the filter of a new rule. Returns the column whose header was clicked.
+fn property_table(
+    ui: &mut egui::Ui,
+    index: usize,
+    properties: &[(String, String)],
+    filter_properties: &mut HashSet<String>,
+    sort_column: SortColumn,
+    sort_ascending: bool,
+) -> Option<SortColumn> {
+    let mut sorted: Vec<&(String, String)> = properties.iter().collect();
+    sorted.sort_by(|a, b| {
+        let ordering = match sort_column {
+            SortColumn::Key => a.0.cmp(&b.0),
+            SortColumn::Value => a.1.cmp(&b.1).then_with(|| a.0.cmp(&b.0)),
+        };
+        if sort_ascending {
+            ordering
+        } else {
+            ordering.reverse()
+        }
+    });
+
+    let arrow = |column| match (column == sort_column, sort_ascending) {
+        (false, _) => "",
+        (true, true) => " ▲",
+        (true, false) => " ▼",
+    };
+
+    let mut clicked = None;
+    Grid::new(("feature properties", index))
+        .num_columns(4)
+        .striped(true)
+        .show(ui, |ui| {
+            ui.label("")
+                .on_hover_text("Checked properties are used in the filter of a new rule");
+            if ui
+                .selectable_label(false, format!("Property{}", arrow(SortColumn::Key)))
+                .clicked()
+            {
+                clicked = Some(SortColumn::Key);
+            }
+            if ui
+                .selectable_label(false, format!("Value{}", arrow(SortColumn::Value)))
+                .clicked()
+            {
+                clicked = Some(SortColumn::Value);
+            }
+            ui.end_row();
+
+            for (key, value) in sorted {
+                let mut checked = filter_properties.contains(key);
+                if ui
+                    .add_enabled(
+                        can_filter_by(key, value),
+                        egui::Checkbox::without_text(&mut checked),
+                    )
+                    .on_disabled_hover_text("The value can't be written in a rule filter")
+                    .changed()
+                {
+                    if checked {
+                        filter_properties.insert(key.clone());
+                    } else {
+                        filter_properties.remove(key);
+                    }
+                }
+                ui.label(key);
+                ui.label(value);
+                if ui
+                    .small_button("Copy")
+                    .on_hover_text("Copy value")
+                    .clicked()
+                {
+                    ui.ctx().copy_text(value.clone());
+                }
+                ui.end_row();
+            }
+        });
+
+    clicked
+}
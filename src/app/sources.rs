@@ -0,0 +1,860 @@
+//! Named tile source profiles and the dialog to edit them.
+
+use egui::{Grid, RichText};
+use galileo::TileSchema;
+use serde::{Deserialize, Serialize};
+
+use super::tiles::{
+    RestSource, RowOrder, TileSchemaSettings, TileSetInfo, TileSource, DEFAULT_TILE_SIZE, MAX_ZOOM,
+    WORLD_HALF_SIZE,
+};
+
+/// Environment variable with the API key of the profiles that use it, see
+/// [`TileSourceProfile::use_env_key`].
+const API_KEY_VAR: &str = "VT_API_KEY";
+
+/// Sample tiles of a synthetic town, generated by `scripts/generate_sample_tiles.py`.
+const SAMPLE_TILES: &[u8] = include_bytes!("../../assets/sample.pmtiles");
+
+/// Where the tiles of a profile come from.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SourceKind {
+    /// Tile server with a `{z}/{x}/{y}` URL template.
+    #[default]
+    Rest,
+    /// Local MBTiles file.
+    MbTiles,
+    /// PMTiles archive, a local file or a URL.
+    PmTiles,
+    /// Local directory with `{z}/{x}/{y}.pbf` or `.mvt` files.
+    Directory,
+    /// Sample tiles bundled with the app.
+    Sample,
+}
+
+impl SourceKind {
+    /// Checks if the tiles of the source are numbered by the tile set format. MBTiles files and
+    /// PMTiles archives translate the map tile indices themselves, so the row order and the
+    /// origin of the tile schema can't be changed for them.
+    fn has_fixed_numbering(self) -> bool {
+        matches!(
+            self,
+            SourceKind::MbTiles | SourceKind::PmTiles | SourceKind::Sample
+        )
+    }
+}
+
+impl std::fmt::Display for SourceKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SourceKind::Rest => write!(f, "Tile server"),
+            SourceKind::MbTiles => write!(f, "MBTiles file"),
+            SourceKind::PmTiles => write!(f, "PMTiles archive"),
+            SourceKind::Directory => write!(f, "Tile directory"),
+            SourceKind::Sample => write!(f, "Sample tiles"),
+        }
+    }
+}
+
+/// Settings of a vector tile source.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TileSourceProfile {
+    pub name: String,
+    #[serde(default)]
+    pub kind: SourceKind,
+    pub url_template: String,
+    /// Path of the tile file for the file sources.
+    #[serde(default)]
+    pub path: String,
+    /// API key sent as the `api_key_param` query parameter. Not sent if empty.
+    pub api_key: String,
+    /// If `api_key` is empty, the key is read from the `VT_API_KEY` environment variable when the
+    /// source is opened. The key itself is not saved with the profile.
+    #[serde(default)]
+    pub use_env_key: bool,
+    pub api_key_param: String,
+    /// Additional query parameters of the tile requests.
+    pub query: Vec<(String, String)>,
+    /// Additional headers of the tile requests.
+    pub headers: Vec<(String, String)>,
+    pub attribution: String,
+    pub attribution_url: String,
+    /// Tile schema settings given in addition to the tile set metadata.
+    #[serde(default)]
+    pub schema: TileSchemaSettings,
+}
+
+impl TileSourceProfile {
+    /// MapTiler v3 tiles. The API key is taken from the `VT_API_KEY` environment variable.
+    fn maptiler() -> Self {
+        Self {
+            name: "MapTiler v3".to_string(),
+            kind: SourceKind::Rest,
+            url_template: "https://api.maptiler.com/tiles/v3/{z}/{x}/{y}.pbf".to_string(),
+            path: String::new(),
+            api_key: String::new(),
+            use_env_key: true,
+            api_key_param: "key".to_string(),
+            query: vec![],
+            headers: vec![],
+            attribution: "© MapTiler© OpenStreetMap contributors".to_string(),
+            attribution_url: "https://www.maptiler.com/copyright/".to_string(),
+            schema: TileSchemaSettings::default(),
+        }
+    }
+
+    /// Returns the tile schema of the tile set. The row order and origin settings are ignored
+    /// for sources that number their tiles themselves.
+    pub fn tile_schema(&self, info: &TileSetInfo) -> TileSchema {
+        let mut settings = self.schema.clone();
+        if self.kind.has_fixed_numbering() {
+            settings.row_order = None;
+            settings.origin = None;
+        }
+        settings.tile_schema(info)
+    }
+
+    /// Tiles bundled with the app, available without network and API keys. The attribution is
+    /// taken from the archive.
+    fn sample() -> Self {
+        Self {
+            name: "Sample City".to_string(),
+            kind: SourceKind::Sample,
+            ..Self::empty()
+        }
+    }
+
+    fn empty() -> Self {
+        Self {
+            name: String::new(),
+            kind: SourceKind::Rest,
+            url_template: String::new(),
+            path: String::new(),
+            api_key: String::new(),
+            use_env_key: false,
+            api_key_param: "key".to_string(),
+            query: vec![],
+            headers: vec![],
+            attribution: String::new(),
+            attribution_url: String::new(),
+            schema: TileSchemaSettings::default(),
+        }
+    }
+
+    /// Returns a description of the first problem with the profile, if there is one.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.name.trim().is_empty() {
+            return Err("The name is empty".to_string());
+        }
+        self.schema.validate()?;
+
+        if self.kind == SourceKind::Sample {
+            return Ok(());
+        }
+        if self.kind != SourceKind::Rest {
+            if self.path.trim().is_empty() {
+                return Err("The file path is empty".to_string());
+            }
+            return Ok(());
+        }
+
+        if self.url_template.trim().is_empty() {
+            return Err("The URL is empty".to_string());
+        }
+        for placeholder in ["{z}", "{x}", "{y}"] {
+            if self.tilejson_url().is_none() && !self.url_template.contains(placeholder) {
+                return Err(format!("The URL template has no {placeholder}"));
+            }
+        }
+
+        if !self.api_key().is_empty() && self.api_key_param.trim().is_empty() {
+            return Err("The API key parameter name is empty".to_string());
+        }
+
+        for (name, value) in &self.headers {
+            if reqwest::header::HeaderName::from_bytes(name.as_bytes()).is_err() {
+                return Err(format!("Invalid header name \"{name}\""));
+            }
+            if reqwest::header::HeaderValue::from_str(value).is_err() {
+                return Err(format!("Invalid value of header \"{name}\""));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the URL of the TileJSON of the tiles if it is given instead of the URL template.
+    /// Such URLs have no placeholders.
+    fn tilejson_url(&self) -> Option<&str> {
+        let url = self.url_template.trim();
+        (!url.contains('{')).then_some(url)
+    }
+
+    /// Returns the API key of the profile, or the key from the environment if the profile uses
+    /// it.
+    fn api_key(&self) -> String {
+        if self.api_key.is_empty() && self.use_env_key {
+            env_api_key().unwrap_or_default()
+        } else {
+            self.api_key.clone()
+        }
+    }
+
+    /// Query parameters of the tile requests, including the API key.
+    fn query_params(&self) -> Vec<(String, String)> {
+        let mut query: Vec<(String, String)> = self
+            .query
+            .iter()
+            .filter(|(key, _)| !key.trim().is_empty())
+            .cloned()
+            .collect();
+        let api_key = self.api_key();
+        if !api_key.is_empty() {
+            query.push((self.api_key_param.trim().to_string(), api_key));
+        }
+
+        query
+    }
+
+    /// Directory name of the tile cache of the source: the host of the tile server with a hash of
+    /// the URL template, so renamed profiles keep their cache and different servers don't share
+    /// one.
+    pub fn cache_dir_name(&self) -> String {
+        let url = self.url_template.trim();
+        let host = reqwest::Url::parse(url)
+            .ok()
+            .and_then(|url| url.host_str().map(str::to_string))
+            .unwrap_or_else(|| "tiles".to_string());
+        let host: String = host
+            .to_lowercase()
+            .chars()
+            .map(|c| match c {
+                c if c.is_ascii_alphanumeric() || c == '.' || c == '-' => c,
+                _ => '_',
+            })
+            .collect();
+
+        format!("{host}-{:016x}", fnv1a(url.as_bytes()))
+    }
+
+    /// Opens the source of the tiles as configured by the profile. Archives are read over the
+    /// network, so this may take a while. In the `offline` mode only the local files and the
+    /// cached tiles are used.
+    pub async fn open_source(&self, offline: bool) -> Result<Box<dyn TileSource>, String> {
+        let path = self.path.trim();
+        if offline && self.kind == SourceKind::PmTiles && path.contains("://") {
+            return Err(format!("{path} cannot be read in the offline mode"));
+        }
+
+        match self.kind {
+            SourceKind::Rest => {
+                let source = self.rest_source().with_offline(offline);
+                match self.tilejson_url() {
+                    Some(url) => Ok(Box::new(source.with_tilejson(url).await?)),
+                    None => Ok(Box::new(source)),
+                }
+            }
+            #[cfg(not(target_arch = "wasm32"))]
+            SourceKind::MbTiles => Ok(Box::new(super::tiles::MbTilesSource::open(path)?)),
+            #[cfg(not(target_arch = "wasm32"))]
+            SourceKind::Directory => Ok(Box::new(super::tiles::DirectorySource::open(path)?)),
+            #[cfg(target_arch = "wasm32")]
+            SourceKind::MbTiles | SourceKind::Directory => {
+                Err("Local files cannot be opened in the browser".into())
+            }
+            SourceKind::PmTiles => Ok(Box::new(super::tiles::PmTilesSource::open(path).await?)),
+            SourceKind::Sample => Ok(Box::new(
+                super::tiles::PmTilesSource::from_bytes(
+                    bytes::Bytes::from_static(SAMPLE_TILES),
+                    "sample tiles",
+                )
+                .await?,
+            )),
+        }
+    }
+
+    fn rest_source(&self) -> RestSource {
+        let source = RestSource::new(self.url_template.trim())
+            .with_query(self.query_params())
+            .with_headers(
+                self.headers
+                    .iter()
+                    .map(|(name, value)| (name.as_str(), value.as_str())),
+            );
+
+        #[cfg(not(target_arch = "wasm32"))]
+        let source = source.with_cache(super::tiles::FileCache::new(
+            std::path::Path::new(super::tiles::CACHE_DIR).join(self.cache_dir_name()),
+        ));
+
+        source
+    }
+}
+
+/// FNV-1a hash, which unlike the hasher of the standard library stays the same between Rust
+/// versions.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// Returns the API key set in the environment, if there is one.
+fn env_api_key() -> Option<String> {
+    std::env::var(API_KEY_VAR)
+        .ok()
+        .filter(|key| !key.is_empty())
+}
+
+/// The tile source profiles and the one the map is showing.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SourceSettings {
+    profiles: Vec<TileSourceProfile>,
+    active: usize,
+    /// Never load tiles over the network.
+    #[serde(default)]
+    pub offline: bool,
+}
+
+impl Default for SourceSettings {
+    /// MapTiler tiles if the API key is set, the sample tiles otherwise.
+    fn default() -> Self {
+        Self {
+            profiles: vec![TileSourceProfile::maptiler(), TileSourceProfile::sample()],
+            active: if env_api_key().is_some() { 0 } else { 1 },
+            offline: false,
+        }
+    }
+}
+
+impl SourceSettings {
+    pub fn active(&self) -> &TileSourceProfile {
+        &self.profiles[self.active]
+    }
+
+    /// Fixes settings loaded from the storage: falls back to the default settings if there are
+    /// no profiles and keeps the active profile in range.
+    pub fn validated(mut self) -> Self {
+        if self.profiles.is_empty() {
+            log::warn!("No tile source profiles saved, using the default ones");
+            return Self {
+                offline: self.offline,
+                ..Self::default()
+            };
+        }
+        if self.active >= self.profiles.len() {
+            log::warn!("Active tile source profile {} does not exist", self.active);
+            self.active = 0;
+        }
+
+        self
+    }
+}
+
+/// Window to manage the tile source profiles.
+#[derive(Debug, Default)]
+pub struct TileSourceWindow {
+    pub open: bool,
+    /// Profile being edited with its index, `None` for a new profile.
+    edited: Option<(Option<usize>, TileSourceProfile)>,
+    error: Option<String>,
+    /// Error of opening the active source.
+    source_error: Option<String>,
+}
+
+impl TileSourceWindow {
+    /// Sets the error of opening the active source. The window is opened to show it.
+    pub fn set_source_error(&mut self, error: Option<String>) {
+        self.open |= error.is_some();
+        self.source_error = error;
+    }
+
+    /// Shows the window. Returns `true` if the map must switch to the active source, because
+    /// another profile was activated or the active one was changed.
+    pub fn show(&mut self, ctx: &egui::Context, settings: &mut SourceSettings) -> bool {
+        let mut open = self.open;
+        let mut switch = false;
+        egui::Window::new("Tile sources")
+            .open(&mut open)
+            .default_width(450.0)
+            .show(ctx, |ui| switch = self.ui(ui, settings));
+        self.open = open;
+
+        switch
+    }
+
+    fn ui(&mut self, ui: &mut egui::Ui, settings: &mut SourceSettings) -> bool {
+        let mut switch = ui
+            .checkbox(&mut settings.offline, "Offline mode")
+            .on_hover_text("Use only local files and cached tiles, without network requests")
+            .changed();
+        ui.separator();
+
+        let mut activate = None;
+        let mut remove = None;
+        let can_remove = settings.profiles.len() > 1;
+        if let Some(error) = &self.source_error {
+            ui.colored_label(ui.visuals().error_fg_color, error);
+        }
+        for (index, profile) in settings.profiles.iter().enumerate() {
+            ui.horizontal(|ui| {
+                if ui
+                    .radio(settings.active == index, &profile.name)
+                    .on_hover_text(&profile.url_template)
+                    .clicked()
+                    && settings.active != index
+                {
+                    activate = Some(index);
+                }
+
+                if ui.small_button("Edit").clicked() {
+                    self.edited = Some((Some(index), profile.clone()));
+                    self.error = None;
+                }
+                if ui
+                    .add_enabled(can_remove, egui::Button::new("Del").small())
+                    .clicked()
+                {
+                    remove = Some(index);
+                }
+            });
+        }
+
+        if let Some(index) = activate {
+            settings.active = index;
+            switch = true;
+        }
+        if let Some(index) = remove {
+            settings.profiles.remove(index);
+            if settings.active == index {
+                settings.active = 0;
+                switch = true;
+            } else if settings.active > index {
+                settings.active -= 1;
+            }
+            if matches!(self.edited, Some((Some(edited), _)) if edited >= index) {
+                self.edited = None;
+            }
+        }
+
+        if ui.button("Add source").clicked() {
+            self.edited = Some((None, TileSourceProfile::empty()));
+            self.error = None;
+        }
+
+        let Some((index, profile)) = &mut self.edited else {
+            return switch;
+        };
+
+        ui.separator();
+        profile_ui(ui, profile);
+
+        if let Some(error) = &self.error {
+            ui.colored_label(ui.visuals().error_fg_color, error);
+        }
+
+        let (save, cancel) = ui
+            .horizontal(|ui| (ui.button("Save").clicked(), ui.button("Cancel").clicked()))
+            .inner;
+        if save {
+            if let Err(err) = profile.validate() {
+                self.error = Some(err);
+                return switch;
+            }
+
+            match *index {
+                Some(index) => {
+                    settings.profiles[index] = profile.clone();
+                    switch |= settings.active == index;
+                }
+                None => settings.profiles.push(profile.clone()),
+            }
+        }
+        if save || cancel {
+            self.edited = None;
+            self.error = None;
+        }
+
+        switch
+    }
+}
+
+fn profile_ui(ui: &mut egui::Ui, profile: &mut TileSourceProfile) {
+    Grid::new("tile source profile")
+        .num_columns(2)
+        .show(ui, |ui| {
+            ui.label("Name");
+            ui.text_edit_singleline(&mut profile.name);
+            ui.end_row();
+
+            ui.label("Type");
+            egui::ComboBox::from_id_salt("tile source kind")
+                .selected_text(profile.kind.to_string())
+                .show_ui(ui, |ui| {
+                    for kind in [
+                        SourceKind::Rest,
+                        SourceKind::MbTiles,
+                        SourceKind::PmTiles,
+                        SourceKind::Directory,
+                        SourceKind::Sample,
+                    ] {
+                        ui.selectable_value(&mut profile.kind, kind, kind.to_string());
+                    }
+                });
+            ui.end_row();
+
+            match profile.kind {
+                SourceKind::Rest => {
+                    ui.label("URL");
+                    ui.text_edit_singleline(&mut profile.url_template)
+                        .on_hover_text(
+                            "URL template with {z}, {x} and {y} placeholders, \
+                             or the URL of the TileJSON of the tiles",
+                        );
+                    ui.end_row();
+
+                    ui.label("API key");
+                    ui.vertical(|ui| {
+                        ui.add(egui::TextEdit::singleline(&mut profile.api_key).password(true));
+                        ui.checkbox(
+                            &mut profile.use_env_key,
+                            format!("Use {API_KEY_VAR} if the key is empty"),
+                        )
+                        .on_hover_text("The key is read from the environment and is not saved");
+                    });
+                    ui.end_row();
+
+                    ui.label("Key parameter");
+                    ui.text_edit_singleline(&mut profile.api_key_param);
+                    ui.end_row();
+                }
+                SourceKind::MbTiles => {
+                    ui.label("File");
+                    ui.horizontal(|ui| {
+                        ui.text_edit_singleline(&mut profile.path);
+                        #[cfg(not(target_arch = "wasm32"))]
+                        file_dialog_button(ui, &mut profile.path, "MBTiles Files", "mbtiles");
+                    });
+                    ui.end_row();
+                }
+                SourceKind::PmTiles => {
+                    ui.label("File or URL");
+                    ui.horizontal(|ui| {
+                        ui.text_edit_singleline(&mut profile.path)
+                            .on_hover_text("Path of a local archive or its http(s) URL");
+                        #[cfg(not(target_arch = "wasm32"))]
+                        file_dialog_button(ui, &mut profile.path, "PMTiles Files", "pmtiles");
+                    });
+                    ui.end_row();
+                }
+                SourceKind::Directory => {
+                    ui.label("Directory");
+                    ui.horizontal(|ui| {
+                        ui.text_edit_singleline(&mut profile.path)
+                            .on_hover_text("Directory with {z}/{x}/{y}.pbf or .mvt files");
+                        #[cfg(not(target_arch = "wasm32"))]
+                        directory_dialog_button(ui, &mut profile.path);
+                    });
+                    ui.end_row();
+                }
+                SourceKind::Sample => {}
+            }
+
+            ui.label("Attribution");
+            ui.text_edit_singleline(&mut profile.attribution);
+            ui.end_row();
+
+            ui.label("Attribution URL");
+            ui.text_edit_singleline(&mut profile.attribution_url);
+            ui.end_row();
+        });
+
+    if profile.kind == SourceKind::Rest {
+        key_values_ui(ui, "Query parameters", "query", &mut profile.query);
+        key_values_ui(ui, "Headers", "headers", &mut profile.headers);
+    }
+
+    egui::CollapsingHeader::new("Tile schema")
+        .id_salt("tile source schema")
+        .show(ui, |ui| {
+            schema_ui(ui, &mut profile.schema, !profile.kind.has_fixed_numbering())
+        });
+}
+
+/// Editor of the tile schema settings. Settings that are not checked are taken from the tile
+/// set metadata. The row order and origin are shown only if `numbering` is set.
+fn schema_ui(ui: &mut egui::Ui, schema: &mut TileSchemaSettings, numbering: bool) {
+    Grid::new("tile source schema grid")
+        .num_columns(2)
+        .show(ui, |ui| {
+            optional_ui(
+                ui,
+                "Tile size",
+                &mut schema.tile_size,
+                DEFAULT_TILE_SIZE,
+                |ui, size| {
+                    ui.add(egui::DragValue::new(size).range(64..=4096).suffix(" px"));
+                },
+            );
+            optional_ui(ui, "Min zoom", &mut schema.min_zoom, 0, |ui, zoom| {
+                ui.add(egui::DragValue::new(zoom).range(0..=MAX_ZOOM));
+            });
+            optional_ui(ui, "Max zoom", &mut schema.max_zoom, 14, |ui, zoom| {
+                ui.add(egui::DragValue::new(zoom).range(0..=MAX_ZOOM));
+            });
+            if numbering {
+                optional_ui(
+                    ui,
+                    "Row order",
+                    &mut schema.row_order,
+                    RowOrder::Xyz,
+                    |ui, order| {
+                        egui::ComboBox::from_id_salt("tile row order")
+                            .selected_text(order.to_string())
+                            .show_ui(ui, |ui| {
+                                for value in [RowOrder::Xyz, RowOrder::Tms] {
+                                    ui.selectable_value(order, value, value.to_string());
+                                }
+                            });
+                    },
+                );
+                optional_ui(
+                    ui,
+                    "Origin",
+                    &mut schema.origin,
+                    [-WORLD_HALF_SIZE, WORLD_HALF_SIZE],
+                    |ui, origin| {
+                        for value in origin {
+                            ui.add(egui::DragValue::new(value).speed(1000.0));
+                        }
+                    },
+                )
+                .on_hover_text("Corner where the tile numbering starts, in EPSG:3857 meters");
+            }
+            optional_ui(
+                ui,
+                "Bounds",
+                &mut schema.bounds,
+                [
+                    -WORLD_HALF_SIZE,
+                    -WORLD_HALF_SIZE,
+                    WORLD_HALF_SIZE,
+                    WORLD_HALF_SIZE,
+                ],
+                |ui, bounds| {
+                    for value in bounds {
+                        ui.add(egui::DragValue::new(value).speed(1000.0));
+                    }
+                },
+            )
+            .on_hover_text("Area with the tiles as x min, y min, x max, y max in EPSG:3857 meters");
+        });
+}
+
+/// Row of an optional setting: a checkbox to set the value and the editor of the value.
+fn optional_ui<T>(
+    ui: &mut egui::Ui,
+    label: &str,
+    value: &mut Option<T>,
+    default: T,
+    add_editor: impl FnOnce(&mut egui::Ui, &mut T),
+) -> egui::Response {
+    let response = ui.label(label);
+    ui.horizontal(|ui| {
+        let mut set = value.is_some();
+        if ui.checkbox(&mut set, "").changed() {
+            *value = set.then_some(default);
+        }
+        match value {
+            Some(value) => add_editor(ui, value),
+            None => {
+                ui.weak("auto");
+            }
+        }
+    });
+    ui.end_row();
+
+    response
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn file_dialog_button(ui: &mut egui::Ui, path: &mut String, filter: &str, extension: &str) {
+    if ui.button("Browse...").clicked() {
+        match native_dialog::FileDialog::new()
+            .add_filter(filter, &[extension])
+            .show_open_single_file()
+        {
+            Ok(Some(file)) => *path = file.display().to_string(),
+            Ok(None) => {}
+            Err(e) => log::error!("Failed to open file dialog: {e}"),
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn directory_dialog_button(ui: &mut egui::Ui, path: &mut String) {
+    if ui.button("Browse...").clicked() {
+        match native_dialog::FileDialog::new().show_open_single_dir() {
+            Ok(Some(dir)) => *path = dir.display().to_string(),
+            Ok(None) => {}
+            Err(e) => log::error!("Failed to open file dialog: {e}"),
+        }
+    }
+}
+
+/// Editor of a list of name-value pairs.
+fn key_values_ui(ui: &mut egui::Ui, title: &str, id: &str, values: &mut Vec<(String, String)>) {
+    ui.label(RichText::new(title).strong());
+    let mut remove = None;
+    Grid::new(("tile source", id))
+        .num_columns(3)
+        .show(ui, |ui| {
+            for (index, (key, value)) in values.iter_mut().enumerate() {
+                ui.add(egui::TextEdit::singleline(key).hint_text("name"));
+                ui.add(egui::TextEdit::singleline(value).hint_text("value"));
+                if ui.small_button("x").clicked() {
+                    remove = Some(index);
+                }
+                ui.end_row();
+            }
+        });
+
+    if let Some(index) = remove {
+        values.remove(index);
+    }
+    if ui.small_button("+").clicked() {
+        values.push((String::new(), String::new()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use galileo::tile_schema::VerticalDirection;
+    use galileo_types::cartesian::Point2;
+
+    use super::*;
+
+    #[test]
+    fn validate_profile() {
+        let mut profile = TileSourceProfile {
+            name: "Tiles".to_string(),
+            url_template: "https://example.com/{z}/{x}/{y}.pbf".to_string(),
+            ..TileSourceProfile::empty()
+        };
+        assert_eq!(profile.validate(), Ok(()));
+
+        profile.headers = vec![("bad header".to_string(), "value".to_string())];
+        assert!(profile.validate().is_err());
+
+        profile.headers.clear();
+        profile.url_template = "https://example.com/{z}/{x}.pbf".to_string();
+        assert!(profile.validate().is_err());
+
+        profile.url_template = "https://example.com/tiles.json".to_string();
+        assert_eq!(profile.validate(), Ok(()));
+        assert_eq!(
+            profile.tilejson_url(),
+            Some("https://example.com/tiles.json")
+        );
+
+        profile.schema.min_zoom = Some(15);
+        profile.schema.max_zoom = Some(10);
+        assert!(profile.validate().is_err());
+    }
+
+    #[test]
+    fn query_with_api_key() {
+        let profile = TileSourceProfile {
+            api_key: "secret".to_string(),
+            query: vec![
+                ("lang".to_string(), "en".to_string()),
+                (" ".to_string(), "skipped".to_string()),
+            ],
+            ..TileSourceProfile::maptiler()
+        };
+        assert_eq!(
+            profile.query_params(),
+            vec![
+                ("lang".to_string(), "en".to_string()),
+                ("key".to_string(), "secret".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn cache_dir_names() {
+        let maptiler = TileSourceProfile::maptiler();
+        let name = maptiler.cache_dir_name();
+        assert!(name.starts_with("api.maptiler.com-"), "{name}");
+
+        // The name of the profile does not matter, the URL does
+        let renamed = TileSourceProfile {
+            name: "MAPTILER V3".to_string(),
+            ..maptiler.clone()
+        };
+        assert_eq!(renamed.cache_dir_name(), name);
+        let other = TileSourceProfile {
+            url_template: "https://api.maptiler.com/tiles/v4/{z}/{x}/{y}.pbf".to_string(),
+            ..maptiler
+        };
+        assert_ne!(other.cache_dir_name(), name);
+
+        assert_eq!(fnv1a(b""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(fnv1a(b"a"), 0xaf63_dc4c_8601_ec8c);
+    }
+
+    #[test]
+    fn archives_keep_their_row_order() {
+        let schema = TileSchemaSettings {
+            row_order: Some(RowOrder::Tms),
+            origin: Some([0.0, 0.0]),
+            ..Default::default()
+        };
+        let rest = TileSourceProfile {
+            schema: schema.clone(),
+            ..TileSourceProfile::empty()
+        };
+        let mbtiles = TileSourceProfile {
+            kind: SourceKind::MbTiles,
+            schema,
+            ..TileSourceProfile::empty()
+        };
+
+        let info = TileSetInfo::default();
+        assert!(matches!(
+            rest.tile_schema(&info).y_direction,
+            VerticalDirection::BottomToTop
+        ));
+        let schema = mbtiles.tile_schema(&info);
+        assert!(matches!(schema.y_direction, VerticalDirection::TopToBottom));
+        assert_eq!(
+            schema.origin,
+            Point2::new(-WORLD_HALF_SIZE, WORLD_HALF_SIZE)
+        );
+    }
+
+    #[test]
+    fn validate_loaded_settings() {
+        let settings = SourceSettings {
+            profiles: vec![],
+            active: 3,
+            offline: true,
+        }
+        .validated();
+        assert!(!settings.profiles.is_empty());
+        assert!(settings.offline);
+        assert_eq!(
+            settings.active().name,
+            SourceSettings::default().active().name
+        );
+
+        let sample = TileSourceProfile::sample();
+        let settings = SourceSettings {
+            profiles: vec![sample.clone()],
+            active: 1,
+            offline: false,
+        }
+        .validated();
+        assert_eq!(settings.active(), &sample);
+    }
+}
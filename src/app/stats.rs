@@ -0,0 +1,184 @@
+//! Number of features drawn with each rule in the current view.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Arc,
+    time::Duration,
+};
+
+use galileo::{tile_schema::TileIndex, MapView, TileSchema};
+use galileo_mvt::MvtTile;
+use parking_lot::Mutex;
+
+use super::{
+    inspector::property_strings,
+    style::RuleMatcher,
+    tiles::{visible_tiles, TileStore},
+};
+
+/// Minimum time between two recalculations of the counts while the map is moving.
+const UPDATE_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Features drawn with a rule.
+#[derive(Debug, Clone, Default)]
+pub struct FeatureCount {
+    /// Number of features in the tiles of the current view.
+    pub in_view: usize,
+    /// Average number of features per tile at each zoom level, over all stored tiles of the zoom
+    /// level overlapping the view. The store keeps the most recently loaded tiles.
+    pub per_tile: BTreeMap<u32, f64>,
+}
+
+impl FeatureCount {
+    /// Returns the zoom level with the most features per tile.
+    pub fn densest_zoom(&self) -> Option<u32> {
+        self.per_tile
+            .iter()
+            .max_by(|a, b| a.1.total_cmp(b.1))
+            .map(|(z, _)| *z)
+    }
+}
+
+/// Counts the features drawn with each rule in the given tiles, which are the stored tiles
+/// overlapping the view. Returns counts by rule id.
+fn count_features(
+    tiles: &[(TileIndex, Arc<MvtTile>)],
+    visible: &[TileIndex],
+    matcher: &RuleMatcher,
+) -> HashMap<u64, FeatureCount> {
+    let mut counts: HashMap<u64, FeatureCount> = HashMap::new();
+    let mut tiles_per_zoom: BTreeMap<u32, usize> = BTreeMap::new();
+    for (index, tile) in tiles {
+        let in_view = visible.contains(index);
+        *tiles_per_zoom.entry(index.z).or_default() += 1;
+        for layer in &tile.layers {
+            for feature in &layer.features {
+                let Some(rule_id) = matcher.first_match(&layer.name, &property_strings(feature))
+                else {
+                    continue;
+                };
+
+                let count = counts.entry(rule_id).or_default();
+                *count.per_tile.entry(index.z).or_default() += 1.0;
+                if in_view {
+                    count.in_view += 1;
+                }
+            }
+        }
+    }
+
+    for count in counts.values_mut() {
+        for (z, total) in count.per_tile.iter_mut() {
+            *total /= tiles_per_zoom[z] as f64;
+        }
+    }
+
+    counts
+}
+
+/// Counts the features in the background and keeps the counts of the latest request.
+#[derive(Default)]
+pub struct FeatureCounter {
+    request: u64,
+    result: Arc<Mutex<Option<(u64, HashMap<u64, FeatureCount>)>>>,
+}
+
+impl FeatureCounter {
+    /// Starts counting the features of the stored tiles overlapping the view, see
+    /// [`count_features`]. The web has no threads, so the features are counted right away there.
+    pub fn start(
+        &mut self,
+        store: &TileStore,
+        view: &MapView,
+        schema: &TileSchema,
+        matcher: RuleMatcher,
+        ctx: &egui::Context,
+    ) {
+        self.request += 1;
+        let request = self.request;
+        let tiles = store.tiles_in_view(view, schema);
+        let visible = visible_tiles(view, schema);
+        let result = self.result.clone();
+        let ctx = ctx.clone();
+        let count = move || {
+            let counts = count_features(&tiles, &visible, &matcher);
+            let mut result = result.lock();
+            // Counts of an earlier request may be ready after the counts of a later one
+            if result
+                .as_ref()
+                .map_or(true, |(stored, _)| *stored < request)
+            {
+                *result = Some((request, counts));
+                ctx.request_repaint();
+            }
+        };
+
+        #[cfg(not(target_arch = "wasm32"))]
+        tokio::task::spawn_blocking(count);
+        #[cfg(target_arch = "wasm32")]
+        count();
+    }
+
+    /// Returns the counts of the latest request once they are ready.
+    pub fn take_counts(&mut self) -> Option<HashMap<u64, FeatureCount>> {
+        let (request, counts) = self.result.lock().take()?;
+        (request == self.request).then_some(counts)
+    }
+}
+
+/// Decides when the feature counts must be recalculated: when the view, the style or the loaded
+/// tiles change, but not more often than once in [`UPDATE_INTERVAL`].
+#[derive(Debug, Default)]
+pub struct StatsUpdater {
+    view: Option<[f64; 5]>,
+    tile_generation: u64,
+    style_changed: bool,
+    /// Time of the last update in seconds, as egui counts it. `Instant::now` panics in
+    /// the browser.
+    updated_at: Option<f64>,
+}
+
+impl StatsUpdater {
+    pub fn style_changed(&mut self) {
+        self.style_changed = true;
+    }
+
+    /// Returns `true` if the counts should be recalculated now. If something changed but the
+    /// last update was too recent, a repaint is requested to check again later.
+    pub fn needs_update(
+        &mut self,
+        view: &MapView,
+        tile_generation: u64,
+        ctx: &egui::Context,
+    ) -> bool {
+        let view_key = view.get_bbox().map(|bbox| {
+            [
+                view.resolution(),
+                bbox.x_min(),
+                bbox.y_min(),
+                bbox.x_max(),
+                bbox.y_max(),
+            ]
+        });
+        let changed =
+            self.style_changed || self.tile_generation != tile_generation || self.view != view_key;
+        if !changed {
+            return false;
+        }
+
+        let now = ctx.input(|i| i.time);
+        if let Some(updated_at) = self.updated_at {
+            let elapsed = Duration::from_secs_f64((now - updated_at).max(0.0));
+            if elapsed < UPDATE_INTERVAL {
+                ctx.request_repaint_after(UPDATE_INTERVAL - elapsed);
+                return false;
+            }
+        }
+
+        self.view = view_key;
+        self.tile_generation = tile_generation;
+        self.style_changed = false;
+        self.updated_at = Some(now);
+        true
+    }
+}
@@ -0,0 +1,251 @@
+//! Status bar under the map with the zoom level, the cursor position and a scale bar.
+
+use egui::{Align2, FontId, Stroke};
+use galileo_types::cartesian::{CartesianPoint2d, Point2};
+
+use super::{tiles::MAX_LATITUDE, viewport::resolution_zoom};
+
+const EARTH_RADIUS: f64 = 6378137.0;
+/// Maximum width of the scale bar in points.
+const SCALE_BAR_WIDTH: f32 = 100.0;
+
+/// Position the map is asked to move to.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GoTo {
+    pub lat: f64,
+    pub lon: f64,
+    /// Style zoom level, as in MapLibre styles. The resolution of the map is kept if it is not given.
+    pub zoom: Option<f64>,
+}
+
+/// What the status bar shows.
+pub struct MapStatus {
+    /// Meters per pixel.
+    pub resolution: f64,
+    /// Zoom level of the tiles the map shows.
+    pub tile_zoom: Option<u32>,
+    /// Position of the cursor over the map in EPSG:3857 meters.
+    pub cursor: Option<Point2>,
+    /// Latitude of the center of the map.
+    pub center_lat: Option<f64>,
+}
+
+#[derive(Debug, Default)]
+pub struct StatusBar {
+    go_to_text: String,
+    go_to_error: Option<String>,
+}
+
+impl StatusBar {
+    /// Shows the status bar. Returns the position entered in the "Go to" field.
+    pub fn show(&mut self, ctx: &egui::Context, status: MapStatus) -> Option<GoTo> {
+        egui::TopBottomPanel::bottom("status_bar")
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    let go_to = self.go_to_ui(ui);
+                    ui.separator();
+                    status_ui(ui, &status);
+                    go_to
+                })
+                .inner
+            })
+            .inner
+    }
+
+    fn go_to_ui(&mut self, ui: &mut egui::Ui) -> Option<GoTo> {
+        ui.label("Go to");
+        let response = ui.add(
+            egui::TextEdit::singleline(&mut self.go_to_text)
+                .hint_text("lat, lon, zoom")
+                .desired_width(160.0),
+        );
+        let submitted = response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+        if !(submitted || ui.button("Go").clicked()) {
+            if let Some(error) = &self.go_to_error {
+                ui.colored_label(ui.visuals().error_fg_color, error);
+            }
+            return None;
+        }
+
+        match parse_go_to(&self.go_to_text) {
+            Ok(go_to) => {
+                self.go_to_error = None;
+                Some(go_to)
+            }
+            Err(err) => {
+                self.go_to_error = Some(err);
+                None
+            }
+        }
+    }
+}
+
+fn status_ui(ui: &mut egui::Ui, status: &MapStatus) {
+    ui.label(format!("Zoom {:.2}", resolution_zoom(status.resolution)))
+        .on_hover_text(format!(
+            "Style zoom level, as in MapLibre styles and bookmarks: {:.3} m per pixel",
+            status.resolution
+        ));
+
+    if let Some(z) = status.tile_zoom {
+        ui.label(format!("Tiles z{z}"))
+            .on_hover_text("Zoom level of the tiles shown on the map");
+    }
+
+    ui.separator();
+    match status.cursor.map(to_lat_lon) {
+        Some((lat, lon)) => ui.monospace(format!("{lat:>9.5}, {lon:>10.5}")),
+        None => ui.monospace(format!("{:>21}", "")),
+    };
+
+    if let Some(lat) = status.center_lat {
+        ui.separator();
+        // Web mercator stretches the map by 1 / cos(lat), and the map is drawn in pixels
+        let meters_per_point =
+            status.resolution * lat.to_radians().cos() * ui.ctx().pixels_per_point() as f64;
+        scale_bar_ui(ui, meters_per_point);
+    }
+}
+
+/// Draws a bar of a round length with the length as the label.
+fn scale_bar_ui(ui: &mut egui::Ui, meters_per_point: f64) {
+    let Some((meters, width)) = scale_bar(meters_per_point, SCALE_BAR_WIDTH as f64) else {
+        return;
+    };
+    let label = if meters >= 1000.0 {
+        format!("{} km", meters / 1000.0)
+    } else {
+        format!("{meters} m")
+    };
+
+    let height = ui.spacing().interact_size.y;
+    let (rect, _) = ui.allocate_exact_size(
+        egui::vec2(SCALE_BAR_WIDTH + 40.0, height),
+        egui::Sense::hover(),
+    );
+    let painter = ui.painter();
+    let stroke = Stroke::new(1.5, ui.visuals().text_color());
+    let left = rect.left() + 2.0;
+    let right = left + width as f32;
+    let bottom = rect.bottom() - 3.0;
+    let tick = height / 3.0;
+    painter.line_segment(
+        [egui::pos2(left, bottom), egui::pos2(right, bottom)],
+        stroke,
+    );
+    for x in [left, right] {
+        painter.line_segment(
+            [egui::pos2(x, bottom), egui::pos2(x, bottom - tick)],
+            stroke,
+        );
+    }
+    painter.text(
+        egui::pos2(right + 4.0, bottom),
+        Align2::LEFT_BOTTOM,
+        label,
+        FontId::proportional(11.0),
+        ui.visuals().text_color(),
+    );
+}
+
+/// Returns the longest round length in meters that fits into `max_width` points, with its width.
+fn scale_bar(meters_per_point: f64, max_width: f64) -> Option<(f64, f64)> {
+    if !(meters_per_point.is_finite() && meters_per_point > 0.0) {
+        return None;
+    }
+
+    let max_meters = meters_per_point * max_width;
+    let magnitude = 10f64.powi(max_meters.log10().floor() as i32);
+    let meters = [5.0, 2.0, 1.0]
+        .into_iter()
+        .map(|factor| factor * magnitude)
+        .find(|meters| *meters <= max_meters)?;
+
+    Some((meters, meters / meters_per_point))
+}
+
+/// Converts EPSG:3857 coordinates to latitude and longitude in degrees.
+fn to_lat_lon(point: Point2) -> (f64, f64) {
+    let lon = (point.x() / EARTH_RADIUS).to_degrees();
+    let lat =
+        (2.0 * (point.y() / EARTH_RADIUS).exp().atan() - std::f64::consts::FRAC_PI_2).to_degrees();
+    (lat, lon)
+}
+
+/// Parses `lat, lon` or `lat, lon, zoom`. Numbers may be separated by commas or spaces.
+fn parse_go_to(text: &str) -> Result<GoTo, String> {
+    let numbers = text
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            part.parse::<f64>()
+                .map_err(|_| format!("\"{part}\" is not a number"))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let (lat, lon, zoom) = match numbers[..] {
+        [lat, lon] => (lat, lon, None),
+        [lat, lon, zoom] => (lat, lon, Some(zoom)),
+        _ => return Err("Enter the latitude, the longitude and optionally the zoom".into()),
+    };
+    // The web mercator map ends before the poles
+    if !(-MAX_LATITUDE..=MAX_LATITUDE).contains(&lat) {
+        return Err("The latitude must be between -85.05 and 85.05".into());
+    }
+    if !(-180.0..=180.0).contains(&lon) {
+        return Err("The longitude must be between -180 and 180".into());
+    }
+    if zoom.is_some_and(|zoom| !(0.0..=30.0).contains(&zoom)) {
+        return Err("The zoom must be between 0 and 30".into());
+    }
+
+    Ok(GoTo { lat, lon, zoom })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scale_bar_lengths() {
+        assert_eq!(scale_bar(1.0, 100.0), Some((100.0, 100.0)));
+        assert_eq!(scale_bar(3.0, 100.0), Some((200.0, 200.0 / 3.0)));
+        assert_eq!(scale_bar(0.3, 100.0), Some((20.0, 20.0 / 0.3)));
+        assert_eq!(scale_bar(60.0, 100.0), Some((5000.0, 5000.0 / 60.0)));
+        assert_eq!(scale_bar(0.0, 100.0), None);
+    }
+
+    #[test]
+    fn lat_lon_of_projected_point() {
+        let (lat, lon) = to_lat_lon(Point2::new(20037508.342789244, 0.0));
+        assert!((lat - 0.0).abs() < 1e-9 && (lon - 180.0).abs() < 1e-9);
+
+        let (lat, _) = to_lat_lon(Point2::new(0.0, 1118889.9748579594));
+        assert!((lat - 10.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn go_to_input() {
+        assert_eq!(
+            parse_go_to("55.75, 37.62"),
+            Ok(GoTo {
+                lat: 55.75,
+                lon: 37.62,
+                zoom: None
+            })
+        );
+        assert_eq!(
+            parse_go_to(" 55.75 37.62  12.5"),
+            Ok(GoTo {
+                lat: 55.75,
+                lon: 37.62,
+                zoom: Some(12.5)
+            })
+        );
+        assert!(parse_go_to("55.75").is_err());
+        assert!(parse_go_to("95, 37").is_err());
+        assert!(parse_go_to("-89, 37").is_err());
+        assert!(parse_go_to("85.05, 37").is_ok());
+        assert!(parse_go_to("55, east").is_err());
+    }
+}
@@ -1,22 +1,95 @@
 use std::{
+    collections::HashMap,
     fmt::Formatter,
+    ops::RangeInclusive,
     time::{Duration, Instant},
 };
 
-use egui::{CollapsingHeader, Color32, ComboBox, DragValue};
+use egui::{
+    collapsing_header::CollapsingState, Color32, ComboBox, DragValue, RichText, Stroke, StrokeKind,
+};
 use galileo::{
-    Color, layer::vector_tile_layer::style::{
-        PropertyFilter, PropertyFilterOperator, StyleRule, VectorTileLabelSymbol,
-        VectorTileLineSymbol, VectorTilePointSymbol, VectorTilePolygonSymbol, VectorTileSymbol,
-    }, render::text::TextStyle
+    layer::vector_tile_layer::style::{
+        PropertyFilter, StyleRule, VectorTileLabelSymbol, VectorTileLineSymbol,
+        VectorTilePointSymbol, VectorTilePolygonSymbol, VectorTileSymbol,
+    },
+    render::text::TextStyle,
+    Color,
 };
 use itertools::Itertools;
 use serde::{Deserialize, Serialize};
 
-use super::VectorTileStyle;
+use bulk::BulkEditState;
+use filter::FilterCondition;
+use group::RuleGroup;
+use label::{
+    enum_combo, LabelHorizontalAlignment, LabelStyle, LabelVerticalAlignment, LabelWeight,
+};
+
+use super::{catalog::TileCatalog, stats::FeatureCount, VectorTileStyle};
+
+mod analysis;
+mod bulk;
+mod clipboard;
+mod completion;
+mod filter;
+mod group;
+mod label;
+mod lookup;
+#[cfg(test)]
+mod test_utils;
+
+pub(crate) use label::default_font_family;
+pub use lookup::{can_filter_by, RuleMatch, RuleMatcher};
 
 const UPDATE_TIMEOUT: Duration = Duration::from_millis(100);
 
+/// Opacity of the rules that are not soloed when they are dimmed in the solo mode.
+const SOLO_DIM_OPACITY: f32 = 0.2;
+
+/// Sizes of point and line symbols the editor allows.
+const SYMBOL_SIZE_RANGE: RangeInclusive<f64> = 0.0..=20.0;
+/// Label font sizes the editor allows.
+const LABEL_SIZE_RANGE: RangeInclusive<f64> = 1.0..=72.0;
+
+/// Asks the user for a MapTiler style file and converts it. Errors are logged.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn pick_maptiler_style() -> Option<crate::maptiler_style::ConvertedStyle> {
+    match native_dialog::FileDialog::new()
+        .add_filter("JSON Files", &["json"])
+        .show_open_single_file()
+    {
+        Ok(Some(path)) => match std::fs::read_to_string(&path) {
+            Ok(json_content) => {
+                match serde_json::from_str::<crate::maptiler_style::Style>(&json_content) {
+                    Ok(maptiler_style) => {
+                        log::info!("Successfully loaded MapTiler style from {:?}", path);
+                        Some(crate::maptiler_style::convert_maptiler_style(
+                            &maptiler_style,
+                        ))
+                    }
+                    Err(e) => {
+                        log::error!("Failed to parse MapTiler style: {}", e);
+                        None
+                    }
+                }
+            }
+            Err(e) => {
+                log::error!("Failed to read file: {}", e);
+                None
+            }
+        },
+        Ok(None) => {
+            // User cancelled the dialog
+            None
+        }
+        Err(e) => {
+            log::error!("Failed to open file dialog: {}", e);
+            None
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StyleWindow {
     is_changed: bool,
@@ -25,6 +98,35 @@ pub struct StyleWindow {
     background_color: egui::Color32,
     rules: Vec<Rule>,
     last_rule_id: u64,
+    #[serde(default)]
+    groups: Vec<RuleGroup>,
+    #[serde(skip)]
+    search: String,
+    #[serde(skip)]
+    bulk_edit: BulkEditState,
+    #[serde(skip)]
+    new_group_name: String,
+    #[serde(skip)]
+    show_paste: bool,
+    #[serde(skip)]
+    paste_text: String,
+    #[serde(skip)]
+    paste_error: Option<String>,
+    /// Warnings of the rule analysis by rule id. Cleared when the rules change.
+    #[serde(skip)]
+    analysis: Option<HashMap<u64, Vec<String>>>,
+    /// Features drawn with each rule in the current view, by rule id.
+    #[serde(skip)]
+    feature_counts: Option<HashMap<u64, FeatureCount>>,
+    /// In the solo mode, draw the other rules dimmed instead of hiding them.
+    #[serde(skip)]
+    dim_unsoloed: bool,
+    /// View of the last loaded MapTiler style, until the map is moved to it.
+    #[serde(skip)]
+    style_view: Option<crate::maptiler_style::StyleView>,
+    /// Style as it was imported from MapTiler, to compare the edited style with.
+    #[serde(skip)]
+    imported_style: Option<VectorTileStyle>,
 }
 
 impl StyleWindow {
@@ -45,17 +147,29 @@ impl StyleWindow {
             background_color: to_egui_color(style.background),
             rules,
             last_rule_id: last_id,
+            groups: vec![],
+            search: String::new(),
+            bulk_edit: BulkEditState::default(),
+            new_group_name: String::new(),
+            show_paste: false,
+            paste_text: String::new(),
+            paste_error: None,
+            analysis: None,
+            feature_counts: None,
+            dim_unsoloed: false,
+            style_view: None,
+            imported_style: None,
         }
     }
 
-    pub fn show(&mut self, ctx: &egui::Context) -> &mut Self {
+    pub fn show(&mut self, ctx: &egui::Context, catalog: &TileCatalog) -> &mut Self {
         egui::Window::new("Layer Style")
             .resizable([false, true])
             .default_width(300.0)
             .default_height(600.0)
             .max_width(300.0)
             .scroll([false, true])
-            .show(ctx, |ui| self.ui(ctx, ui));
+            .show(ctx, |ui| self.ui(ctx, ui, catalog));
 
         self
     }
@@ -64,9 +178,55 @@ impl StyleWindow {
         self.is_changed
     }
 
+    /// Sets the number of features drawn with each rule, shown next to the rule headers.
+    pub fn set_feature_counts(&mut self, counts: HashMap<u64, FeatureCount>) {
+        self.feature_counts = Some(counts);
+    }
+
     pub fn style(&self) -> VectorTileStyle {
         VectorTileStyle {
-            rules: self.rules.iter().map(Rule::get_rule).collect(),
+            rules: self
+                .rules
+                .iter()
+                .filter(|rule| rule.visible)
+                .map(Rule::get_rule)
+                .collect(),
+            background: to_galileo_color(self.background_color),
+        }
+    }
+
+    /// Returns `true` if some rules are soloed.
+    fn is_solo(&self) -> bool {
+        self.rules.iter().any(|rule| rule.solo)
+    }
+
+    /// Returns the style to draw the map with. In the solo mode only the soloed rules are drawn,
+    /// and the other visible rules are dimmed or hidden. Hidden rules are kept without a symbol,
+    /// so that the features they apply to are not drawn with a later rule. The edited style is
+    /// not changed.
+    pub fn layer_style(&self) -> VectorTileStyle {
+        if !self.is_solo() {
+            return self.style();
+        }
+
+        VectorTileStyle {
+            rules: self
+                .rules
+                .iter()
+                .filter(|rule| rule.visible || rule.solo)
+                .map(|rule| {
+                    if rule.solo {
+                        rule.get_rule()
+                    } else if self.dim_unsoloed {
+                        rule.dimmed().get_rule()
+                    } else {
+                        StyleRule {
+                            symbol: VectorTileSymbol::None,
+                            ..rule.get_rule()
+                        }
+                    }
+                })
+                .collect(),
             background: to_galileo_color(self.background_color),
         }
     }
@@ -84,43 +244,62 @@ impl StyleWindow {
             .collect();
         self.last_rule_id = last_id;
         self.background_color = to_egui_color(style.background);
+        self.groups.clear();
         self.mark_changed(ctx);
     }
 
-    fn ui(&mut self, ctx: &egui::Context, ui: &mut egui::Ui) {
+    /// Load a style converted from MapTiler, replacing the current one. Rules are put into the
+    /// groups defined by the source style.
+    pub fn load_maptiler_style(
+        &mut self,
+        converted: crate::maptiler_style::ConvertedStyle,
+        ctx: &egui::Context,
+    ) {
+        let mut last_id = 0;
+        self.rules = converted
+            .rules
+            .iter()
+            .map(|converted_rule| {
+                last_id += 1;
+                let mut rule = Rule::new(&converted_rule.rule, last_id);
+                rule.name = converted_rule.layer_id.clone();
+                rule.group = converted
+                    .layer_group(&converted_rule.layer_id)
+                    .map(|group| group.id.clone());
+                rule
+            })
+            .collect();
+        self.groups = converted
+            .groups
+            .iter()
+            .map(|group| RuleGroup {
+                id: group.id.clone(),
+                name: group.name.clone(),
+            })
+            .collect();
+        self.last_rule_id = last_id;
+        self.background_color = to_egui_color(converted.background);
+        self.style_view = converted.view;
+        self.imported_style = Some(converted.into());
+        self.mark_changed(ctx);
+    }
+
+    /// Returns the view of the MapTiler style loaded since the last call, if the style has one.
+    pub fn take_style_view(&mut self) -> Option<crate::maptiler_style::StyleView> {
+        self.style_view.take()
+    }
+
+    /// Returns the style of the last MapTiler import as it was before editing.
+    pub fn imported_style(&self) -> Option<&VectorTileStyle> {
+        self.imported_style.as_ref()
+    }
+
+    fn ui(&mut self, ctx: &egui::Context, ui: &mut egui::Ui, catalog: &TileCatalog) {
         // Load style button
         #[cfg(not(target_arch = "wasm32"))]
         if ui.button("Load MapTiler Style...").clicked() {
-            match native_dialog::FileDialog::new()
-                .add_filter("JSON Files", &["json"])
-                .show_open_single_file()
-            {
-                Ok(Some(path)) => match std::fs::read_to_string(&path) {
-                    Ok(json_content) => {
-                        match serde_json::from_str::<crate::maptiler_style::Style>(&json_content) {
-                            Ok(maptiler_style) => {
-                                let galileo_style =
-                                    crate::maptiler_style::convert_maptiler_to_galileo(
-                                        &maptiler_style,
-                                    );
-                                self.load_style(galileo_style, ctx);
-                                log::info!("Successfully loaded MapTiler style from {:?}", path);
-                            }
-                            Err(e) => {
-                                log::error!("Failed to parse MapTiler style: {}", e);
-                            }
-                        }
-                    }
-                    Err(e) => {
-                        log::error!("Failed to read file: {}", e);
-                    }
-                },
-                Ok(None) => {
-                    // User cancelled the dialog
-                }
-                Err(e) => {
-                    log::error!("Failed to open file dialog: {}", e);
-                }
+            if let Some(converted) = pick_maptiler_style() {
+                self.load_maptiler_style(converted, ctx);
             }
         }
 
@@ -140,13 +319,62 @@ impl StyleWindow {
 
         ui.label("Rules");
 
-        let mut ui_action = None;
-        for (index, rule) in self.rules.iter_mut().enumerate() {
-            let action = rule.ui(ui).action();
-            if action != RuleAction::None {
-                ui_action = Some((index, action));
+        ui.horizontal(|ui| {
+            ui.label("Search");
+            ui.text_edit_singleline(&mut self.search);
+            if ui.small_button("x").clicked() {
+                self.search.clear();
+            }
+        });
+
+        ui.horizontal(|ui| {
+            if ui.button("Select shown").clicked() {
+                let search = self.search.clone();
+                for rule in &mut self.rules {
+                    rule.selected = rule.matches_search(&search);
+                }
+            }
+            if ui.button("Clear selection").clicked() {
+                for rule in &mut self.rules {
+                    rule.selected = false;
+                }
             }
+            if ui
+                .button("Solo selected")
+                .on_hover_text("Draw only the selected rules")
+                .clicked()
+            {
+                for rule in &mut self.rules {
+                    rule.solo = rule.selected;
+                }
+                self.mark_changed(ctx);
+            }
+        });
+
+        self.solo_ui(ctx, ui);
+
+        let selected_count = self.rules.iter().filter(|rule| rule.selected).count();
+        if selected_count > 0 {
+            let target_count = self.bulk_targets().count();
+            let hidden_count = selected_count - target_count;
+            if let Some(action) = self
+                .bulk_edit
+                .ui(ui, target_count, hidden_count, &self.groups)
+            {
+                self.apply_bulk_action(action);
+                self.mark_changed(ctx);
+            }
+        }
+
+        ui.separator();
+
+        if self.analysis.is_none() {
+            self.analysis = Some(self.analyze_rules());
+        }
+        for rule in &mut self.rules {
+            rule.hovered = false;
         }
+        let ui_action = self.rule_list_ui(ui, catalog);
 
         if let Some((index, action)) = ui_action {
             match action {
@@ -159,6 +387,25 @@ impl StyleWindow {
                 RuleAction::Remove => {
                     self.rules.remove(index);
                 }
+                RuleAction::Drop { rule_id, before } => {
+                    self.drop_rule(rule_id, index, before);
+                }
+                RuleAction::GroupChanged => {
+                    self.keep_group_together(index);
+                }
+                RuleAction::Duplicate => {
+                    let mut rule = self.rules[index].clone();
+                    rule.id = self.next_rule_id();
+                    if !rule.name.is_empty() {
+                        rule.name.push_str(" copy");
+                    }
+                    rule.selected = false;
+                    rule.solo = false;
+                    self.rules.insert(index + 1, rule);
+                }
+                RuleAction::Copy => {
+                    Self::copy_rules(ctx, std::iter::once(&self.rules[index]));
+                }
                 _ => {}
             }
 
@@ -174,9 +421,38 @@ impl StyleWindow {
             }
         });
 
+        self.groups_ui(ui);
+        self.clipboard_ui(ctx, ui);
+
         self.update_changed();
     }
 
+    fn solo_ui(&mut self, ctx: &egui::Context, ui: &mut egui::Ui) {
+        let solo_count = self.rules.iter().filter(|rule| rule.solo).count();
+        if solo_count == 0 {
+            return;
+        }
+
+        ui.horizontal(|ui| {
+            ui.colored_label(
+                ui.visuals().warn_fg_color,
+                format!("Solo: {solo_count} rules"),
+            );
+            if ui
+                .checkbox(&mut self.dim_unsoloed, "Dim other rules")
+                .changed()
+            {
+                self.mark_changed(ctx);
+            }
+            if ui.button("Exit solo").clicked() {
+                for rule in &mut self.rules {
+                    rule.solo = false;
+                }
+                self.mark_changed(ctx);
+            }
+        });
+    }
+
     fn next_rule_id(&mut self) -> u64 {
         self.last_rule_id += 1;
         self.last_rule_id
@@ -193,6 +469,7 @@ impl StyleWindow {
         if timed_out {
             self.is_changed = true;
             self.last_changed_at = None;
+            self.analysis = None;
         }
     }
 
@@ -214,9 +491,52 @@ fn to_galileo_color(color: Color32) -> Color {
     Color::rgba(color.r(), color.g(), color.b(), color.a())
 }
 
+/// Data from outside of a rule shown in the rule editor.
+struct RuleContext<'a> {
+    groups: &'a [RuleGroup],
+    catalog: &'a TileCatalog,
+    /// Warnings of the rule list analysis for the rule.
+    issues: &'a [String],
+    /// Some rules are soloed.
+    solo: bool,
+    /// Features drawn with the rule in the view, `None` if there are none.
+    feature_count: Option<&'a FeatureCount>,
+    /// Feature counts have been calculated, so a missing count means zero.
+    show_feature_count: bool,
+}
+
+/// Shows the number of features drawn with a rule in the view, with the density by zoom level
+/// on hover.
+fn feature_count_badge(ui: &mut egui::Ui, count: Option<&FeatureCount>) {
+    let in_view = count.map(|count| count.in_view).unwrap_or_default();
+    let mut text = RichText::new(in_view.to_string())
+        .small()
+        .background_color(ui.visuals().faint_bg_color);
+    if in_view == 0 {
+        text = text.weak();
+    }
+
+    let mut hover_text = format!("{in_view} features drawn in the view");
+    if let Some(count) = count.filter(|count| !count.per_tile.is_empty()) {
+        hover_text.push_str("\nAverage over the loaded tiles overlapping the view:");
+        for (z, per_tile) in &count.per_tile {
+            hover_text.push_str(&format!("\nz{z}: {per_tile:.1} per tile"));
+        }
+        if let Some(z) = count.densest_zoom() {
+            hover_text.push_str(&format!("\nDensest at z{z}"));
+        }
+    }
+
+    ui.label(text).on_hover_text(hover_text);
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct Rule {
     id: u64,
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    note: String,
     layer_name: String,
     filter: String,
     color: Color32,
@@ -226,15 +546,55 @@ struct Rule {
     halo_width: f32,
     halo_color: Color32,
     pattern: String,
+    #[serde(default = "label::default_font_family")]
+    font_family: Vec<String>,
+    #[serde(default)]
+    font_weight: LabelWeight,
+    #[serde(default)]
+    font_style: LabelStyle,
+    #[serde(default)]
+    horizontal_alignment: LabelHorizontalAlignment,
+    #[serde(default)]
+    vertical_alignment: LabelVerticalAlignment,
+    #[serde(skip)]
+    pattern_token: String,
+    #[serde(default = "default_visible")]
+    visible: bool,
+    #[serde(skip)]
+    selected: bool,
+    #[serde(default)]
+    group: Option<String>,
+    #[serde(skip)]
+    highlighted: bool,
+    #[serde(skip)]
+    reveal: bool,
+    /// The mouse cursor is over the rule header.
+    #[serde(skip)]
+    hovered: bool,
+    /// The rule is drawn in the solo mode.
+    #[serde(skip)]
+    solo: bool,
+}
+
+fn default_visible() -> bool {
+    true
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 enum RuleAction {
     None,
     Modified,
+    /// The rule was moved to another group.
+    GroupChanged,
     MoveUp,
     MoveDown,
     Remove,
+    Duplicate,
+    Copy,
+    Drop {
+        rule_id: u64,
+        before: bool,
+    },
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -278,32 +638,37 @@ impl Rule {
             _ => (to_egui_color(Color::TRANSPARENT), 0.0, SymbolType::None),
         };
 
-        let (halo_color, halo_width, pattern) = match &style_rule.symbol {
-            VectorTileSymbol::Label(s) => (
-                to_egui_color(s.text_style.outline_color),
-                s.text_style.outline_width,
-                s.pattern.clone(),
-            ),
-            _ => (Color32::WHITE, 2.0, String::new()),
-        };
-
-        Self {
-            id,
+        let mut rule = Self {
             layer_name: style_rule.layer_name.clone().unwrap_or_default(),
             filter,
             color,
             size,
             symbol_type,
-            action: RuleAction::None,
-            halo_color,
-            halo_width,
-            pattern,
+            ..Self::new_empty(id)
+        };
+
+        if let VectorTileSymbol::Label(s) = &style_rule.symbol {
+            let text_style = &s.text_style;
+            rule.halo_color = to_egui_color(text_style.outline_color);
+            rule.halo_width = text_style.outline_width;
+            rule.pattern = s.pattern.clone();
+            rule.font_family = text_style.font_family.clone();
+            rule.font_weight = LabelWeight::from_galileo(text_style.weight);
+            rule.font_style = LabelStyle::from_galileo(text_style.style);
+            rule.horizontal_alignment =
+                LabelHorizontalAlignment::from_galileo(text_style.horizontal_alignment);
+            rule.vertical_alignment =
+                LabelVerticalAlignment::from_galileo(text_style.vertical_alignment);
         }
+
+        rule
     }
 
     fn new_empty(id: u64) -> Self {
         Self {
             id,
+            name: String::new(),
+            note: String::new(),
             layer_name: String::from(""),
             filter: String::from(""),
             color: Color32::from_rgba_unmultiplied(0, 0, 0, 0),
@@ -313,6 +678,28 @@ impl Rule {
             halo_color: to_egui_color(Color::WHITE),
             halo_width: 2.0,
             pattern: String::new(),
+            font_family: label::default_font_family(),
+            font_weight: LabelWeight::default(),
+            font_style: LabelStyle::default(),
+            horizontal_alignment: LabelHorizontalAlignment::default(),
+            vertical_alignment: LabelVerticalAlignment::default(),
+            pattern_token: String::new(),
+            visible: true,
+            selected: false,
+            group: None,
+            highlighted: false,
+            reveal: false,
+            hovered: false,
+            solo: false,
+        }
+    }
+
+    /// Returns a copy of the rule with translucent colors.
+    fn dimmed(&self) -> Self {
+        Self {
+            color: self.color.gamma_multiply(SOLO_DIM_OPACITY),
+            halo_color: self.halo_color.gamma_multiply(SOLO_DIM_OPACITY),
+            ..self.clone()
         }
     }
 
@@ -334,29 +721,26 @@ impl Rule {
             SymbolType::Polygon => VectorTileSymbol::Polygon(VectorTilePolygonSymbol {
                 fill_color: to_galileo_color(self.color),
             }),
-            SymbolType::Label => {
-                VectorTileSymbol::Label(VectorTileLabelSymbol {
+            SymbolType::Label => VectorTileSymbol::Label(VectorTileLabelSymbol {
                 text_style: TextStyle {
-                    font_family: vec![
-                        "Noto Sans".to_string(),
-                        "Noto Sans CJK JP".to_string(),
-                        "Noto Sans CJK KR".to_string(),
-                        "Noto Sans CJK SC".to_string(),
-                        "Noto Sans CJK TC".to_string(),
-                        "Noto Sans KR".to_string(),
-                        "Noto Sans JP".to_string(),
-                    ],
+                    font_family: self
+                        .font_family
+                        .iter()
+                        .map(|family| family.trim())
+                        .filter(|family| !family.is_empty())
+                        .map(str::to_string)
+                        .collect(),
                     font_size: self.size as f32,
                     font_color: to_galileo_color(self.color),
-                    horizontal_alignment: Default::default(),
-                    vertical_alignment: Default::default(),
-                    weight: galileo::render::text::FontWeight::BOLD,
-                    style: Default::default(),
+                    horizontal_alignment: self.horizontal_alignment.to_galileo(),
+                    vertical_alignment: self.vertical_alignment.to_galileo(),
+                    weight: self.font_weight.to_galileo(),
+                    style: self.font_style.to_galileo(),
                     outline_width: self.halo_width,
                     outline_color: to_galileo_color(self.halo_color),
                 },
                 pattern: self.pattern.clone(),
-            })},
+            }),
         };
 
         StyleRule {
@@ -367,49 +751,22 @@ impl Rule {
     }
 
     fn parse_filter(&self) -> Option<Vec<PropertyFilter>> {
-        let split = self.filter.split("&&");
-        let mut properties = vec![];
-        let operators = [
-            "==",
-            "!=",
-            ">",
-            "<",
-            ">=",
-            "<=",
-            " not in ",
-            " in ",
-            "exist",
-            "not exist",
-        ];
-        for block in split {
-            for operator in operators {
-                if block.contains(operator) {
-                    let blocks: Vec<&str> = block.split(operator).map(|v| v.trim()).collect();
-                    if blocks.len() != 2 {
-                        eprintln!("Invalid filter block: {}", block);
-                        return None;
-                    }
-
-                    let operator = operator.trim();
-                    let value = if operator == "in" || operator == "not in" {
-                        blocks[1].trim().trim_matches(&['[', ']'][..])
-                    } else {
-                        blocks[1]
-                    };
-
-                    let Some(operator) = PropertyFilterOperator::from_str(operator, value) else {
-                        eprintln!("Invalid operator in filter block: {}", block);
-                        return None;
-                    };
+        let conditions = match filter::parse_conditions(&self.filter) {
+            Ok(conditions) => conditions,
+            Err(err) => {
+                log::warn!("{err}");
+                return None;
+            }
+        };
 
-                    properties.push(PropertyFilter {
-                        property_name: blocks[0].to_string(),
-                        operator,
-                    });
+        let mut properties = vec![];
+        for condition in conditions {
+            let Some(property_filter) = condition.to_property_filter() else {
+                log::warn!("Invalid operator in filter block: {condition}");
+                return None;
+            };
 
-                    break;
-                }
-            }
+            properties.push(property_filter);
         }
 
         if properties.is_empty() {
@@ -419,17 +776,103 @@ impl Rule {
         }
     }
 
+    /// Returns the filter conditions the same way they are applied in the rendered style: if the
+    /// filter cannot be parsed, the rule has no conditions.
+    fn conditions(&self) -> Vec<FilterCondition> {
+        match filter::parse_conditions(&self.filter) {
+            Ok(conditions)
+                if conditions
+                    .iter()
+                    .all(|condition| condition.to_property_filter().is_some()) =>
+            {
+                conditions
+            }
+            _ => vec![],
+        }
+    }
+
+    /// Checks if the rule applies to a feature of the given source layer with the given
+    /// properties. Hidden rules don't apply to any feature.
+    fn matches_feature(&self, layer: &str, properties: &[(String, String)]) -> bool {
+        self.visible
+            && (self.layer_name.is_empty() || self.layer_name == layer)
+            && self
+                .conditions()
+                .iter()
+                .all(|condition| condition.matches(properties))
+    }
+
     fn action(&self) -> RuleAction {
         self.action
     }
 
-    fn ui(&mut self, ui: &mut egui::Ui) -> &mut Self {
+    /// Sizes the editor allows for the symbol type, if the symbol has a size.
+    fn size_range(&self) -> Option<RangeInclusive<f64>> {
+        match self.symbol_type {
+            SymbolType::Point | SymbolType::Line => Some(SYMBOL_SIZE_RANGE),
+            SymbolType::Label => Some(LABEL_SIZE_RANGE),
+            SymbolType::Polygon | SymbolType::None => None,
+        }
+    }
+
+    /// Returns `true` if the name, note, layer name, filter or symbol type of the rule contains
+    /// the `query` (case-insensitive). An empty query matches every rule.
+    fn matches_search(&self, query: &str) -> bool {
+        let query = query.trim().to_lowercase();
+        if query.is_empty() {
+            return true;
+        }
+
+        self.name.to_lowercase().contains(&query)
+            || self.note.to_lowercase().contains(&query)
+            || self.layer_name.to_lowercase().contains(&query)
+            || self.filter.to_lowercase().contains(&query)
+            || self.symbol_type.to_string().contains(&query)
+    }
+
+    fn ui(&mut self, ui: &mut egui::Ui, context: &RuleContext<'_>) -> &mut Self {
         self.action = RuleAction::None;
         let mut changed = false;
-        CollapsingHeader::new(self.header())
-            .id_salt(self.id)
-            .show(ui, |ui| {
+        let mut group_changed = false;
+        let catalog = context.catalog;
+        let mut warnings = self.schema_warnings(catalog);
+        warnings.extend_from_slice(context.issues);
+        let id = ui.make_persistent_id(self.id);
+        let mut state = CollapsingState::load_with_default_open(ui.ctx(), id, false);
+        if self.reveal {
+            state.set_open(true);
+            state.store(ui.ctx());
+        }
+
+        let (toggle, header, _) = state
+            .show_header(ui, |ui| {
+                group::drag_handle(ui, self.id);
+                ui.checkbox(&mut self.selected, "");
+                changed |= ui
+                    .toggle_value(&mut self.solo, "S")
+                    .on_hover_text("Solo: draw only the soloed rules")
+                    .changed();
+                let mut header = RichText::new(self.header());
+                if !self.visible && !self.solo {
+                    header = header.weak().strikethrough();
+                } else if context.solo && !self.solo {
+                    header = header.weak();
+                }
+                ui.label(header).on_hover_text(self.header_hover_text());
+                if self.visible && context.show_feature_count {
+                    feature_count_badge(ui, context.feature_count);
+                }
+                if !warnings.is_empty() {
+                    ui.colored_label(ui.visuals().warn_fg_color, "⚠")
+                        .on_hover_text(warnings.join("\n"));
+                }
+            })
+            .body(|ui| {
                 ui.horizontal(|ui| {
+                    if ui.checkbox(&mut self.visible, "Visible").changed() {
+                        changed = true;
+                    }
+
                     if ui.button("Down").clicked() {
                         self.action = RuleAction::MoveDown;
                     }
@@ -441,76 +884,274 @@ impl Rule {
                     if ui.button("Del").clicked() {
                         self.action = RuleAction::Remove;
                     }
+
+                    if ui.button("Dup").on_hover_text("Duplicate").clicked() {
+                        self.action = RuleAction::Duplicate;
+                    }
+
+                    if ui.button("Copy").on_hover_text("Copy as JSON").clicked() {
+                        self.action = RuleAction::Copy;
+                    }
+                });
+
+                ui.horizontal(|ui| {
+                    ui.label("Name");
+                    changed |= ui.text_edit_singleline(&mut self.name).changed();
+                });
+
+                ui.horizontal(|ui| {
+                    ui.label("Note");
+                    changed |= ui
+                        .add(egui::TextEdit::multiline(&mut self.note).desired_rows(1))
+                        .changed();
+                });
+
+                ui.horizontal(|ui| {
+                    ui.label("Group");
+                    group_changed = group::group_combo(ui, &mut self.group, context.groups);
                 });
 
                 ui.horizontal(|ui| {
                     ui.label("Layer name");
-                    changed = changed || ui.text_edit_singleline(&mut self.layer_name).changed();
+                    changed |= ui.text_edit_singleline(&mut self.layer_name).changed();
+                    changed |= completion::layer_menu(ui, &mut self.layer_name, catalog);
                 });
 
                 ui.horizontal(|ui| {
                     ui.label("Filter");
-                    changed = changed || ui.text_edit_singleline(&mut self.filter).changed();
+                    changed |= ui.text_edit_singleline(&mut self.filter).changed();
+                    changed |=
+                        completion::filter_menu(ui, &self.layer_name, &mut self.filter, catalog);
                 });
 
+                for warning in &warnings {
+                    ui.colored_label(ui.visuals().warn_fg_color, warning);
+                }
+
                 ui.horizontal(|ui| {
                     ui.label("Type");
                     let v = &mut self.symbol_type;
-                    changed = changed
-                        || ComboBox::new("symbol type", "")
-                            .selected_text(v.to_string())
-                            .show_ui(ui, |ui| {
-                                ui.selectable_value(
-                                    v,
-                                    SymbolType::None,
-                                    SymbolType::None.to_string(),
-                                );
-                                ui.selectable_value(
-                                    v,
-                                    SymbolType::Point,
-                                    SymbolType::Point.to_string(),
-                                );
-                                ui.selectable_value(
-                                    v,
-                                    SymbolType::Line,
-                                    SymbolType::Line.to_string(),
-                                );
-                                ui.selectable_value(
-                                    v,
-                                    SymbolType::Polygon,
-                                    SymbolType::Polygon.to_string(),
-                                );
-                            })
-                            .response
-                            .changed();
+                    changed |= ComboBox::new("symbol type", "")
+                        .selected_text(v.to_string())
+                        .show_ui(ui, |ui| {
+                            ui.selectable_value(v, SymbolType::None, SymbolType::None.to_string());
+                            ui.selectable_value(
+                                v,
+                                SymbolType::Point,
+                                SymbolType::Point.to_string(),
+                            );
+                            ui.selectable_value(v, SymbolType::Line, SymbolType::Line.to_string());
+                            ui.selectable_value(
+                                v,
+                                SymbolType::Polygon,
+                                SymbolType::Polygon.to_string(),
+                            );
+                            ui.selectable_value(
+                                v,
+                                SymbolType::Label,
+                                SymbolType::Label.to_string(),
+                            );
+                        })
+                        .response
+                        .changed();
 
                     if !matches!(self.symbol_type, SymbolType::None) {
-                        changed = changed || ui.color_edit_button_srgba(&mut self.color).changed();
+                        changed |= ui.color_edit_button_srgba(&mut self.color).changed();
                     }
 
                     if matches!(self.symbol_type, SymbolType::Point | SymbolType::Line) {
-                        changed = changed
-                            || ui
-                                .add(DragValue::new(&mut self.size).speed(0.01).range(0.0..=20.0))
-                                .changed();
+                        changed |= ui
+                            .add(
+                                DragValue::new(&mut self.size)
+                                    .speed(0.01)
+                                    .range(SYMBOL_SIZE_RANGE),
+                            )
+                            .changed();
                     }
                 });
+
+                if self.symbol_type == SymbolType::Label {
+                    changed = self.label_ui(ui, catalog) || changed;
+                }
             });
 
-        if self.action == RuleAction::None && changed {
+        let header_response = toggle.union(header.response);
+        self.hovered = header_response.hovered();
+        if self.highlighted {
+            ui.painter().rect_stroke(
+                header_response.rect.expand(2.0),
+                2.0,
+                Stroke::new(1.5, ui.visuals().selection.stroke.color),
+                StrokeKind::Outside,
+            );
+        }
+
+        if self.reveal {
+            header_response.scroll_to_me(Some(egui::Align::Center));
+            self.reveal = false;
+        }
+
+        if let Some(action) = group::drop_target(ui, &header_response) {
+            self.action = action;
+        }
+
+        if self.action == RuleAction::None && group_changed {
+            self.action = RuleAction::GroupChanged;
+        } else if self.action == RuleAction::None && changed {
             self.action = RuleAction::Modified;
         }
 
         self
     }
 
+    fn label_ui(&mut self, ui: &mut egui::Ui, catalog: &TileCatalog) -> bool {
+        let mut changed = false;
+
+        ui.horizontal(|ui| {
+            ui.label("Pattern");
+            changed |= ui.text_edit_singleline(&mut self.pattern).changed();
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("Insert property");
+            ComboBox::new("pattern property", "")
+                .selected_text(&self.pattern_token)
+                .show_ui(ui, |ui| {
+                    for property in completion::pattern_properties(catalog, &self.layer_name) {
+                        ui.selectable_value(
+                            &mut self.pattern_token,
+                            property.to_string(),
+                            property,
+                        );
+                    }
+                });
+            ui.add(egui::TextEdit::singleline(&mut self.pattern_token).desired_width(80.0));
+            if ui.button("Insert").clicked() && !self.pattern_token.trim().is_empty() {
+                self.pattern
+                    .push_str(&format!("{{{}}}", self.pattern_token.trim()));
+                changed = true;
+            }
+        });
+
+        ui.label("Font family");
+        let mut removed = None;
+        for (index, family) in self.font_family.iter_mut().enumerate() {
+            ui.horizontal(|ui| {
+                changed |= ui.text_edit_singleline(family).changed();
+                if ui.small_button("x").clicked() {
+                    removed = Some(index);
+                }
+            });
+        }
+        if let Some(index) = removed {
+            self.font_family.remove(index);
+            changed = true;
+        }
+        if ui.small_button("Add family").clicked() {
+            self.font_family.push(String::new());
+        }
+
+        ui.horizontal(|ui| {
+            ui.label("Size");
+            changed |= ui
+                .add(
+                    DragValue::new(&mut self.size)
+                        .speed(0.1)
+                        .range(LABEL_SIZE_RANGE),
+                )
+                .changed();
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("Weight");
+            changed |= enum_combo(ui, "font weight", &mut self.font_weight, &LabelWeight::ALL);
+            ui.label("Style");
+            changed |= enum_combo(ui, "font style", &mut self.font_style, &LabelStyle::ALL);
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("Halo");
+            changed |= ui.color_edit_button_srgba(&mut self.halo_color).changed();
+            changed |= ui
+                .add(
+                    DragValue::new(&mut self.halo_width)
+                        .speed(0.1)
+                        .range(0.0..=10.0),
+                )
+                .changed();
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("Align");
+            changed |= enum_combo(
+                ui,
+                "horizontal alignment",
+                &mut self.horizontal_alignment,
+                &LabelHorizontalAlignment::ALL,
+            );
+            changed |= enum_combo(
+                ui,
+                "vertical alignment",
+                &mut self.vertical_alignment,
+                &LabelVerticalAlignment::ALL,
+            );
+        });
+
+        changed
+    }
+
     fn header(&self) -> String {
         const MAX_LEN: usize = 60;
-        let text = format!("{} ({})", self.layer_name, self.filter);
-        if text.len() > MAX_LEN {
-            format!("{}...", &text[..MAX_LEN])
+        let text = if self.name.is_empty() {
+            format!("{} ({})", self.layer_name, self.filter)
+        } else {
+            format!("{} ({})", self.name, self.layer_name)
+        };
+
+        if text.chars().count() > MAX_LEN {
+            format!("{}...", text.chars().take(MAX_LEN).collect::<String>())
         } else {
             text
         }
     }
+
+    fn header_hover_text(&self) -> String {
+        let mut text = format!("{} ({})", self.layer_name, self.filter);
+        if !self.note.is_empty() {
+            text.push_str("\n\n");
+            text.push_str(&self.note);
+        }
+
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::test_utils::window;
+    use super::*;
+
+    #[test]
+    fn solo_keeps_the_rule_order() {
+        let mut window = window(&[("road", "class == primary"), ("road", ""), ("water", "")]);
+        for rule in &mut window.rules {
+            rule.symbol_type = SymbolType::Line;
+        }
+        window.rules[1].solo = true;
+
+        // The primary roads are still drawn with the first rule, which hides them
+        let style = window.layer_style();
+        assert_eq!(style.rules.len(), 3);
+        assert!(matches!(style.rules[0].symbol, VectorTileSymbol::None));
+        assert_eq!(style.rules[0].properties.len(), 1);
+        assert!(matches!(style.rules[1].symbol, VectorTileSymbol::Line(_)));
+        assert!(matches!(style.rules[2].symbol, VectorTileSymbol::None));
+
+        window.dim_unsoloed = true;
+        let style = window.layer_style();
+        assert!(matches!(style.rules[0].symbol, VectorTileSymbol::Line(_)));
+
+        window.rules[2].visible = false;
+        assert_eq!(window.layer_style().rules.len(), 2);
+    }
 }
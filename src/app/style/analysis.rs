@@ -0,0 +1,410 @@
+//! Static analysis of the rule list.
+//!
+//! A feature is drawn with the first rule that applies to it, so a rule that only matches
+//! features already matched by an earlier rule never draws anything. The analysis finds such
+//! rules, exact duplicates and rules whose filter cannot match any feature.
+//!
+//! Each filter is turned into a set of allowed values for every property it checks. `==`, `in`,
+//! `!=`, `not in`, `exist` and `not exist` conditions are handled exactly, numeric comparisons as
+//! ranges. Comparisons with non-numeric values are only considered equal to identical conditions.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use super::{
+    filter::{FilterCondition, FilterOperator},
+    Rule, StyleWindow,
+};
+
+/// A property value, or `None` for a property the feature doesn't have.
+type Value = Option<String>;
+
+/// Values are compared as numbers when possible, so `5` and `5.0` are the same value.
+fn normalize(value: &str) -> String {
+    match value.parse::<f64>() {
+        Ok(number) => number.to_string(),
+        Err(_) => value.to_string(),
+    }
+}
+
+fn numeric(value: &Value) -> Option<f64> {
+    value.as_deref().and_then(|value| value.parse().ok())
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum ValueSet {
+    /// Only the listed values.
+    Only(BTreeSet<Value>),
+    /// Any value except the listed ones.
+    Except(BTreeSet<Value>),
+}
+
+impl ValueSet {
+    fn intersect(&self, other: &Self) -> Self {
+        match (self, other) {
+            (ValueSet::Only(a), ValueSet::Only(b)) => {
+                ValueSet::Only(a.intersection(b).cloned().collect())
+            }
+            (ValueSet::Only(a), ValueSet::Except(b)) | (ValueSet::Except(b), ValueSet::Only(a)) => {
+                ValueSet::Only(a.difference(b).cloned().collect())
+            }
+            (ValueSet::Except(a), ValueSet::Except(b)) => {
+                ValueSet::Except(a.union(b).cloned().collect())
+            }
+        }
+    }
+
+    fn contains(&self, value: &Value) -> bool {
+        match self {
+            ValueSet::Only(values) => values.contains(value),
+            ValueSet::Except(values) => !values.contains(value),
+        }
+    }
+}
+
+/// Numeric bounds of a value. Each bound is a number and whether it is inclusive.
+#[derive(Debug, Clone, Default, PartialEq)]
+struct Range {
+    lower: Option<(f64, bool)>,
+    upper: Option<(f64, bool)>,
+}
+
+impl Range {
+    fn add_lower(&mut self, bound: f64, inclusive: bool) {
+        let tighter = match self.lower {
+            None => true,
+            Some((current, current_inclusive)) => {
+                bound > current || (bound == current && current_inclusive && !inclusive)
+            }
+        };
+        if tighter {
+            self.lower = Some((bound, inclusive));
+        }
+    }
+
+    fn add_upper(&mut self, bound: f64, inclusive: bool) {
+        let tighter = match self.upper {
+            None => true,
+            Some((current, current_inclusive)) => {
+                bound < current || (bound == current && current_inclusive && !inclusive)
+            }
+        };
+        if tighter {
+            self.upper = Some((bound, inclusive));
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        match (self.lower, self.upper) {
+            (Some((lower, lower_inclusive)), Some((upper, upper_inclusive))) => {
+                lower > upper || (lower == upper && !(lower_inclusive && upper_inclusive))
+            }
+            _ => false,
+        }
+    }
+
+    fn contains(&self, value: f64) -> bool {
+        let above = match self.lower {
+            None => true,
+            Some((bound, inclusive)) => value > bound || (inclusive && value == bound),
+        };
+        let below = match self.upper {
+            None => true,
+            Some((bound, inclusive)) => value < bound || (inclusive && value == bound),
+        };
+
+        above && below
+    }
+
+    /// Checks that every number in `other` is in this range.
+    fn contains_range(&self, other: &Range) -> bool {
+        let lower = match (self.lower, other.lower) {
+            (None, _) => true,
+            (Some(_), None) => false,
+            (Some((bound, inclusive)), Some((other_bound, other_inclusive))) => {
+                other_bound > bound || (other_bound == bound && (inclusive || !other_inclusive))
+            }
+        };
+        let upper = match (self.upper, other.upper) {
+            (None, _) => true,
+            (Some(_), None) => false,
+            (Some((bound, inclusive)), Some((other_bound, other_inclusive))) => {
+                other_bound < bound || (other_bound == bound && (inclusive || !other_inclusive))
+            }
+        };
+
+        lower && upper
+    }
+}
+
+/// Values of one property allowed by the conditions of a filter.
+#[derive(Debug, Clone, PartialEq)]
+struct PropertyConstraint {
+    values: ValueSet,
+    /// Set by numeric comparisons. Only numbers in the range are allowed.
+    range: Option<Range>,
+    /// Comparisons with non-numeric values, which are not analyzed.
+    other: BTreeSet<String>,
+}
+
+impl Default for PropertyConstraint {
+    fn default() -> Self {
+        Self {
+            values: ValueSet::Except(BTreeSet::new()),
+            range: None,
+            other: BTreeSet::new(),
+        }
+    }
+}
+
+impl PropertyConstraint {
+    fn add(&mut self, condition: &FilterCondition) {
+        let values = || -> BTreeSet<Value> {
+            condition
+                .values
+                .iter()
+                .map(|value| Some(normalize(value)))
+                .collect()
+        };
+
+        let allowed = match condition.operator {
+            FilterOperator::Equal | FilterOperator::In => ValueSet::Only(values()),
+            FilterOperator::NotEqual | FilterOperator::NotIn => ValueSet::Except(values()),
+            FilterOperator::Exist => ValueSet::Except(BTreeSet::from([None])),
+            FilterOperator::NotExist => ValueSet::Only(BTreeSet::from([None])),
+            FilterOperator::Greater
+            | FilterOperator::Less
+            | FilterOperator::GreaterOrEqual
+            | FilterOperator::LessOrEqual => {
+                let Ok(bound) = condition.values[0].parse::<f64>() else {
+                    self.other.insert(condition.to_string());
+                    return;
+                };
+
+                let range = self.range.get_or_insert_with(Range::default);
+                match condition.operator {
+                    FilterOperator::Greater => range.add_lower(bound, false),
+                    FilterOperator::GreaterOrEqual => range.add_lower(bound, true),
+                    FilterOperator::Less => range.add_upper(bound, false),
+                    _ => range.add_upper(bound, true),
+                }
+                return;
+            }
+        };
+
+        self.values = self.values.intersect(&allowed);
+    }
+
+    /// Checks if the value passes the analyzed conditions.
+    fn allows(&self, value: &Value) -> bool {
+        let in_range = match &self.range {
+            None => true,
+            Some(range) => numeric(value).is_some_and(|number| range.contains(number)),
+        };
+
+        in_range && self.values.contains(value)
+    }
+
+    fn is_empty(&self) -> bool {
+        match &self.values {
+            ValueSet::Only(values) => !values.iter().any(|value| self.allows(value)),
+            ValueSet::Except(_) => self.range.as_ref().is_some_and(Range::is_empty),
+        }
+    }
+
+    /// Checks that every value allowed by this constraint is allowed by `other`.
+    fn is_subset(&self, other: &Self) -> bool {
+        if !other.other.is_subset(&self.other) {
+            return false;
+        }
+
+        match &self.values {
+            ValueSet::Only(values) => values
+                .iter()
+                .filter(|value| self.allows(value))
+                .all(|value| other.allows(value)),
+            ValueSet::Except(_) => {
+                let ValueSet::Except(excluded) = &other.values else {
+                    return false;
+                };
+                if excluded.iter().any(|value| self.allows(value)) {
+                    return false;
+                }
+
+                match (&self.range, &other.range) {
+                    (_, None) => true,
+                    (Some(range), Some(other_range)) => other_range.contains_range(range),
+                    (None, Some(_)) => false,
+                }
+            }
+        }
+    }
+}
+
+/// The features a rule applies to.
+#[derive(Debug, Clone, PartialEq)]
+struct RuleScope {
+    /// Source layer of the rule, `None` for rules applying to all layers.
+    layer: Option<String>,
+    properties: BTreeMap<String, PropertyConstraint>,
+}
+
+impl RuleScope {
+    fn new(rule: &Rule) -> Self {
+        let mut properties: BTreeMap<String, PropertyConstraint> = BTreeMap::new();
+        for condition in rule.conditions() {
+            properties
+                .entry(condition.property.clone())
+                .or_default()
+                .add(&condition);
+        }
+
+        Self {
+            layer: (!rule.layer_name.is_empty()).then(|| rule.layer_name.clone()),
+            properties,
+        }
+    }
+
+    /// Returns `true` if the conditions contradict each other, so no feature can match.
+    fn is_empty(&self) -> bool {
+        self.properties.values().any(PropertyConstraint::is_empty)
+    }
+
+    /// Checks that every feature matching this scope also matches `other`.
+    fn is_subset(&self, other: &Self) -> bool {
+        if other.layer.is_some() && other.layer != self.layer {
+            return false;
+        }
+
+        let any = PropertyConstraint::default();
+        other.properties.iter().all(|(property, constraint)| {
+            self.properties
+                .get(property)
+                .unwrap_or(&any)
+                .is_subset(constraint)
+        })
+    }
+}
+
+impl StyleWindow {
+    /// Finds visible rules that never draw anything: rules with contradicting conditions, and
+    /// rules matching only features that an earlier rule already draws. Returns warnings by
+    /// rule id.
+    pub(super) fn analyze_rules(&self) -> HashMap<u64, Vec<String>> {
+        let rules: Vec<(&Rule, RuleScope)> = self
+            .rules
+            .iter()
+            .filter(|rule| rule.visible)
+            .map(|rule| (rule, RuleScope::new(rule)))
+            .collect();
+
+        let mut warnings: HashMap<u64, Vec<String>> = HashMap::new();
+        for (index, (rule, scope)) in rules.iter().enumerate() {
+            if scope.is_empty() {
+                warnings
+                    .entry(rule.id)
+                    .or_default()
+                    .push("The filter conditions contradict each other".to_string());
+                continue;
+            }
+
+            let covering = rules[..index]
+                .iter()
+                .find(|(_, earlier)| !earlier.is_empty() && scope.is_subset(earlier));
+            if let Some((earlier_rule, earlier)) = covering {
+                let warning = if earlier.layer == scope.layer && earlier.is_subset(scope) {
+                    format!("Duplicate of \"{}\"", earlier_rule.header())
+                } else {
+                    format!(
+                        "Never drawn: its features are drawn by \"{}\"",
+                        earlier_rule.header()
+                    )
+                };
+                warnings.entry(rule.id).or_default().push(warning);
+            }
+        }
+
+        warnings
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::style::test_utils::window;
+
+    fn warned(rules: &[(&str, &str)]) -> Vec<u64> {
+        let mut ids: Vec<u64> = window(rules).analyze_rules().into_keys().collect();
+        ids.sort();
+        ids
+    }
+
+    #[test]
+    fn contradicting_conditions() {
+        assert_eq!(warned(&[("road", "class == a && class == b")]), vec![1]);
+        assert_eq!(warned(&[("road", "rank > 5 && rank < 3")]), vec![1]);
+        assert_eq!(
+            warned(&[("road", "rank >= 3 && rank <= 3")]),
+            vec![] as Vec<u64>
+        );
+        assert_eq!(
+            warned(&[("road", "class in [a,b] && class not in [a,b]")]),
+            vec![1]
+        );
+        assert_eq!(warned(&[("road", "ref exist && ref not exist")]), vec![1]);
+        assert_eq!(
+            warned(&[("road", "class == a && rank > 2")]),
+            vec![] as Vec<u64>
+        );
+    }
+
+    #[test]
+    fn duplicates_and_shadowed_rules() {
+        assert_eq!(
+            warned(&[("road", "class == a"), ("road", "class == a")]),
+            vec![2]
+        );
+        // A more specific rule after a general one is never drawn
+        assert_eq!(
+            warned(&[
+                ("road", "class in [a,b]"),
+                ("road", "class == a && rank > 2")
+            ]),
+            vec![2]
+        );
+        assert_eq!(warned(&[("", ""), ("water", "class == lake")]), vec![2]);
+        assert_eq!(
+            warned(&[("road", "rank > 2"), ("road", "rank >= 5")]),
+            vec![2]
+        );
+        assert_eq!(
+            warned(&[("road", "class != a"), ("road", "class == b")]),
+            vec![2]
+        );
+        // Features without the property pass `!=` conditions
+        assert_eq!(
+            warned(&[("road", "class != a"), ("road", "class not exist")]),
+            vec![2]
+        );
+    }
+
+    #[test]
+    fn reachable_rules() {
+        // A specific rule before a general one is the usual order
+        assert_eq!(
+            warned(&[("road", "class == a"), ("road", "class in [a,b]")]),
+            vec![] as Vec<u64>
+        );
+        assert_eq!(
+            warned(&[("road", "class == a"), ("water", "class == a")]),
+            vec![] as Vec<u64>
+        );
+        assert_eq!(
+            warned(&[("road", "rank > 2"), ("road", "rank >= 2")]),
+            vec![] as Vec<u64>
+        );
+        assert_eq!(
+            warned(&[("road", "class == a"), ("road", "class not exist")]),
+            vec![] as Vec<u64>
+        );
+    }
+}
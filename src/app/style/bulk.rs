@@ -0,0 +1,294 @@
+//! Operations applied to all selected rules at once. Selected rules hidden by the search are
+//! not changed.
+
+use std::collections::HashSet;
+
+use egui::{Color32, DragValue};
+
+use super::{group::group_combo, Rule, RuleGroup, StyleWindow};
+
+/// Values of the bulk edit widgets. They are kept between frames so that the same operation can
+/// be applied to several selections in a row.
+#[derive(Debug, Clone)]
+pub(super) struct BulkEditState {
+    color: Color32,
+    scale: f64,
+    group: Option<String>,
+}
+
+impl Default for BulkEditState {
+    fn default() -> Self {
+        Self {
+            color: Color32::GRAY,
+            scale: 1.0,
+            group: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(super) enum BulkAction {
+    SetColor(Color32),
+    ScaleSize(f64),
+    SetVisible(bool),
+    SetGroup(Option<String>),
+    Remove,
+    MoveToTop,
+    MoveToBottom,
+}
+
+impl BulkEditState {
+    /// Resets the group picked for the selected rules if it was deleted.
+    pub(super) fn forget_group(&mut self, id: &str) {
+        if self.group.as_deref() == Some(id) {
+            self.group = None;
+        }
+    }
+
+    /// Shows bulk edit controls for `selected_count` selected rules shown by the search, with
+    /// the number of selected rules the search hides. Returns the action the user requested, if
+    /// any.
+    pub(super) fn ui(
+        &mut self,
+        ui: &mut egui::Ui,
+        selected_count: usize,
+        hidden_count: usize,
+        groups: &[RuleGroup],
+    ) -> Option<BulkAction> {
+        let mut action = None;
+
+        ui.horizontal(|ui| {
+            ui.label(format!("{selected_count} rules selected"));
+            if hidden_count > 0 {
+                ui.colored_label(
+                    ui.visuals().warn_fg_color,
+                    format!("{hidden_count} hidden by the search"),
+                )
+                .on_hover_text("Bulk edits only change the selected rules shown by the search");
+            }
+        });
+
+        ui.horizontal(|ui| {
+            ui.color_edit_button_srgba(&mut self.color);
+            if ui.button("Set color").clicked() {
+                action = Some(BulkAction::SetColor(self.color));
+            }
+
+            ui.add(
+                DragValue::new(&mut self.scale)
+                    .speed(0.01)
+                    .range(0.01..=10.0)
+                    .prefix("x"),
+            );
+            if ui.button("Scale size").clicked() {
+                action = Some(BulkAction::ScaleSize(self.scale));
+            }
+        });
+
+        ui.horizontal(|ui| {
+            if ui.button("Hide").clicked() {
+                action = Some(BulkAction::SetVisible(false));
+            }
+            if ui.button("Show").clicked() {
+                action = Some(BulkAction::SetVisible(true));
+            }
+            if ui
+                .button("To top")
+                .on_hover_text("Groups of the selected rules are moved as a whole")
+                .clicked()
+            {
+                action = Some(BulkAction::MoveToTop);
+            }
+            if ui
+                .button("To bottom")
+                .on_hover_text("Groups of the selected rules are moved as a whole")
+                .clicked()
+            {
+                action = Some(BulkAction::MoveToBottom);
+            }
+            if ui.button("Del").clicked() {
+                action = Some(BulkAction::Remove);
+            }
+        });
+
+        ui.horizontal(|ui| {
+            group_combo(ui, &mut self.group, groups);
+            if ui.button("Set group").clicked() {
+                action = Some(BulkAction::SetGroup(self.group.clone()));
+            }
+        });
+
+        action
+    }
+}
+
+impl StyleWindow {
+    pub(super) fn apply_bulk_action(&mut self, action: BulkAction) {
+        match action {
+            BulkAction::SetColor(color) => {
+                for rule in self.selected_rules_mut() {
+                    rule.color = color;
+                }
+            }
+            BulkAction::ScaleSize(factor) => {
+                for rule in self.selected_rules_mut() {
+                    if let Some(range) = rule.size_range() {
+                        rule.size = (rule.size * factor).clamp(*range.start(), *range.end());
+                    }
+                }
+            }
+            BulkAction::SetVisible(visible) => {
+                for rule in self.selected_rules_mut() {
+                    rule.visible = visible;
+                }
+            }
+            BulkAction::SetGroup(group) => {
+                let mut ids = vec![];
+                for rule in self.selected_rules_mut() {
+                    rule.group = group.clone();
+                    ids.push(rule.id);
+                }
+                for id in ids {
+                    if let Some(index) = self.rules.iter().position(|rule| rule.id == id) {
+                        self.keep_group_together(index);
+                    }
+                }
+            }
+            BulkAction::Remove => {
+                let search = self.search.clone();
+                self.rules.retain(|rule| !is_target(rule, &search));
+            }
+            BulkAction::MoveToTop => {
+                let (selected, other) = self.partition_moved();
+                self.rules = selected.into_iter().chain(other).collect();
+            }
+            BulkAction::MoveToBottom => {
+                let (selected, other) = self.partition_moved();
+                self.rules = other.into_iter().chain(selected).collect();
+            }
+        }
+    }
+
+    /// Returns the rules bulk actions apply to.
+    pub(super) fn bulk_targets(&self) -> impl Iterator<Item = &Rule> {
+        self.rules
+            .iter()
+            .filter(|rule| is_target(rule, &self.search))
+    }
+
+    fn selected_rules_mut(&mut self) -> impl Iterator<Item = &mut Rule> {
+        let search = &self.search;
+        self.rules
+            .iter_mut()
+            .filter(move |rule| is_target(rule, search))
+    }
+
+    /// Takes all rules, split into the rules to move and the other rules. The rules to move are
+    /// the rules bulk actions apply to and all rules of their groups, so that no group is split.
+    fn partition_moved(&mut self) -> (Vec<Rule>, Vec<Rule>) {
+        let groups: HashSet<String> = self
+            .bulk_targets()
+            .filter_map(|rule| rule.group.clone())
+            .collect();
+        let search = self.search.clone();
+        self.rules.drain(..).partition(|rule| {
+            is_target(rule, &search) || rule.group.as_ref().is_some_and(|g| groups.contains(g))
+        })
+    }
+}
+
+fn is_target(rule: &Rule, search: &str) -> bool {
+    rule.selected && rule.matches_search(search)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::style::{test_utils::window, SymbolType};
+
+    fn ids(window: &StyleWindow) -> Vec<u64> {
+        window.rules.iter().map(|rule| rule.id).collect()
+    }
+
+    fn select(window: &mut StyleWindow, ids: &[u64]) {
+        for rule in &mut window.rules {
+            rule.selected = ids.contains(&rule.id);
+        }
+    }
+
+    #[test]
+    fn move_and_remove_selected() {
+        let mut window = window(&[("a", ""), ("b", ""), ("c", ""), ("d", "")]);
+        select(&mut window, &[2, 4]);
+
+        window.apply_bulk_action(BulkAction::MoveToTop);
+        assert_eq!(ids(&window), vec![2, 4, 1, 3]);
+        window.apply_bulk_action(BulkAction::MoveToBottom);
+        assert_eq!(ids(&window), vec![1, 3, 2, 4]);
+        window.apply_bulk_action(BulkAction::Remove);
+        assert_eq!(ids(&window), vec![1, 3]);
+    }
+
+    #[test]
+    fn move_whole_groups() {
+        let mut window = window(&[("", ""); 5]);
+        for rule in &mut window.rules[1..3] {
+            rule.group = Some("a".to_string());
+        }
+        select(&mut window, &[3, 5]);
+
+        window.apply_bulk_action(BulkAction::MoveToTop);
+        assert_eq!(ids(&window), vec![2, 3, 5, 1, 4]);
+        window.apply_bulk_action(BulkAction::MoveToBottom);
+        assert_eq!(ids(&window), vec![1, 4, 2, 3, 5]);
+    }
+
+    #[test]
+    fn set_properties_of_selected() {
+        let mut window = window(&[("a", ""), ("b", "")]);
+        select(&mut window, &[1]);
+
+        window.apply_bulk_action(BulkAction::SetColor(Color32::RED));
+        window.apply_bulk_action(BulkAction::SetVisible(false));
+        window.apply_bulk_action(BulkAction::SetGroup(Some("roads".to_string())));
+        assert_eq!(window.rules[0].color, Color32::RED);
+        assert!(!window.rules[0].visible);
+        assert_eq!(window.rules[0].group.as_deref(), Some("roads"));
+        assert!(window.rules[1].visible);
+        assert_eq!(window.rules[1].group, None);
+    }
+
+    #[test]
+    fn scaled_sizes_stay_in_range() {
+        let mut window = window(&[("a", ""), ("b", ""), ("c", "")]);
+        let types = [SymbolType::Line, SymbolType::Label, SymbolType::Polygon];
+        for (rule, symbol_type) in window.rules.iter_mut().zip(types) {
+            rule.symbol_type = symbol_type;
+            rule.size = 10.0;
+        }
+        select(&mut window, &[1, 2, 3]);
+
+        window.apply_bulk_action(BulkAction::ScaleSize(10.0));
+        assert_eq!(window.rules[0].size, 20.0);
+        assert_eq!(window.rules[1].size, 72.0);
+        assert_eq!(window.rules[2].size, 10.0);
+
+        window.apply_bulk_action(BulkAction::ScaleSize(0.01));
+        assert_eq!(window.rules[1].size, 1.0);
+    }
+
+    #[test]
+    fn rules_hidden_by_search_are_not_changed() {
+        let mut window = window(&[("water", ""), ("road", ""), ("water", "")]);
+        select(&mut window, &[1, 2, 3]);
+        window.search = "water".to_string();
+        assert_eq!(window.bulk_targets().count(), 2);
+
+        window.apply_bulk_action(BulkAction::SetVisible(false));
+        assert!(!window.rules[0].visible && !window.rules[2].visible);
+        assert!(window.rules[1].visible);
+
+        window.apply_bulk_action(BulkAction::Remove);
+        assert_eq!(ids(&window), vec![2]);
+    }
+}
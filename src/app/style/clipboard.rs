@@ -0,0 +1,246 @@
+//! Copying and pasting rules as JSON.
+
+use galileo::layer::vector_tile_layer::style::StyleRule;
+use serde::{Deserialize, Serialize};
+
+use crate::maptiler_style::{convert_layer, Layer};
+
+use super::{Rule, StyleWindow};
+
+/// A `StyleRule` with the editor-only name and note of the rule. Serialized as a plain `StyleRule`
+/// object with two additional fields, so the copied JSON can be used as a Galileo style rule.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(super) struct NamedRule {
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub(super) name: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub(super) note: String,
+    #[serde(flatten)]
+    pub(super) rule: StyleRule,
+}
+
+impl From<&Rule> for NamedRule {
+    fn from(rule: &Rule) -> Self {
+        Self {
+            name: rule.name.clone(),
+            note: rule.note.clone(),
+            rule: rule.get_rule(),
+        }
+    }
+}
+
+/// Parses pasted text as rules. The text can contain a Galileo `StyleRule` or an array of them,
+/// or a MapLibre layer or an array of layers. Layers are converted the same way as when a whole
+/// MapTiler style is loaded, and the layer id becomes the rule name.
+pub(super) fn parse_rules(text: &str) -> Result<Vec<NamedRule>, String> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Err("nothing to paste".to_string());
+    }
+
+    if let Ok(rules) = serde_json::from_str::<Vec<NamedRule>>(text) {
+        return Ok(rules);
+    }
+
+    if let Ok(rule) = serde_json::from_str::<NamedRule>(text) {
+        return Ok(vec![rule]);
+    }
+
+    let layers = match serde_json::from_str::<Vec<Layer>>(text) {
+        Ok(layers) => layers,
+        Err(_) => match serde_json::from_str::<Layer>(text) {
+            Ok(layer) => vec![layer],
+            Err(err) => return Err(format!("not a style rule or a MapLibre layer: {err}")),
+        },
+    };
+
+    let rules: Vec<_> = layers
+        .iter()
+        .filter_map(|layer| {
+            let rule = convert_layer(layer);
+            if rule.is_none() {
+                log::warn!("Layer {} cannot be converted to a rule", layer.id);
+            }
+
+            rule.map(|rule| NamedRule {
+                name: layer.id.clone(),
+                note: String::new(),
+                rule,
+            })
+        })
+        .collect();
+
+    if rules.is_empty() {
+        Err("none of the pasted layers can be converted to a rule".to_string())
+    } else {
+        Ok(rules)
+    }
+}
+
+impl StyleWindow {
+    /// Copies the given rules to the clipboard as a JSON array of `StyleRule`s with rule names.
+    pub(super) fn copy_rules<'a>(ctx: &egui::Context, rules: impl Iterator<Item = &'a Rule>) {
+        let rules: Vec<NamedRule> = rules.map(NamedRule::from).collect();
+        match serde_json::to_string_pretty(&rules) {
+            Ok(json) => ctx.copy_text(json),
+            Err(err) => log::error!("Failed to serialize rules: {err}"),
+        }
+    }
+
+    /// Adds rules parsed from `text` after the last selected rule, or to the end of the list if
+    /// no rule is selected. Pasted rules become the new selection.
+    pub(super) fn paste_rules(&mut self, text: &str) -> Result<usize, String> {
+        let pasted = parse_rules(text)?;
+        let count = pasted.len();
+
+        let index = self
+            .rules
+            .iter()
+            .rposition(|rule| rule.selected)
+            .map(|index| index + 1)
+            .unwrap_or(self.rules.len());
+
+        for rule in &mut self.rules {
+            rule.selected = false;
+        }
+
+        let new_rules: Vec<Rule> = pasted
+            .into_iter()
+            .map(|named| {
+                let mut rule = Rule::new(&named.rule, self.next_rule_id());
+                rule.name = named.name;
+                rule.note = named.note;
+                rule.selected = true;
+                rule
+            })
+            .collect();
+        self.rules.splice(index..index, new_rules);
+
+        Ok(count)
+    }
+
+    pub(super) fn clipboard_ui(&mut self, ctx: &egui::Context, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            let has_selection = self.rules.iter().any(|rule| rule.selected);
+            if ui
+                .add_enabled(has_selection, egui::Button::new("Copy selected"))
+                .clicked()
+            {
+                Self::copy_rules(ctx, self.rules.iter().filter(|rule| rule.selected));
+            }
+
+            ui.toggle_value(&mut self.show_paste, "Paste...");
+        });
+
+        if !self.show_paste {
+            return;
+        }
+
+        ui.label("Style rules or MapLibre layers as JSON:");
+        egui::ScrollArea::vertical()
+            .id_salt("paste rules")
+            .max_height(120.0)
+            .show(ui, |ui| {
+                ui.add(
+                    egui::TextEdit::multiline(&mut self.paste_text)
+                        .code_editor()
+                        .desired_rows(4)
+                        .desired_width(f32::INFINITY),
+                );
+            });
+
+        ui.horizontal(|ui| {
+            if ui.button("Add rules").clicked() {
+                let text = std::mem::take(&mut self.paste_text);
+                match self.paste_rules(&text) {
+                    Ok(count) => {
+                        log::info!("Pasted {count} rules");
+                        self.paste_error = None;
+                        self.show_paste = false;
+                        self.mark_changed(ctx);
+                    }
+                    Err(err) => {
+                        self.paste_text = text;
+                        self.paste_error = Some(err);
+                    }
+                }
+            }
+
+            if ui.button("Cancel").clicked() {
+                self.paste_text.clear();
+                self.paste_error = None;
+                self.show_paste = false;
+            }
+        });
+
+        if let Some(err) = &self.paste_error {
+            ui.colored_label(ui.visuals().error_fg_color, err);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use galileo::layer::vector_tile_layer::style::VectorTileSymbol;
+
+    use super::*;
+
+    #[test]
+    fn parse_style_rules() {
+        let rule = StyleRule {
+            layer_name: Some("water".to_string()),
+            properties: vec![],
+            symbol: VectorTileSymbol::None,
+        };
+        let json = serde_json::to_string(&vec![rule.clone(), rule]).unwrap();
+
+        let rules = parse_rules(&json).unwrap();
+        assert_eq!(rules.len(), 2);
+        assert_eq!(rules[0].rule.layer_name.as_deref(), Some("water"));
+        assert!(rules[0].name.is_empty());
+    }
+
+    #[test]
+    fn named_rule_roundtrip() {
+        let rule = NamedRule {
+            name: "Lakes".to_string(),
+            note: "Only large lakes".to_string(),
+            rule: StyleRule {
+                layer_name: Some("water".to_string()),
+                properties: vec![],
+                symbol: VectorTileSymbol::None,
+            },
+        };
+        let json = serde_json::to_string(&rule).unwrap();
+
+        let rules = parse_rules(&json).unwrap();
+        assert_eq!(rules.len(), 1);
+        assert_eq!(rules[0].name, "Lakes");
+        assert_eq!(rules[0].note, "Only large lakes");
+        assert_eq!(rules[0].rule.layer_name.as_deref(), Some("water"));
+    }
+
+    #[test]
+    fn parse_maplibre_layer() {
+        let json = r##"{
+            "id": "River",
+            "type": "line",
+            "source": "maptiler_planet",
+            "source-layer": "waterway",
+            "paint": {"line-color": "#a0c8f0", "line-width": 2},
+            "filter": ["==", "class", "river"]
+        }"##;
+
+        let rules = parse_rules(json).unwrap();
+        assert_eq!(rules.len(), 1);
+        assert_eq!(rules[0].name, "River");
+        assert_eq!(rules[0].rule.layer_name.as_deref(), Some("waterway"));
+        assert!(matches!(rules[0].rule.symbol, VectorTileSymbol::Line(_)));
+    }
+
+    #[test]
+    fn parse_invalid_text() {
+        assert!(parse_rules("").is_err());
+        assert!(parse_rules("not json").is_err());
+    }
+}
@@ -0,0 +1,234 @@
+//! Suggestions and checks of the rule fields based on the tile catalog.
+
+use std::collections::BTreeSet;
+
+use crate::app::catalog::TileCatalog;
+
+use super::{
+    filter::{self, FilterCondition, FilterOperator},
+    label::COMMON_PATTERN_PROPERTIES,
+    Rule, SymbolType,
+};
+
+/// Maximum height of the suggestion lists.
+const MAX_MENU_HEIGHT: f32 = 300.0;
+
+/// Shows a menu with the source layers of the catalog. Layers containing the current text are
+/// listed, or all layers if none does. Returns `true` if a layer was picked.
+pub(super) fn layer_menu(
+    ui: &mut egui::Ui,
+    layer_name: &mut String,
+    catalog: &TileCatalog,
+) -> bool {
+    let query = layer_name.trim().to_lowercase();
+    let mut names: Vec<&str> = catalog
+        .layer_names()
+        .filter(|name| name.to_lowercase().contains(&query))
+        .collect();
+    if names.is_empty() {
+        names = catalog.layer_names().collect();
+    }
+
+    let mut changed = false;
+    ui.add_enabled_ui(!catalog.is_empty(), |ui| {
+        ui.menu_button("▾", |ui| {
+            egui::ScrollArea::vertical()
+                .max_height(MAX_MENU_HEIGHT)
+                .show(ui, |ui| {
+                    for name in names {
+                        if ui.selectable_label(name == layer_name, name).clicked() {
+                            *layer_name = name.to_string();
+                            changed = true;
+                            ui.close();
+                        }
+                    }
+                });
+        })
+        .response
+        .on_hover_text("Layers found in the loaded tiles");
+    });
+
+    changed
+}
+
+/// Shows a menu with the properties of the `layer` and their sample values. Picking a value
+/// appends an equality condition to the filter. Values that can't be written in a filter are
+/// disabled. Returns `true` if the filter was changed.
+pub(super) fn filter_menu(
+    ui: &mut egui::Ui,
+    layer: &str,
+    filter: &mut String,
+    catalog: &TileCatalog,
+) -> bool {
+    let mut condition = None;
+    ui.add_enabled_ui(!catalog.is_empty(), |ui| {
+        ui.menu_button("+", |ui| {
+            egui::ScrollArea::vertical()
+                .max_height(MAX_MENU_HEIGHT)
+                .show(ui, |ui| {
+                    for property in catalog.property_names(layer) {
+                        ui.menu_button(property, |ui| {
+                            if ui.button(format!("{property} exist")).clicked() {
+                                condition = Some(FilterCondition {
+                                    property: property.to_string(),
+                                    operator: FilterOperator::Exist,
+                                    values: vec![],
+                                });
+                            }
+                            ui.separator();
+
+                            egui::ScrollArea::vertical()
+                                .max_height(MAX_MENU_HEIGHT)
+                                .show(ui, |ui| {
+                                    for value in catalog.property_values(layer, property) {
+                                        let equal = FilterCondition::equal(property, value);
+                                        if ui
+                                            .add_enabled(equal.is_some(), egui::Button::new(value))
+                                            .on_disabled_hover_text(
+                                                "The value can't be written in a rule filter",
+                                            )
+                                            .clicked()
+                                        {
+                                            condition = equal;
+                                        }
+                                    }
+                                });
+
+                            if condition.is_some() {
+                                ui.close();
+                            }
+                        });
+                    }
+                });
+        })
+        .response
+        .on_hover_text("Add a condition for a property found in the loaded tiles");
+    });
+
+    let Some(condition) = condition else {
+        return false;
+    };
+
+    if filter.trim().is_empty() {
+        *filter = condition.to_string();
+    } else {
+        filter.push_str(&format!(" && {condition}"));
+    }
+
+    true
+}
+
+/// Returns the property names to offer for a label pattern of a rule for the `layer`.
+pub(super) fn pattern_properties<'a>(catalog: &'a TileCatalog, layer: &str) -> Vec<&'a str> {
+    if catalog.is_empty() {
+        return COMMON_PATTERN_PROPERTIES.to_vec();
+    }
+
+    catalog.property_names(layer).into_iter().collect()
+}
+
+impl Rule {
+    /// Returns warnings about the source layer and properties used by the rule that are not in
+    /// the tiles. Layers and properties declared in the tile set metadata are known in advance;
+    /// otherwise only the loaded tiles are checked, so the warning says they were not seen yet.
+    /// Nothing is reported until some tiles are loaded.
+    pub(super) fn schema_warnings(&self, catalog: &TileCatalog) -> Vec<String> {
+        if catalog.is_empty() {
+            return vec![];
+        }
+
+        if !self.layer_name.is_empty() && catalog.layer(&self.layer_name).is_none() {
+            return vec![if catalog.has_declared_layers() {
+                format!("Layer \"{}\" is not in the tile set", self.layer_name)
+            } else {
+                format!(
+                    "Layer \"{}\" has not been seen in the loaded tiles yet",
+                    self.layer_name
+                )
+            }];
+        }
+
+        let mut properties: BTreeSet<String> = filter::parse_conditions(&self.filter)
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|condition| condition.operator != FilterOperator::NotExist)
+            .map(|condition| condition.property)
+            .collect();
+        if self.symbol_type == SymbolType::Label {
+            properties.extend(pattern_tokens(&self.pattern).map(str::to_string));
+        }
+
+        let properties_declared = catalog
+            .layer(&self.layer_name)
+            .is_some_and(|layer| layer.has_declared_properties);
+        properties
+            .into_iter()
+            .filter(|property| !catalog.has_property(&self.layer_name, property))
+            .map(|property| {
+                if properties_declared {
+                    format!("Property \"{property}\" is not in the tile set")
+                } else {
+                    format!("Property \"{property}\" has not been seen in the loaded tiles yet")
+                }
+            })
+            .collect()
+    }
+}
+
+/// Returns the property names used in a label pattern as `{name}`.
+fn pattern_tokens(pattern: &str) -> impl Iterator<Item = &str> {
+    pattern
+        .split('{')
+        .skip(1)
+        .filter_map(|part| part.split_once('}'))
+        .map(|(token, _)| token.trim())
+        .filter(|token| !token.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::tiles::VectorLayerInfo;
+
+    #[test]
+    fn find_pattern_tokens() {
+        let tokens: Vec<&str> = pattern_tokens("{name}\n{ref} - {name:latin} {").collect();
+        assert_eq!(tokens, vec!["name", "ref", "name:latin"]);
+        assert_eq!(pattern_tokens("no tokens").count(), 0);
+    }
+
+    #[test]
+    fn warnings_for_declared_and_seen_layers() {
+        let mut rule = Rule::new_empty(1);
+        rule.layer_name = "water".to_string();
+        rule.filter = "class == lake".to_string();
+
+        let mut catalog = TileCatalog::default();
+        assert!(rule.schema_warnings(&catalog).is_empty());
+
+        catalog.add_vector_layers(&[VectorLayerInfo {
+            id: "roads".to_string(),
+            fields: vec!["class".to_string()],
+        }]);
+        assert_eq!(
+            rule.schema_warnings(&catalog),
+            vec!["Layer \"water\" is not in the tile set"]
+        );
+
+        catalog.add_vector_layers(&[VectorLayerInfo {
+            id: "water".to_string(),
+            fields: vec![],
+        }]);
+        assert_eq!(
+            rule.schema_warnings(&catalog),
+            vec!["Property \"class\" has not been seen in the loaded tiles yet"]
+        );
+
+        rule.layer_name = "roads".to_string();
+        rule.filter = "class == primary && surface == paved".to_string();
+        assert_eq!(
+            rule.schema_warnings(&catalog),
+            vec!["Property \"surface\" is not in the tile set"]
+        );
+    }
+}
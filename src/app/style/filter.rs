@@ -0,0 +1,345 @@
+//! Parsing and evaluation of the rule filter text.
+//!
+//! A filter is a list of conditions joined with `&&`, for example
+//! `class == primary && brunnel not in [bridge,tunnel]`. The conditions are converted into
+//! Galileo [`PropertyFilter`]s for rendering, and evaluated here against feature properties to
+//! find out which rules apply to a feature without going through the renderer.
+//!
+//! Unlike the original parser, operators are searched for longest first, so `rank >= 3` is a
+//! `>=` condition on `rank` (not `>` with the value `= 3`) and `ref not exist` is a
+//! `not exist` condition on `ref` (not `exist` on `ref not`).
+
+use galileo::layer::vector_tile_layer::style::{PropertyFilter, PropertyFilterOperator};
+
+/// Operators in the order they are searched for in a condition. Longer operators come before
+/// the shorter ones they contain (`>=` before `>`, `not exist` before `exist`).
+const OPERATORS: [(&str, FilterOperator); 10] = [
+    ("==", FilterOperator::Equal),
+    ("!=", FilterOperator::NotEqual),
+    (">=", FilterOperator::GreaterOrEqual),
+    ("<=", FilterOperator::LessOrEqual),
+    (">", FilterOperator::Greater),
+    ("<", FilterOperator::Less),
+    (" not in ", FilterOperator::NotIn),
+    (" in ", FilterOperator::In),
+    ("not exist", FilterOperator::NotExist),
+    ("exist", FilterOperator::Exist),
+];
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub(super) enum FilterOperator {
+    Equal,
+    NotEqual,
+    Greater,
+    Less,
+    GreaterOrEqual,
+    LessOrEqual,
+    In,
+    NotIn,
+    Exist,
+    NotExist,
+}
+
+impl FilterOperator {
+    fn as_str(self) -> &'static str {
+        match self {
+            FilterOperator::Equal => "==",
+            FilterOperator::NotEqual => "!=",
+            FilterOperator::Greater => ">",
+            FilterOperator::Less => "<",
+            FilterOperator::GreaterOrEqual => ">=",
+            FilterOperator::LessOrEqual => "<=",
+            FilterOperator::In => "in",
+            FilterOperator::NotIn => "not in",
+            FilterOperator::Exist => "exist",
+            FilterOperator::NotExist => "not exist",
+        }
+    }
+}
+
+/// A single `property operator value` condition of a rule filter.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(super) struct FilterCondition {
+    pub(super) property: String,
+    pub(super) operator: FilterOperator,
+    /// Compared values. `in` and `not in` conditions can have several values, `exist` and
+    /// `not exist` have none.
+    pub(super) values: Vec<String>,
+}
+
+impl FilterCondition {
+    fn parse(block: &str) -> Result<Option<Self>, String> {
+        for (token, operator) in OPERATORS {
+            if !block.contains(token) {
+                continue;
+            }
+
+            let parts: Vec<&str> = block.split(token).map(|v| v.trim()).collect();
+            if parts.len() != 2 || parts[0].is_empty() {
+                return Err(format!("Invalid filter block: {block}"));
+            }
+
+            let values = match operator {
+                FilterOperator::In | FilterOperator::NotIn => parts[1]
+                    .trim_matches(&['[', ']'][..])
+                    .split(',')
+                    .map(|v| v.trim().to_string())
+                    .filter(|v| !v.is_empty())
+                    .collect(),
+                FilterOperator::Exist | FilterOperator::NotExist => vec![],
+                _ => vec![parts[1].to_string()],
+            };
+
+            return Ok(Some(Self {
+                property: parts[0].to_string(),
+                operator,
+                values,
+            }));
+        }
+
+        // Blocks without an operator are ignored
+        Ok(None)
+    }
+
+    /// Creates a `property == value` condition. Filter text has no quoting, so returns `None` if
+    /// the condition would be parsed back differently, e.g. when the value contains `&&`, `==`
+    /// or surrounding spaces.
+    pub(super) fn equal(property: &str, value: &str) -> Option<Self> {
+        let condition = Self {
+            property: property.to_string(),
+            operator: FilterOperator::Equal,
+            values: vec![value.to_string()],
+        };
+        let parsed = parse_conditions(&condition.to_string()).ok()?;
+        (parsed == [condition.clone()]).then_some(condition)
+    }
+
+    /// Converts the condition into a Galileo property filter.
+    pub(super) fn to_property_filter(&self) -> Option<PropertyFilter> {
+        let operator =
+            PropertyFilterOperator::from_str(self.operator.as_str(), &self.values.join(","))?;
+        Some(PropertyFilter {
+            property_name: self.property.clone(),
+            operator,
+        })
+    }
+
+    /// Checks the condition against feature properties given as string key-value pairs.
+    pub(super) fn matches(&self, properties: &[(String, String)]) -> bool {
+        let value = properties
+            .iter()
+            .find(|(key, _)| key == &self.property)
+            .map(|(_, value)| value.as_str());
+
+        match (self.operator, value) {
+            (FilterOperator::Exist, value) => value.is_some(),
+            (FilterOperator::NotExist, value) => value.is_none(),
+            (FilterOperator::Equal, Some(value)) => values_equal(value, &self.values[0]),
+            (FilterOperator::NotEqual, Some(value)) => !values_equal(value, &self.values[0]),
+            (FilterOperator::NotEqual, None) => true,
+            (FilterOperator::In, Some(value)) => self.values.iter().any(|v| values_equal(value, v)),
+            (FilterOperator::NotIn, Some(value)) => {
+                !self.values.iter().any(|v| values_equal(value, v))
+            }
+            (FilterOperator::NotIn, None) => true,
+            (FilterOperator::Greater, Some(value)) => compare(value, &self.values[0]).is_gt(),
+            (FilterOperator::Less, Some(value)) => compare(value, &self.values[0]).is_lt(),
+            (FilterOperator::GreaterOrEqual, Some(value)) => {
+                compare(value, &self.values[0]).is_ge()
+            }
+            (FilterOperator::LessOrEqual, Some(value)) => compare(value, &self.values[0]).is_le(),
+            (_, None) => false,
+        }
+    }
+}
+
+impl std::fmt::Display for FilterCondition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.operator {
+            FilterOperator::Exist | FilterOperator::NotExist => {
+                write!(f, "{} {}", self.property, self.operator.as_str())
+            }
+            FilterOperator::In | FilterOperator::NotIn => write!(
+                f,
+                "{} {} [{}]",
+                self.property,
+                self.operator.as_str(),
+                self.values.join(",")
+            ),
+            _ => write!(
+                f,
+                "{} {} {}",
+                self.property,
+                self.operator.as_str(),
+                self.values[0]
+            ),
+        }
+    }
+}
+
+/// Parses filter text into a list of conditions.
+pub(super) fn parse_conditions(filter: &str) -> Result<Vec<FilterCondition>, String> {
+    let mut conditions = vec![];
+    for block in filter.split("&&") {
+        if let Some(condition) = FilterCondition::parse(block)? {
+            conditions.push(condition);
+        }
+    }
+
+    Ok(conditions)
+}
+
+fn values_equal(a: &str, b: &str) -> bool {
+    match (a.parse::<f64>(), b.parse::<f64>()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+fn compare(a: &str, b: &str) -> std::cmp::Ordering {
+    match (a.parse::<f64>(), b.parse::<f64>()) {
+        (Ok(a), Ok(b)) => a.total_cmp(&b),
+        _ => a.cmp(b),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::style::test_utils::props;
+
+    #[test]
+    fn parse_operators() {
+        let conditions =
+            parse_conditions("class == primary && rank >= 3 && ref not exist && x in [a, b]")
+                .unwrap();
+        assert_eq!(conditions.len(), 4);
+        assert_eq!(conditions[0].operator, FilterOperator::Equal);
+        assert_eq!(conditions[1].operator, FilterOperator::GreaterOrEqual);
+        assert_eq!(conditions[1].values, vec!["3"]);
+        assert_eq!(conditions[2].operator, FilterOperator::NotExist);
+        assert_eq!(conditions[2].property, "ref");
+        assert_eq!(conditions[3].operator, FilterOperator::In);
+        assert_eq!(conditions[3].values, vec!["a", "b"]);
+    }
+
+    #[test]
+    fn operator_order_regressions() {
+        // These were parsed with the shorter operator before the search order was fixed
+        let condition = |filter: &str| parse_conditions(filter).unwrap().remove(0);
+
+        let ge = condition("rank >= 3");
+        assert_eq!(
+            (ge.property.as_str(), ge.operator),
+            ("rank", FilterOperator::GreaterOrEqual)
+        );
+        assert_eq!(ge.values, vec!["3"]);
+
+        let le = condition("rank <= 3");
+        assert_eq!(
+            (le.property.as_str(), le.operator),
+            ("rank", FilterOperator::LessOrEqual)
+        );
+        assert_eq!(le.values, vec!["3"]);
+
+        let not_exist = condition("ref not exist");
+        assert_eq!(
+            (not_exist.property.as_str(), not_exist.operator),
+            ("ref", FilterOperator::NotExist)
+        );
+
+        let not_in = condition("class not in [a,b]");
+        assert_eq!(
+            (not_in.property.as_str(), not_in.operator),
+            ("class", FilterOperator::NotIn)
+        );
+        assert_eq!(not_in.values, vec!["a", "b"]);
+
+        for filter in [
+            "rank >= 3",
+            "rank <= 3",
+            "ref not exist",
+            "class not in [a,b]",
+        ] {
+            assert_eq!(condition(filter).to_string(), filter);
+        }
+    }
+
+    #[test]
+    fn missing_properties_and_numbers() {
+        let check = |filter: &str, properties: &[(&str, &str)]| {
+            parse_conditions(filter)
+                .unwrap()
+                .iter()
+                .all(|c| c.matches(&props(properties)))
+        };
+
+        // Negative operators match features without the property
+        assert!(check("class != primary", &[]));
+        assert!(check("class not in [primary,secondary]", &[]));
+        // Other comparisons don't
+        assert!(!check("class == primary", &[]));
+        assert!(!check("class in [primary]", &[]));
+        assert!(!check("rank > 1", &[]));
+        assert!(!check("rank <= 1", &[]));
+
+        // Numbers are compared as numbers, not as text
+        assert!(check("rank > 9", &[("rank", "10")]));
+        assert!(check("rank < 10", &[("rank", "9")]));
+        assert!(check("rank == 3", &[("rank", "3.0")]));
+        assert!(!check("rank != 3", &[("rank", "3.0")]));
+        assert!(check("rank in [1,3]", &[("rank", "3.0")]));
+        assert!(check("rank >= -1.5", &[("rank", "-1.5")]));
+        // Anything else is compared as text
+        assert!(check("name > a", &[("name", "b")]));
+        assert!(!check("name == 3", &[("name", "three")]));
+    }
+
+    #[test]
+    fn equal_conditions_round_trip() {
+        for (property, value) in [
+            ("class", "primary"),
+            ("name", "New York"),
+            ("name", "Rock in Rio"),
+            ("name", "not existing"),
+            ("name", "<none>"),
+            ("rank", "3"),
+        ] {
+            let condition = FilterCondition::equal(property, value).unwrap();
+            assert_eq!(
+                parse_conditions(&condition.to_string()).unwrap(),
+                vec![condition]
+            );
+        }
+
+        for (property, value) in [
+            ("name", "Fish && Chips"),
+            ("name", "a == b"),
+            ("name", "x==y"),
+            ("name", " padded "),
+            ("", "value"),
+        ] {
+            assert_eq!(FilterCondition::equal(property, value), None, "{value}");
+        }
+    }
+
+    #[test]
+    fn match_conditions() {
+        let properties = props(&[("class", "primary"), ("rank", "5")]);
+        let check = |filter: &str| {
+            parse_conditions(filter)
+                .unwrap()
+                .iter()
+                .all(|c| c.matches(&properties))
+        };
+
+        assert!(check("class == primary"));
+        assert!(!check("class != primary"));
+        assert!(check("class in [minor,primary]"));
+        assert!(check("class not in [minor,service]"));
+        assert!(check("rank > 4.5 && rank <= 5"));
+        assert!(check("ref not exist"));
+        assert!(!check("ref == 1"));
+        assert!(check(""));
+    }
+}
@@ -0,0 +1,402 @@
+//! Named groups of rules and drag-and-drop reordering of the rule list.
+
+use std::collections::HashSet;
+
+use egui::{ComboBox, Id, RichText, Stroke};
+use serde::{Deserialize, Serialize};
+
+use super::{Rule, RuleAction, RuleContext, StyleWindow};
+use crate::app::catalog::TileCatalog;
+
+/// A named collection of rules shown under a common collapsible header.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(super) struct RuleGroup {
+    pub(super) id: String,
+    pub(super) name: String,
+}
+
+/// Payload set while a rule is being dragged by its handle.
+#[derive(Debug, Copy, Clone)]
+pub(super) struct RuleDragPayload {
+    pub(super) rule_id: u64,
+}
+
+/// Shows a drag handle for the rule with the given id.
+pub(super) fn drag_handle(ui: &mut egui::Ui, rule_id: u64) {
+    ui.dnd_drag_source(
+        Id::new(("rule drag handle", rule_id)),
+        RuleDragPayload { rule_id },
+        |ui| ui.label("☰"),
+    )
+    .response
+    .on_hover_text("Drag to reorder");
+}
+
+/// Checks if a rule is being dragged over the `response` of a rule header. Draws an insertion
+/// marker while hovering and returns the drop action when the rule is released.
+pub(super) fn drop_target(ui: &egui::Ui, response: &egui::Response) -> Option<RuleAction> {
+    let payload = response.dnd_hover_payload::<RuleDragPayload>()?;
+    let pointer = ui.ctx().pointer_interact_pos()?;
+    let rect = response.rect;
+    let before = pointer.y < rect.center().y;
+
+    let y = if before { rect.top() } else { rect.bottom() };
+    ui.painter().hline(
+        rect.x_range(),
+        y,
+        Stroke::new(2.0, ui.visuals().selection.stroke.color),
+    );
+
+    response
+        .dnd_release_payload::<RuleDragPayload>()
+        .map(|_| RuleAction::Drop {
+            rule_id: payload.rule_id,
+            before,
+        })
+}
+
+/// Shows a combo box to pick the group of a rule. Returns `true` if the group was changed.
+pub(super) fn group_combo(
+    ui: &mut egui::Ui,
+    group: &mut Option<String>,
+    groups: &[RuleGroup],
+) -> bool {
+    let selected_text = group
+        .as_ref()
+        .and_then(|id| groups.iter().find(|g| &g.id == id))
+        .map(|g| g.name.clone())
+        .unwrap_or_else(|| "none".to_string());
+
+    ComboBox::new("rule group", "")
+        .selected_text(selected_text)
+        .show_ui(ui, |ui| {
+            let mut changed = ui.selectable_value(group, None, "none").changed();
+            for g in groups {
+                changed |= ui
+                    .selectable_value(group, Some(g.id.clone()), &g.name)
+                    .changed();
+            }
+            changed
+        })
+        .inner
+        .unwrap_or(false)
+}
+
+impl StyleWindow {
+    /// Moves the rule with `rule_id` next to the rule at `target` index. The moved rule joins the
+    /// group of the target rule.
+    pub(super) fn drop_rule(&mut self, rule_id: u64, target: usize, before: bool) {
+        let Some(from) = self.rules.iter().position(|rule| rule.id == rule_id) else {
+            return;
+        };
+
+        if from == target {
+            return;
+        }
+
+        let group = self.rules[target].group.clone();
+        let mut rule = self.rules.remove(from);
+        rule.group = group;
+
+        let target = if from < target { target - 1 } else { target };
+        let index = if before { target } else { target + 1 };
+        self.rules.insert(index.min(self.rules.len()), rule);
+    }
+
+    /// Moves the rule at `index` after its group changed, so that no group is split: next to the
+    /// other rules of its new group, or out of the group it is in the middle of. A rule that
+    /// doesn't split any group is not moved. Returns the new index of the rule.
+    pub(super) fn keep_group_together(&mut self, index: usize) -> usize {
+        let group = self.rules[index].group.clone();
+        let joined = |index: usize| {
+            index
+                .checked_sub(1)
+                .into_iter()
+                .chain([index + 1])
+                .any(|i| self.rules.get(i).is_some_and(|rule| rule.group == group))
+        };
+        let in_group_run = group.is_some() && joined(index);
+        let splits_run = index > 0
+            && self.rules.get(index + 1).is_some_and(|next| {
+                let previous = &self.rules[index - 1];
+                next.group.is_some() && next.group == previous.group && next.group != group
+            });
+        if in_group_run && !splits_run {
+            return index;
+        }
+
+        let rule = self.rules.remove(index);
+        let group_run_end = group.as_ref().and_then(|_| {
+            let first = self.rules.iter().position(|other| other.group == group)?;
+            Some(first + run_length(&self.rules[first..]))
+        });
+        let new_index = group_run_end.unwrap_or_else(|| {
+            if splits_run {
+                // Put the rule after the group it was in the middle of
+                index + run_length(&self.rules[index..])
+            } else {
+                index
+            }
+        });
+        self.rules.insert(new_index, rule);
+
+        new_index
+    }
+
+    /// Creates a new empty group and returns its id.
+    pub(super) fn add_group(&mut self, name: &str) -> String {
+        let mut index = self.groups.len() + 1;
+        let mut id = format!("group-{index}");
+        while self.groups.iter().any(|g| g.id == id) {
+            index += 1;
+            id = format!("group-{index}");
+        }
+
+        self.groups.push(RuleGroup {
+            id: id.clone(),
+            name: name.to_string(),
+        });
+
+        id
+    }
+
+    /// Deletes the group with the given id. Its rules stay in place without a group.
+    pub(super) fn remove_group(&mut self, id: &str) {
+        self.groups.retain(|group| group.id != id);
+        for rule in &mut self.rules {
+            if rule.group.as_deref() == Some(id) {
+                rule.group = None;
+            }
+        }
+        self.bulk_edit.forget_group(id);
+    }
+
+    /// Shows the rule list, putting consecutive rules of the same group under a collapsible
+    /// group header. A group split by other rules, like one of an imported style with mixed
+    /// layers, gets a header for every part. Returns the index of the rule the user interacted
+    /// with and the action.
+    pub(super) fn rule_list_ui(
+        &mut self,
+        ui: &mut egui::Ui,
+        catalog: &TileCatalog,
+    ) -> Option<(usize, RuleAction)> {
+        let mut ui_action = None;
+        let solo = self.is_solo();
+        let mut shown_groups = HashSet::new();
+        let mut start = 0;
+        while start < self.rules.len() {
+            let group_id = self.rules[start].group.clone();
+            let end = start
+                + self.rules[start..]
+                    .iter()
+                    .take_while(|rule| rule.group == group_id)
+                    .count();
+
+            let search = &self.search;
+            if !self.rules[start..end]
+                .iter()
+                .any(|rule| rule.matches_search(search))
+            {
+                start = end;
+                continue;
+            }
+
+            let first_rule_id = self.rules[start].id;
+            let group = group_id
+                .as_ref()
+                .and_then(|id| self.groups.iter().find(|g| &g.id == id));
+            let groups = &self.groups;
+            let analysis = &self.analysis;
+            let feature_counts = &self.feature_counts;
+            let rules = &mut self.rules[start..end];
+            let reveal = rules.iter().any(|rule| rule.reveal);
+
+            let mut show_rules = |ui: &mut egui::Ui| {
+                for (offset, rule) in rules.iter_mut().enumerate() {
+                    if !rule.matches_search(search) {
+                        continue;
+                    }
+
+                    let context = RuleContext {
+                        groups,
+                        catalog,
+                        issues: analysis
+                            .as_ref()
+                            .and_then(|analysis| analysis.get(&rule.id))
+                            .map(Vec::as_slice)
+                            .unwrap_or_default(),
+                        feature_count: feature_counts
+                            .as_ref()
+                            .and_then(|counts| counts.get(&rule.id)),
+                        show_feature_count: feature_counts.is_some(),
+                        solo,
+                    };
+                    let action = rule.ui(ui, &context).action();
+                    if action != RuleAction::None {
+                        ui_action = Some((start + offset, action));
+                    }
+                }
+            };
+
+            match group {
+                Some(group) => {
+                    let continued = if shown_groups.insert(&group.id) {
+                        ""
+                    } else {
+                        ", continued"
+                    };
+                    egui::CollapsingHeader::new(
+                        RichText::new(format!("{} ({}{continued})", group.name, end - start))
+                            .strong(),
+                    )
+                    .id_salt(("rule group", &group.id, first_rule_id))
+                    .open(reveal.then_some(true))
+                    .show(ui, show_rules);
+                }
+                None => show_rules(ui),
+            }
+
+            start = end;
+        }
+
+        ui_action
+    }
+
+    pub(super) fn groups_ui(&mut self, ui: &mut egui::Ui) {
+        let mut removed = None;
+        for group in &mut self.groups {
+            ui.horizontal(|ui| {
+                ui.add(
+                    egui::TextEdit::singleline(&mut group.name)
+                        .hint_text("Group name")
+                        .desired_width(160.0),
+                )
+                .on_hover_text("Rename the group");
+                if ui
+                    .button("Del")
+                    .on_hover_text("Delete the group. Its rules are kept without a group")
+                    .clicked()
+                {
+                    removed = Some(group.id.clone());
+                }
+            });
+        }
+        if let Some(id) = removed {
+            self.remove_group(&id);
+        }
+
+        ui.horizontal(|ui| {
+            ui.label("New group");
+            ui.text_edit_singleline(&mut self.new_group_name);
+            if ui.button("+").clicked() && !self.new_group_name.trim().is_empty() {
+                let name = std::mem::take(&mut self.new_group_name);
+                self.add_group(name.trim());
+            }
+        });
+    }
+}
+
+/// Returns the number of rules at the start of `rules` in the group of the first rule.
+fn run_length(rules: &[Rule]) -> usize {
+    let Some(first) = rules.first() else {
+        return 0;
+    };
+    rules
+        .iter()
+        .take_while(|rule| rule.group == first.group)
+        .count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::style::test_utils::window;
+
+    /// Style window with rules 1 to 5, with rules 2 and 3 in the group "a".
+    fn grouped_window() -> StyleWindow {
+        let mut window = window(&[("", ""); 5]);
+        window.rules[1].group = Some("a".to_string());
+        window.rules[2].group = Some("a".to_string());
+        window
+    }
+
+    fn ids(window: &StyleWindow) -> Vec<u64> {
+        window.rules.iter().map(|rule| rule.id).collect()
+    }
+
+    fn group_of(window: &StyleWindow, id: u64) -> Option<&str> {
+        window
+            .rules
+            .iter()
+            .find(|rule| rule.id == id)
+            .and_then(|rule| rule.group.as_deref())
+    }
+
+    #[test]
+    fn drop_rule_before_and_after() {
+        let mut window = grouped_window();
+        window.drop_rule(5, 1, true);
+        assert_eq!(ids(&window), vec![1, 5, 2, 3, 4]);
+        assert_eq!(group_of(&window, 5), Some("a"));
+
+        let mut window = grouped_window();
+        window.drop_rule(1, 3, false);
+        assert_eq!(ids(&window), vec![2, 3, 4, 1, 5]);
+        assert_eq!(group_of(&window, 1), None);
+
+        let mut window = grouped_window();
+        window.drop_rule(1, 4, false);
+        assert_eq!(ids(&window), vec![2, 3, 4, 5, 1]);
+
+        let mut window = grouped_window();
+        window.drop_rule(3, 0, true);
+        assert_eq!(ids(&window), vec![3, 1, 2, 4, 5]);
+        assert_eq!(group_of(&window, 3), None);
+
+        let mut window = grouped_window();
+        window.drop_rule(2, 1, true);
+        assert_eq!(ids(&window), vec![1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn rule_joins_its_group() {
+        let mut window = grouped_window();
+        window.rules[4].group = Some("a".to_string());
+        assert_eq!(window.keep_group_together(4), 3);
+        assert_eq!(ids(&window), vec![1, 2, 3, 5, 4]);
+
+        // A rule already next to its group stays
+        let mut window = grouped_window();
+        window.rules[0].group = Some("a".to_string());
+        assert_eq!(window.keep_group_together(0), 0);
+        assert_eq!(ids(&window), vec![1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn rule_leaves_the_middle_of_its_group() {
+        let mut window = grouped_window();
+        window.rules[3].group = Some("a".to_string());
+        window.rules[2].group = None;
+        assert_eq!(window.keep_group_together(2), 3);
+        assert_eq!(ids(&window), vec![1, 2, 4, 3, 5]);
+
+        // The only rule of a new group stays where it is
+        let mut window = grouped_window();
+        window.rules[0].group = Some("b".to_string());
+        assert_eq!(window.keep_group_together(0), 0);
+        assert_eq!(ids(&window), vec![1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn remove_group_keeps_its_rules() {
+        let mut window = grouped_window();
+        let id = window.add_group("b");
+        window.rules[4].group = Some(id.clone());
+        window.remove_group(&id);
+        window.remove_group("a");
+
+        assert!(window.groups.is_empty());
+        assert_eq!(ids(&window), vec![1, 2, 3, 4, 5]);
+        assert!(window.rules.iter().all(|rule| rule.group.is_none()));
+    }
+}
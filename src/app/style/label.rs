@@ -0,0 +1,291 @@
+//! Label-specific settings of a style rule.
+
+use std::fmt::Formatter;
+
+use galileo::render::text::{FontStyle, FontWeight, HorizontalAlignment, VerticalAlignment};
+use serde::{Deserialize, Serialize};
+
+/// Font families used for new label rules. Fallback families cover scripts that `Noto Sans`
+/// doesn't have glyphs for. All of them are in `assets/fonts`.
+pub(crate) const DEFAULT_FONT_FAMILY: &[&str] =
+    &["Noto Sans", "Noto Sans Arabic", "Noto Sans Hebrew"];
+
+/// Feature properties offered for insertion into a label pattern.
+pub(super) const COMMON_PATTERN_PROPERTIES: &[&str] = &[
+    "name",
+    "name:latin",
+    "name:nonlatin",
+    "name_en",
+    "name_int",
+    "ref",
+    "housenumber",
+    "class",
+];
+
+pub(crate) fn default_font_family() -> Vec<String> {
+    DEFAULT_FONT_FAMILY.iter().map(|v| v.to_string()).collect()
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub(super) enum LabelWeight {
+    Thin,
+    ExtraLight,
+    Light,
+    Normal,
+    Medium,
+    SemiBold,
+    #[default]
+    Bold,
+    ExtraBold,
+    Black,
+    /// Numeric weight without a name, kept as it was loaded.
+    Custom(u16),
+}
+
+impl LabelWeight {
+    pub(super) const ALL: [LabelWeight; 9] = [
+        LabelWeight::Thin,
+        LabelWeight::ExtraLight,
+        LabelWeight::Light,
+        LabelWeight::Normal,
+        LabelWeight::Medium,
+        LabelWeight::SemiBold,
+        LabelWeight::Bold,
+        LabelWeight::ExtraBold,
+        LabelWeight::Black,
+    ];
+
+    pub(super) fn from_galileo(weight: FontWeight) -> Self {
+        Self::ALL
+            .into_iter()
+            .find(|v| v.to_galileo() == weight)
+            .unwrap_or(LabelWeight::Custom(weight.0))
+    }
+
+    pub(super) fn to_galileo(self) -> FontWeight {
+        match self {
+            LabelWeight::Thin => FontWeight::THIN,
+            LabelWeight::ExtraLight => FontWeight::EXTRA_LIGHT,
+            LabelWeight::Light => FontWeight::LIGHT,
+            LabelWeight::Normal => FontWeight::NORMAL,
+            LabelWeight::Medium => FontWeight::MEDIUM,
+            LabelWeight::SemiBold => FontWeight::SEMI_BOLD,
+            LabelWeight::Bold => FontWeight::BOLD,
+            LabelWeight::ExtraBold => FontWeight::EXTRA_BOLD,
+            LabelWeight::Black => FontWeight::BLACK,
+            LabelWeight::Custom(weight) => FontWeight(weight),
+        }
+    }
+}
+
+impl std::fmt::Display for LabelWeight {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LabelWeight::Thin => write!(f, "thin"),
+            LabelWeight::ExtraLight => write!(f, "extra light"),
+            LabelWeight::Light => write!(f, "light"),
+            LabelWeight::Normal => write!(f, "normal"),
+            LabelWeight::Medium => write!(f, "medium"),
+            LabelWeight::SemiBold => write!(f, "semi bold"),
+            LabelWeight::Bold => write!(f, "bold"),
+            LabelWeight::ExtraBold => write!(f, "extra bold"),
+            LabelWeight::Black => write!(f, "black"),
+            LabelWeight::Custom(weight) => write!(f, "{weight}"),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub(super) enum LabelStyle {
+    #[default]
+    Normal,
+    Italic,
+    Oblique,
+}
+
+impl LabelStyle {
+    pub(super) const ALL: [LabelStyle; 3] =
+        [LabelStyle::Normal, LabelStyle::Italic, LabelStyle::Oblique];
+
+    pub(super) fn from_galileo(style: FontStyle) -> Self {
+        match style {
+            FontStyle::Normal => LabelStyle::Normal,
+            FontStyle::Italic => LabelStyle::Italic,
+            FontStyle::Oblique => LabelStyle::Oblique,
+        }
+    }
+
+    pub(super) fn to_galileo(self) -> FontStyle {
+        match self {
+            LabelStyle::Normal => FontStyle::Normal,
+            LabelStyle::Italic => FontStyle::Italic,
+            LabelStyle::Oblique => FontStyle::Oblique,
+        }
+    }
+}
+
+impl std::fmt::Display for LabelStyle {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LabelStyle::Normal => write!(f, "normal"),
+            LabelStyle::Italic => write!(f, "italic"),
+            LabelStyle::Oblique => write!(f, "oblique"),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub(super) enum LabelHorizontalAlignment {
+    Left,
+    #[default]
+    Center,
+    Right,
+}
+
+impl LabelHorizontalAlignment {
+    pub(super) const ALL: [LabelHorizontalAlignment; 3] = [
+        LabelHorizontalAlignment::Left,
+        LabelHorizontalAlignment::Center,
+        LabelHorizontalAlignment::Right,
+    ];
+
+    pub(super) fn from_galileo(alignment: HorizontalAlignment) -> Self {
+        match alignment {
+            HorizontalAlignment::Left => LabelHorizontalAlignment::Left,
+            HorizontalAlignment::Center => LabelHorizontalAlignment::Center,
+            HorizontalAlignment::Right => LabelHorizontalAlignment::Right,
+        }
+    }
+
+    pub(super) fn to_galileo(self) -> HorizontalAlignment {
+        match self {
+            LabelHorizontalAlignment::Left => HorizontalAlignment::Left,
+            LabelHorizontalAlignment::Center => HorizontalAlignment::Center,
+            LabelHorizontalAlignment::Right => HorizontalAlignment::Right,
+        }
+    }
+}
+
+impl std::fmt::Display for LabelHorizontalAlignment {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LabelHorizontalAlignment::Left => write!(f, "left"),
+            LabelHorizontalAlignment::Center => write!(f, "center"),
+            LabelHorizontalAlignment::Right => write!(f, "right"),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub(super) enum LabelVerticalAlignment {
+    Top,
+    #[default]
+    Middle,
+    Bottom,
+}
+
+impl LabelVerticalAlignment {
+    pub(super) const ALL: [LabelVerticalAlignment; 3] = [
+        LabelVerticalAlignment::Top,
+        LabelVerticalAlignment::Middle,
+        LabelVerticalAlignment::Bottom,
+    ];
+
+    pub(super) fn from_galileo(alignment: VerticalAlignment) -> Self {
+        match alignment {
+            VerticalAlignment::Top => LabelVerticalAlignment::Top,
+            VerticalAlignment::Middle => LabelVerticalAlignment::Middle,
+            VerticalAlignment::Bottom => LabelVerticalAlignment::Bottom,
+        }
+    }
+
+    pub(super) fn to_galileo(self) -> VerticalAlignment {
+        match self {
+            LabelVerticalAlignment::Top => VerticalAlignment::Top,
+            LabelVerticalAlignment::Middle => VerticalAlignment::Middle,
+            LabelVerticalAlignment::Bottom => VerticalAlignment::Bottom,
+        }
+    }
+}
+
+impl std::fmt::Display for LabelVerticalAlignment {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LabelVerticalAlignment::Top => write!(f, "top"),
+            LabelVerticalAlignment::Middle => write!(f, "middle"),
+            LabelVerticalAlignment::Bottom => write!(f, "bottom"),
+        }
+    }
+}
+
+/// Shows a combo box listing all `values` and returns `true` if the selection was changed.
+pub(super) fn enum_combo<T>(ui: &mut egui::Ui, id: &str, value: &mut T, values: &[T]) -> bool
+where
+    T: Copy + PartialEq + std::fmt::Display,
+{
+    egui::ComboBox::new(id, "")
+        .selected_text(value.to_string())
+        .show_ui(ui, |ui| {
+            let mut changed = false;
+            for v in values {
+                changed |= ui.selectable_value(value, *v, v.to_string()).changed();
+            }
+            changed
+        })
+        .inner
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn weight_conversion() {
+        for weight in LabelWeight::ALL {
+            assert_eq!(LabelWeight::from_galileo(weight.to_galileo()), weight);
+        }
+        assert_eq!(
+            LabelWeight::from_galileo(FontWeight::SEMI_BOLD),
+            LabelWeight::SemiBold
+        );
+
+        let custom = LabelWeight::from_galileo(FontWeight(450));
+        assert_eq!(custom, LabelWeight::Custom(450));
+        assert_eq!(custom.to_galileo(), FontWeight(450));
+    }
+
+    #[test]
+    fn style_and_alignment_conversion() {
+        for style in LabelStyle::ALL {
+            assert_eq!(LabelStyle::from_galileo(style.to_galileo()), style);
+        }
+        for alignment in LabelHorizontalAlignment::ALL {
+            assert_eq!(
+                LabelHorizontalAlignment::from_galileo(alignment.to_galileo()),
+                alignment
+            );
+        }
+        for alignment in LabelVerticalAlignment::ALL {
+            assert_eq!(
+                LabelVerticalAlignment::from_galileo(alignment.to_galileo()),
+                alignment
+            );
+        }
+    }
+
+    #[test]
+    fn default_fonts_are_bundled() {
+        for family in DEFAULT_FONT_FAMILY {
+            let path = format!(
+                "{}/assets/fonts/{}.ttf",
+                env!("CARGO_MANIFEST_DIR"),
+                family.replace(' ', "")
+            );
+            assert!(
+                std::path::Path::new(&path).exists(),
+                "{family} is not in assets/fonts"
+            );
+        }
+    }
+}
@@ -0,0 +1,239 @@
+//! Finding the rules that apply to a feature and creating rules from features.
+
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+use itertools::Itertools;
+
+use super::{filter::FilterCondition, Rule, StyleWindow, SymbolType};
+use crate::app::inspector::GeometryType;
+
+/// The visible rules of a style prepared for matching many features.
+#[derive(Hash)]
+pub struct RuleMatcher {
+    rules: Vec<MatcherRule>,
+}
+
+#[derive(Hash)]
+struct MatcherRule {
+    id: u64,
+    layer_name: String,
+    conditions: Vec<FilterCondition>,
+}
+
+impl MatcherRule {
+    fn new(rule: &Rule) -> Self {
+        Self {
+            id: rule.id,
+            layer_name: rule.layer_name.clone(),
+            conditions: rule.conditions(),
+        }
+    }
+}
+
+impl RuleMatcher {
+    /// Returns the id of the rule the feature is drawn with: the first rule that applies to it.
+    pub fn first_match(&self, layer: &str, properties: &[(String, String)]) -> Option<u64> {
+        self.rules
+            .iter()
+            .find(|rule| {
+                (rule.layer_name.is_empty() || rule.layer_name == layer)
+                    && rule
+                        .conditions
+                        .iter()
+                        .all(|condition| condition.matches(properties))
+            })
+            .map(|rule| rule.id)
+    }
+
+    /// Returns a hash of the rules, which changes when a rule filter or layer is edited.
+    pub fn rules_hash(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.hash(&mut hasher);
+        hasher.finish()
+    }
+}
+
+/// Checks if a rule filter can compare the `property` with the `value`. Values containing `&&`
+/// or `==` can't be written in a filter.
+pub fn can_filter_by(property: &str, value: &str) -> bool {
+    FilterCondition::equal(property, value).is_some()
+}
+
+/// A rule that applies to an inspected feature.
+#[derive(Debug, Clone)]
+pub struct RuleMatch {
+    pub rule_id: u64,
+    pub title: String,
+    /// The rule is the first one matching the feature, so the feature is drawn with it. Other
+    /// matching rules are shadowed by it.
+    pub is_used: bool,
+}
+
+impl StyleWindow {
+    /// Prepares the visible rules for matching features with [`RuleMatcher`].
+    pub fn rule_matcher(&self) -> RuleMatcher {
+        RuleMatcher {
+            rules: self
+                .rules
+                .iter()
+                .filter(|rule| rule.visible)
+                .map(MatcherRule::new)
+                .collect(),
+        }
+    }
+
+    /// Returns the ids of the selected rules and of the rule under the mouse cursor.
+    pub fn highlighted_rule_ids(&self) -> Vec<u64> {
+        self.rules
+            .iter()
+            .filter(|rule| rule.selected || rule.hovered)
+            .map(|rule| rule.id)
+            .collect()
+    }
+
+    /// Prepares the rules with the given ids for matching features, whether they are visible
+    /// or not. Returns `None` if there are no such rules.
+    pub fn matcher_for(&self, rule_ids: &[u64]) -> Option<RuleMatcher> {
+        let rules: Vec<MatcherRule> = self
+            .rules
+            .iter()
+            .filter(|rule| rule_ids.contains(&rule.id))
+            .map(MatcherRule::new)
+            .collect();
+
+        (!rules.is_empty()).then_some(RuleMatcher { rules })
+    }
+
+    /// Returns all visible rules that apply to a feature of the `layer` with the given
+    /// properties, in the order of the rule list.
+    pub fn matching_rules(&self, layer: &str, properties: &[(String, String)]) -> Vec<RuleMatch> {
+        self.rules
+            .iter()
+            .filter(|rule| rule.matches_feature(layer, properties))
+            .enumerate()
+            .map(|(index, rule)| RuleMatch {
+                rule_id: rule.id,
+                title: rule.header(),
+                is_used: index == 0,
+            })
+            .collect()
+    }
+
+    /// Expands the rule with the given id, scrolls the rule list to it and highlights it.
+    pub fn reveal_rule(&mut self, rule_id: u64) {
+        let mut found = false;
+        for rule in &mut self.rules {
+            rule.highlighted = rule.id == rule_id;
+            rule.reveal = rule.highlighted;
+            found |= rule.highlighted;
+        }
+
+        if found
+            && !self
+                .rules
+                .iter()
+                .any(|rule| rule.id == rule_id && rule.matches_search(&self.search))
+        {
+            self.search.clear();
+        }
+    }
+
+    /// Creates a rule for features of the `layer` whose `filter` properties are equal to the
+    /// given values. Properties that can't be written in a filter are left out. The rule is
+    /// inserted above the rule with id `before_rule`, or at the end of the list, and is revealed
+    /// in the rule list.
+    pub fn add_feature_rule(
+        &mut self,
+        layer: &str,
+        geometry_type: GeometryType,
+        filter: &[(String, String)],
+        before_rule: Option<u64>,
+        ctx: &egui::Context,
+    ) {
+        let id = self.next_rule_id();
+        let mut rule = Rule::new_empty(id);
+        rule.layer_name = layer.to_string();
+        let conditions: Vec<FilterCondition> = filter
+            .iter()
+            .filter_map(|(key, value)| {
+                let condition = FilterCondition::equal(key, value);
+                if condition.is_none() {
+                    log::warn!("Property {key} = {value} can't be used in a rule filter");
+                }
+                condition
+            })
+            .collect();
+        rule.filter = conditions.iter().join(" && ");
+        rule.symbol_type = match geometry_type {
+            GeometryType::Point => SymbolType::Point,
+            GeometryType::Line => SymbolType::Line,
+            GeometryType::Polygon => SymbolType::Polygon,
+        };
+        rule.name = match conditions.first() {
+            Some(condition) => format!("{layer} {}", condition.values[0]),
+            None => layer.to_string(),
+        };
+        rule.color = egui::Color32::from_rgb(128, 128, 128);
+
+        let index = before_rule
+            .and_then(|rule_id| self.rules.iter().position(|rule| rule.id == rule_id))
+            .unwrap_or(self.rules.len());
+        if let Some(next) = self.rules.get(index) {
+            rule.group = next.group.clone();
+        }
+        self.rules.insert(index, rule);
+
+        self.reveal_rule(id);
+        self.mark_changed(ctx);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::style::test_utils::{props, window};
+
+    #[test]
+    fn feature_rule_filter() {
+        let mut window = window(&[]);
+        let filter = vec![
+            ("name".to_string(), "Fish && Chips".to_string()),
+            ("class".to_string(), "primary".to_string()),
+            ("ref".to_string(), "A 1".to_string()),
+        ];
+        window.add_feature_rule(
+            "transportation",
+            GeometryType::Line,
+            &filter,
+            None,
+            &egui::Context::default(),
+        );
+
+        let rule = window.rules.last().unwrap();
+        assert_eq!(rule.filter, "class == primary && ref == A 1");
+        assert_eq!(rule.name, "transportation primary");
+        let properties = props(&[("class", "primary"), ("ref", "A 1")]);
+        assert!(rule.matches_feature("transportation", &properties));
+        assert!(!can_filter_by("name", "Fish && Chips"));
+    }
+
+    #[test]
+    fn first_visible_rule_is_used() {
+        let mut window = window(&[("water", "class == lake"), ("water", ""), ("", "")]);
+        let lake = props(&[("class", "lake")]);
+        let river = props(&[("class", "river")]);
+
+        let matcher = window.rule_matcher();
+        assert_eq!(matcher.first_match("water", &lake), Some(1));
+        assert_eq!(matcher.first_match("water", &river), Some(2));
+        assert_eq!(matcher.first_match("building", &[]), Some(3));
+
+        let matches = window.matching_rules("water", &lake);
+        assert_eq!(matches.len(), 3);
+        assert!(matches[0].is_used);
+        assert!(!matches[1].is_used);
+
+        window.rules[0].visible = false;
+        assert_eq!(window.rule_matcher().first_match("water", &lake), Some(2));
+    }
+}
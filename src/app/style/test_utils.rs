@@ -0,0 +1,26 @@
+//! Fixtures shared by the tests of the style modules.
+
+use galileo::layer::vector_tile_layer::style::VectorTileStyle;
+
+use super::{Rule, StyleWindow};
+
+/// Feature properties given as name-value pairs.
+pub(super) fn props(values: &[(&str, &str)]) -> Vec<(String, String)> {
+    values
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect()
+}
+
+/// Style window with one rule for every `(layer, filter)` pair, with ids starting at 1.
+pub(super) fn window(rules: &[(&str, &str)]) -> StyleWindow {
+    let mut window = StyleWindow::new(VectorTileStyle::default());
+    for (layer, filter) in rules {
+        let mut rule = Rule::new_empty(window.next_rule_id());
+        rule.layer_name = layer.to_string();
+        rule.filter = filter.to_string();
+        window.rules.push(rule);
+    }
+
+    window
+}
@@ -0,0 +1,261 @@
+//! Overlay layer outlining the tiles in the view, labeled with their indices and how they were
+//! loaded.
+
+use std::sync::Arc;
+
+use galileo::{
+    layer::{feature_layer::Feature, FeatureLayer},
+    render::{
+        render_bundle::RenderBundle,
+        text::{FontWeight, HorizontalAlignment, TextStyle, VerticalAlignment},
+        LineCap, LinePaint,
+    },
+    symbol::Symbol,
+    tile_schema::TileIndex,
+    Color, MapView, TileSchema,
+};
+use galileo_types::{
+    cartesian::{Point2, Point3, Vector2},
+    geo::Crs,
+    geometry::Geom,
+    geometry_type::CartesianSpace2d,
+    impls::Contour,
+};
+use parking_lot::RwLock;
+
+use super::{
+    style::default_font_family,
+    tiles::{format_size, visible_tiles, TileLoad, TileLoadState, TileStore, TileStoreId},
+};
+
+const FONT_SIZE: f32 = 12.0;
+
+/// Outline of a tile, or the point its labels are drawn at.
+pub struct TileDebugFeature {
+    geometry: Geom<Point2>,
+    state: Option<TileLoadState>,
+    lines: Vec<String>,
+}
+
+impl Feature for TileDebugFeature {
+    type Geom = Geom<Point2>;
+
+    fn geometry(&self) -> &Self::Geom {
+        &self.geometry
+    }
+}
+
+pub struct TileDebugSymbol;
+
+impl Symbol<TileDebugFeature> for TileDebugSymbol {
+    fn render(
+        &self,
+        feature: &TileDebugFeature,
+        geometry: &Geom<Point3>,
+        min_resolution: f64,
+        bundle: &mut RenderBundle,
+    ) {
+        let color = state_color(feature.state);
+        match geometry {
+            Geom::Contour(contour) => {
+                let paint = LinePaint {
+                    color,
+                    width: 1.5,
+                    offset: 0.0,
+                    line_cap: LineCap::Butt,
+                };
+                bundle.add_line(contour, &paint, min_resolution);
+            }
+            Geom::Point(point) => {
+                let style = TextStyle {
+                    font_family: default_font_family(),
+                    font_size: FONT_SIZE,
+                    font_color: color,
+                    horizontal_alignment: HorizontalAlignment::Center,
+                    vertical_alignment: VerticalAlignment::Middle,
+                    weight: FontWeight::BOLD,
+                    style: Default::default(),
+                    outline_width: 2.0,
+                    outline_color: Color::BLACK,
+                };
+                // Lines are stacked around the point
+                let first_line = -(feature.lines.len() as f32 - 1.0) / 2.0;
+                for (n, line) in feature.lines.iter().enumerate() {
+                    let offset = Vector2::new(0.0, (first_line + n as f32) * FONT_SIZE * 1.3);
+                    bundle.add_label(point, line, &style, offset, false);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn state_color(state: Option<TileLoadState>) -> Color {
+    match state {
+        None => Color::rgba(160, 160, 160, 255),
+        Some(TileLoadState::Pending) => Color::rgba(255, 200, 0, 255),
+        Some(TileLoadState::Loaded) => Color::rgba(0, 220, 90, 255),
+        Some(TileLoadState::Cached) => Color::rgba(0, 170, 255, 255),
+        Some(TileLoadState::Missing) => Color::rgba(200, 120, 255, 255),
+        Some(TileLoadState::Failed) => Color::rgba(255, 50, 50, 255),
+    }
+}
+
+pub type TileDebugLayer = FeatureLayer<Point2, TileDebugFeature, TileDebugSymbol, CartesianSpace2d>;
+
+fn tile_debug_layer(features: Vec<TileDebugFeature>) -> TileDebugLayer {
+    FeatureLayer::new(features, TileDebugSymbol, Crs::EPSG3857)
+}
+
+/// Returns the label lines of a tile: its index, its state and, for loaded tiles, the size and
+/// the decoding time.
+fn label_lines(index: TileIndex, load: Option<&TileLoad>) -> Vec<String> {
+    let mut lines = vec![format!("{}/{}/{}", index.z, index.x, index.y)];
+    let Some(load) = load else {
+        lines.push("not requested".to_string());
+        return lines;
+    };
+
+    lines.push(load.state.to_string());
+    match (load.bytes, load.decode_time) {
+        (Some(bytes), Some(decode_time)) => lines.push(format!(
+            "{}, {:.1} ms",
+            format_size(bytes as u64),
+            decode_time.as_secs_f64() * 1000.0
+        )),
+        (Some(bytes), None) => lines.push(format_size(bytes as u64)),
+        _ => {}
+    }
+    lines
+}
+
+/// Keeps the overlay layer in sync with the tiles in the view.
+pub struct TileDebugOverlay {
+    layer: Arc<RwLock<TileDebugLayer>>,
+    /// Tile store, its load generation and visible tiles the overlay was built for.
+    shown: Option<(TileStoreId, u64, Vec<(u32, i32, i32)>)>,
+}
+
+impl Default for TileDebugOverlay {
+    fn default() -> Self {
+        Self {
+            layer: Arc::new(RwLock::new(tile_debug_layer(vec![]))),
+            shown: None,
+        }
+    }
+}
+
+impl TileDebugOverlay {
+    /// The overlay layer to add to the map.
+    pub fn layer(&self) -> Arc<RwLock<TileDebugLayer>> {
+        self.layer.clone()
+    }
+
+    /// Outlines the tiles in the view, or clears the overlay if it is not `enabled`. Returns
+    /// `true` if the overlay has changed and the map must be redrawn.
+    pub fn update(
+        &mut self,
+        enabled: bool,
+        store: &TileStore,
+        view: &MapView,
+        schema: &TileSchema,
+    ) -> bool {
+        if !enabled {
+            if self.shown.take().is_none() {
+                return false;
+            }
+            *self.layer.write() = tile_debug_layer(vec![]);
+            return true;
+        }
+
+        let tiles = visible_tiles(view, schema);
+        let tile_keys = tiles
+            .iter()
+            .map(|index| (index.z, index.x, index.y))
+            .collect();
+        let state = (store.id(), store.load_generation(), tile_keys);
+        if self.shown.as_ref() == Some(&state) {
+            return false;
+        }
+
+        let mut features = vec![];
+        for index in tiles {
+            let Some(bbox) = schema.tile_bbox(index) else {
+                continue;
+            };
+            let load = store.load(index);
+            let load_state = load.map(|load| load.state);
+            let outline = vec![
+                Point2::new(bbox.x_min(), bbox.y_min()),
+                Point2::new(bbox.x_min(), bbox.y_max()),
+                Point2::new(bbox.x_max(), bbox.y_max()),
+                Point2::new(bbox.x_max(), bbox.y_min()),
+            ];
+            features.push(TileDebugFeature {
+                geometry: Geom::Contour(Contour::new(outline, true)),
+                state: load_state,
+                lines: vec![],
+            });
+            features.push(TileDebugFeature {
+                geometry: Geom::Point(Point2::new(
+                    (bbox.x_min() + bbox.x_max()) / 2.0,
+                    (bbox.y_min() + bbox.y_max()) / 2.0,
+                )),
+                state: load_state,
+                lines: label_lines(index, load),
+            });
+        }
+
+        *self.layer.write() = tile_debug_layer(features);
+        self.shown = Some(state);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn tile_labels() {
+        let index = TileIndex::new(3, 5, 7);
+        let first = format!("{}/{}/{}", index.z, index.x, index.y);
+        assert_eq!(
+            label_lines(index, None),
+            vec![first.clone(), "not requested".into()]
+        );
+
+        let pending = TileLoad {
+            state: TileLoadState::Pending,
+            bytes: None,
+            decode_time: None,
+        };
+        assert_eq!(
+            label_lines(index, Some(&pending)),
+            vec![first.clone(), "pending".into()]
+        );
+
+        let cached = TileLoad {
+            state: TileLoadState::Cached,
+            bytes: Some(2560),
+            decode_time: Some(Duration::from_micros(4300)),
+        };
+        assert_eq!(
+            label_lines(index, Some(&cached)),
+            vec![first.clone(), "cached".into(), "2.5 KB, 4.3 ms".into()]
+        );
+
+        // Decoding is not timed on the web
+        let loaded = TileLoad {
+            state: TileLoadState::Loaded,
+            bytes: Some(512),
+            decode_time: None,
+        };
+        assert_eq!(
+            label_lines(index, Some(&loaded)),
+            vec![first, "loaded".into(), "512 B".into()]
+        );
+    }
+}
@@ -0,0 +1,523 @@
+//! Loading of the vector tiles shown by the map.
+//!
+//! The [`TileLoader`] is given to the `VectorTileLayer` to load its tiles. It gets the raw tile
+//! data from a [`TileSource`], decodes it and adds every decoded tile to the [`TileCatalog`] and
+//! the [`TileStore`].
+
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    fmt::Formatter,
+    io::Read,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use bytes::Bytes;
+use galileo::{
+    layer::vector_tile_layer::tile_provider::loader::{TileLoadError, VectorTileLoader},
+    tile_schema::TileIndex,
+    MapView, TileSchema,
+};
+use galileo_mvt::{MvtFeature, MvtTile};
+use galileo_types::cartesian::Rect;
+use parking_lot::RwLock;
+
+use super::catalog::TileCatalog;
+
+#[cfg(not(target_arch = "wasm32"))]
+mod cache;
+#[cfg(not(target_arch = "wasm32"))]
+mod directory;
+#[cfg(not(target_arch = "wasm32"))]
+mod mbtiles;
+mod pmtiles;
+mod rest;
+mod schema;
+mod tilejson;
+
+#[cfg(not(target_arch = "wasm32"))]
+pub use cache::{
+    clear_cache, evict_least_used, legacy_tile_path, written_cache_bytes, CacheUsage, FileCache,
+    TileCount, CACHE_DIR,
+};
+#[cfg(not(target_arch = "wasm32"))]
+pub use directory::DirectorySource;
+#[cfg(not(target_arch = "wasm32"))]
+pub use mbtiles::MbTilesSource;
+pub use pmtiles::PmTilesSource;
+pub use rest::RestSource;
+pub use schema::{
+    RowOrder, TileSchemaSettings, DEFAULT_TILE_SIZE, MAX_LATITUDE, MAX_ZOOM, WORLD_HALF_SIZE,
+};
+pub use tilejson::TileJson;
+
+/// Error returned by a [`TileSource`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TileSourceError {
+    /// The source has no tile with the given index.
+    NotFound,
+    /// The tile exists but could not be read.
+    Read(String),
+}
+
+impl std::fmt::Display for TileSourceError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TileSourceError::NotFound => write!(f, "tile not found"),
+            TileSourceError::Read(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+/// Description of the tile set of a source, as far as the source knows it.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TileSetInfo {
+    pub name: Option<String>,
+    pub attribution: Option<String>,
+    pub min_zoom: Option<u32>,
+    pub max_zoom: Option<u32>,
+    /// Area covered by the tiles as `[west, south, east, north]` in degrees.
+    pub bounds: Option<[f64; 4]>,
+    /// Default view as `[longitude, latitude, zoom]`.
+    pub center: Option<[f64; 3]>,
+    pub row_order: Option<RowOrder>,
+    pub vector_layers: Vec<VectorLayerInfo>,
+}
+
+/// Source layer declared in the tile set metadata.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VectorLayerInfo {
+    pub id: String,
+    pub fields: Vec<String>,
+}
+
+impl TileSetInfo {
+    /// Reads the source layers from the TileJSON-like `vector_layers` of the metadata.
+    pub fn parse_vector_layers(json: &str) -> Vec<VectorLayerInfo> {
+        let Ok(value) = serde_json::from_str::<serde_json::Value>(json) else {
+            log::warn!("Failed to parse the vector layers of the tile set metadata");
+            return vec![];
+        };
+
+        Self::vector_layers_of(&value)
+    }
+
+    fn vector_layers_of(value: &serde_json::Value) -> Vec<VectorLayerInfo> {
+        value["vector_layers"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|layer| {
+                Some(VectorLayerInfo {
+                    id: layer["id"].as_str()?.to_string(),
+                    fields: layer["fields"]
+                        .as_object()
+                        .map(|fields| fields.keys().cloned().collect())
+                        .unwrap_or_default(),
+                })
+            })
+            .collect()
+    }
+}
+
+/// Provider of the encoded vector tile data.
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+pub trait TileSource: Send + Sync {
+    /// Returns the protobuf-encoded tile with the given index.
+    async fn load(&self, index: TileIndex) -> Result<Bytes, TileSourceError>;
+
+    /// Returns what the source knows about its tiles.
+    fn info(&self) -> TileSetInfo {
+        TileSetInfo::default()
+    }
+
+    /// Returns `true` if the tile is loaded from a local cache instead of its original location.
+    fn is_cached(&self, _index: TileIndex) -> bool {
+        false
+    }
+}
+
+/// Source without any tiles, used when the configured source cannot be opened.
+pub struct EmptySource;
+
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+impl TileSource for EmptySource {
+    async fn load(&self, _index: TileIndex) -> Result<Bytes, TileSourceError> {
+        Err(TileSourceError::NotFound)
+    }
+}
+
+/// Runs blocking file or database access on the blocking thread pool of the runtime, so it
+/// doesn't stall the other tile loads.
+#[cfg(not(target_arch = "wasm32"))]
+async fn spawn_blocking<T: Send + 'static>(
+    f: impl FnOnce() -> T + Send + 'static,
+) -> Result<T, TileSourceError> {
+    tokio::task::spawn_blocking(f)
+        .await
+        .map_err(|err| TileSourceError::Read(format!("tile reading task failed: {err}")))
+}
+
+/// Decompresses gzip-compressed tile data. Uncompressed data is returned as is.
+pub fn decompress(bytes: Bytes) -> Result<Bytes, TileSourceError> {
+    if !bytes.starts_with(&[0x1f, 0x8b]) {
+        return Ok(bytes);
+    }
+
+    let mut decompressed = vec![];
+    flate2::read::GzDecoder::new(bytes.as_ref())
+        .read_to_end(&mut decompressed)
+        .map_err(|err| TileSourceError::Read(format!("failed to decompress tile: {err}")))?;
+
+    Ok(decompressed.into())
+}
+
+/// Maximum number of decoded tiles kept in the [`TileStore`].
+const MAX_STORED_TILES: usize = 256;
+/// Maximum number of missing tiles remembered by the [`TileStore`].
+const MAX_MISSING_TILES: usize = 10_000;
+/// Maximum number of tile loads remembered by the [`TileStore`].
+const MAX_TILE_LOADS: usize = 10_000;
+
+/// State of a tile requested by the map.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TileLoadState {
+    Pending,
+    Loaded,
+    /// Loaded from the tile cache.
+    Cached,
+    /// The source does not have the tile.
+    Missing,
+    Failed,
+}
+
+impl std::fmt::Display for TileLoadState {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TileLoadState::Pending => write!(f, "pending"),
+            TileLoadState::Loaded => write!(f, "loaded"),
+            TileLoadState::Cached => write!(f, "cached"),
+            TileLoadState::Missing => write!(f, "missing"),
+            TileLoadState::Failed => write!(f, "failed"),
+        }
+    }
+}
+
+/// How the last load of a tile went.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TileLoad {
+    pub state: TileLoadState,
+    /// Size of the encoded tile.
+    pub bytes: Option<usize>,
+    /// Time it took to decode the tile. Not measured on the web, where `Instant` is not
+    /// available.
+    pub decode_time: Option<Duration>,
+}
+
+impl TileLoad {
+    fn new(state: TileLoadState) -> Self {
+        Self {
+            state,
+            bytes: None,
+            decode_time: None,
+        }
+    }
+}
+
+/// Formats a size in bytes with the largest unit it has at least one of.
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        format!("{bytes} B")
+    } else {
+        format!("{size:.1} {}", UNITS[unit])
+    }
+}
+
+/// The most recently decoded tiles, kept to analyze the features shown on the map, the tiles
+/// the source does not have and how the tiles were loaded.
+///
+/// The map owns the tiles it draws, so the loader keeps a copy of every decoded tile here. At
+/// most [`MAX_STORED_TILES`] tiles are kept.
+#[derive(Default)]
+pub struct TileStore {
+    id: TileStoreId,
+    tiles: HashMap<(u32, i32, i32), (TileIndex, Arc<MvtTile>)>,
+    order: VecDeque<(u32, i32, i32)>,
+    missing: HashSet<(u32, i32, i32)>,
+    generation: u64,
+    loads: HashMap<(u32, i32, i32), TileLoad>,
+    load_generation: u64,
+}
+
+/// Identity of a [`TileStore`]. The generations of a new store start from zero again, so
+/// anything built from the tiles of a store must also check that the store is the same.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TileStoreId(u64);
+
+impl Default for TileStoreId {
+    fn default() -> Self {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+        Self(NEXT_ID.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+impl TileStore {
+    pub fn id(&self) -> TileStoreId {
+        self.id
+    }
+
+    fn insert(&mut self, index: TileIndex, tile: Arc<MvtTile>) {
+        let key = (index.z, index.x, index.y);
+        if self.tiles.insert(key, (index, tile)).is_none() {
+            self.order.push_back(key);
+        }
+        self.generation += 1;
+
+        while self.order.len() > MAX_STORED_TILES {
+            if let Some(key) = self.order.pop_front() {
+                self.tiles.remove(&key);
+            }
+        }
+    }
+
+    fn mark_missing(&mut self, index: TileIndex) {
+        if self.missing.len() >= MAX_MISSING_TILES {
+            self.missing.clear();
+        }
+        if self.missing.insert((index.z, index.x, index.y)) {
+            self.generation += 1;
+        }
+    }
+
+    fn set_load(&mut self, index: TileIndex, load: TileLoad) {
+        if self.loads.len() >= MAX_TILE_LOADS {
+            self.loads.clear();
+        }
+        self.loads.insert((index.z, index.x, index.y), load);
+        self.load_generation += 1;
+    }
+
+    /// Returns how the last load of the tile went, if the tile was requested.
+    pub fn load(&self, index: TileIndex) -> Option<&TileLoad> {
+        self.loads.get(&(index.z, index.x, index.y))
+    }
+
+    /// Number that changes every time a tile load starts or ends.
+    pub fn load_generation(&self) -> u64 {
+        self.load_generation
+    }
+
+    /// Returns the number of the given tiles the source does not have.
+    pub fn missing_count(&self, tiles: &[TileIndex]) -> usize {
+        tiles
+            .iter()
+            .filter(|index| self.missing.contains(&(index.z, index.x, index.y)))
+            .count()
+    }
+
+    pub fn get(&self, index: TileIndex) -> Option<&Arc<MvtTile>> {
+        self.tiles
+            .get(&(index.z, index.x, index.y))
+            .map(|(_, tile)| tile)
+    }
+
+    /// Returns the stored tiles of all zoom levels overlapping the view.
+    pub fn tiles_in_view(
+        &self,
+        view: &MapView,
+        schema: &TileSchema,
+    ) -> Vec<(TileIndex, Arc<MvtTile>)> {
+        let Some(view_bbox) = view.get_bbox() else {
+            return vec![];
+        };
+
+        self.tiles
+            .values()
+            .filter(|(index, _)| {
+                schema
+                    .tile_bbox(*index)
+                    .is_some_and(|tile_bbox| overlaps(&tile_bbox, &view_bbox))
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// Number that changes every time a tile is added, to find out if the tiles have changed.
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// Returns the features of the given tiles with their layer names. Tiles that are not
+    /// loaded are skipped. A feature crossing tile borders is returned once for every tile.
+    pub fn features<'a>(
+        &'a self,
+        tiles: &'a [TileIndex],
+    ) -> impl Iterator<Item = (&'a str, &'a MvtFeature)> + 'a {
+        tiles
+            .iter()
+            .filter_map(|index| self.get(*index))
+            .flat_map(|tile| tile.layers.iter())
+            .flat_map(|layer| {
+                layer
+                    .features
+                    .iter()
+                    .map(|feature| (layer.name.as_str(), feature))
+            })
+    }
+}
+
+/// Checks if two rectangles overlap.
+fn overlaps(a: &Rect, b: &Rect) -> bool {
+    a.x_min() < b.x_max() && b.x_min() < a.x_max() && a.y_min() < b.y_max() && b.y_min() < a.y_max()
+}
+
+/// Returns the indices of the tiles covering the visible area of the map.
+pub fn visible_tiles(view: &MapView, schema: &TileSchema) -> Vec<TileIndex> {
+    let Some(bbox) = view.get_bbox() else {
+        return vec![];
+    };
+
+    schema
+        .iter_tiles(view.resolution(), bbox)
+        .map(|tiles| tiles.collect())
+        .unwrap_or_default()
+}
+
+/// Returns the indices of the tiles of all zoom levels up to `max_zoom` covering the visible
+/// area of the map, or `None` if there are more than `limit` of them.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn area_tiles(
+    view: &MapView,
+    schema: &TileSchema,
+    max_zoom: u32,
+    limit: usize,
+) -> Option<Vec<TileIndex>> {
+    let Some(bbox) = view.get_bbox() else {
+        return Some(vec![]);
+    };
+
+    let mut tiles = vec![];
+    for lod in schema.lods.iter().filter(|lod| lod.z_index() <= max_zoom) {
+        let Some(lod_tiles) = schema.iter_tiles(lod.resolution(), bbox) else {
+            continue;
+        };
+        for index in lod_tiles {
+            if tiles.len() == limit {
+                return None;
+            }
+            tiles.push(index);
+        }
+    }
+
+    Some(tiles)
+}
+
+/// Tile loader of the vector tile layer.
+pub struct TileLoader {
+    source: Arc<dyn TileSource>,
+    catalog: Arc<RwLock<TileCatalog>>,
+    store: Arc<RwLock<TileStore>>,
+}
+
+impl TileLoader {
+    pub fn new(
+        source: Arc<dyn TileSource>,
+        catalog: Arc<RwLock<TileCatalog>>,
+        store: Arc<RwLock<TileStore>>,
+    ) -> Self {
+        Self {
+            source,
+            catalog,
+            store,
+        }
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+impl VectorTileLoader for TileLoader {
+    async fn load(&self, index: TileIndex) -> Result<MvtTile, TileLoadError> {
+        let cached = self.source.is_cached(index);
+        self.store
+            .write()
+            .set_load(index, TileLoad::new(TileLoadState::Pending));
+
+        let bytes = self.source.load(index).await.map_err(|err| {
+            let mut store = self.store.write();
+            match err {
+                TileSourceError::NotFound => {
+                    store.mark_missing(index);
+                    store.set_load(index, TileLoad::new(TileLoadState::Missing));
+                    TileLoadError::DoesNotExist
+                }
+                TileSourceError::Read(err) => {
+                    log::warn!("Failed to load tile {index:?}: {err}");
+                    store.set_load(index, TileLoad::new(TileLoadState::Failed));
+                    TileLoadError::Network
+                }
+            }
+        })?;
+
+        let size = bytes.len();
+        #[cfg(not(target_arch = "wasm32"))]
+        let started_at = std::time::Instant::now();
+        let tile = MvtTile::decode(bytes, false);
+        #[cfg(not(target_arch = "wasm32"))]
+        let decode_time = Some(started_at.elapsed());
+        #[cfg(target_arch = "wasm32")]
+        let decode_time = None;
+        let load = TileLoad {
+            state: if cached {
+                TileLoadState::Cached
+            } else {
+                TileLoadState::Loaded
+            },
+            bytes: Some(size),
+            decode_time,
+        };
+        let tile = tile.map_err(|err| {
+            log::warn!("Failed to decode tile {index:?}: {err:?}");
+            self.store.write().set_load(
+                index,
+                TileLoad {
+                    state: TileLoadState::Failed,
+                    ..load
+                },
+            );
+            TileLoadError::Decoding
+        })?;
+
+        self.catalog.write().add_tile(&tile);
+        let mut store = self.store.write();
+        store.insert(index, Arc::new(tile.clone()));
+        store.set_load(index, load);
+
+        Ok(tile)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sizes() {
+        assert_eq!(format_size(512), "512 B");
+        assert_eq!(format_size(1536), "1.5 KB");
+        assert_eq!(format_size(3 * 1024 * 1024), "3.0 MB");
+        assert_eq!(format_size(5 * 1024 * 1024 * 1024), "5.0 GB");
+    }
+}
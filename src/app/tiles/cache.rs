@@ -0,0 +1,409 @@
+//! Tiles stored on disk as `{z}/{x}/{y}.pbf` files.
+//!
+//! Every source has its own directory in [`CACHE_DIR`]. The modification time of a tile file is
+//! updated every time the tile is read, so the least recently used tiles can be evicted when the
+//! cache grows over its size limit.
+//!
+//! Tiles cached by Galileo's file cache, which stored them in [`CACHE_DIR`] under their URL
+//! without the scheme, are moved to the cache of their source when they are first read. Until
+//! then they are counted and evicted like the other cached tiles, under the name of their host.
+
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicU64, Ordering},
+    time::SystemTime,
+};
+
+use bytes::Bytes;
+use galileo::tile_schema::TileIndex;
+
+/// Directory with the tile caches of all sources.
+pub const CACHE_DIR: &str = ".tile_cache";
+
+/// Number of bytes written to the caches since the start of the app.
+static WRITTEN_BYTES: AtomicU64 = AtomicU64::new(0);
+
+/// Returns the number of bytes written to the caches since the start of the app.
+pub fn written_cache_bytes() -> u64 {
+    WRITTEN_BYTES.load(Ordering::Relaxed)
+}
+
+/// Disk cache of the tiles of one source.
+#[derive(Debug, Clone)]
+pub struct FileCache {
+    root: PathBuf,
+}
+
+impl FileCache {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    fn tile_path(&self, index: TileIndex) -> PathBuf {
+        self.root
+            .join(index.z.to_string())
+            .join(index.x.to_string())
+            .join(format!("{}.pbf", index.y))
+    }
+
+    /// Returns the cached tile, if there is one.
+    pub fn get(&self, index: TileIndex) -> Option<Bytes> {
+        let path = self.tile_path(index);
+        let bytes = std::fs::read(&path).ok()?;
+        // Failing to mark the tile as used only makes it evicted earlier
+        if let Ok(file) = std::fs::File::options().write(true).open(&path) {
+            let _ = file.set_modified(SystemTime::now());
+        }
+
+        Some(bytes.into())
+    }
+
+    /// Moves a tile from its path in the Galileo file cache layout (see [`legacy_tile_path`]) to
+    /// this cache and returns it.
+    pub fn take_legacy(&self, index: TileIndex, legacy_path: &Path) -> Option<Bytes> {
+        let bytes = std::fs::read(legacy_path).ok()?;
+        self.put(index, &bytes);
+        if let Err(err) = std::fs::remove_file(legacy_path) {
+            log::warn!("Failed to remove migrated tile {legacy_path:?}: {err}");
+        }
+
+        Some(bytes.into())
+    }
+
+    /// Returns `true` if the tile is cached.
+    pub fn contains(&self, index: TileIndex) -> bool {
+        self.tile_path(index).is_file()
+    }
+
+    /// Stores the tile. Failures are logged, since the tile can still be used without the cache.
+    pub fn put(&self, index: TileIndex, bytes: &[u8]) {
+        write(self.tile_path(index), bytes);
+    }
+
+    /// Returns a cached file describing the tiles, like the TileJSON of the source.
+    pub fn get_file(&self, name: &str) -> Option<Bytes> {
+        std::fs::read(self.root.join(name)).ok().map(Bytes::from)
+    }
+
+    /// Stores a file describing the tiles. Failures are logged.
+    pub fn put_file(&self, name: &str, bytes: &[u8]) {
+        write(self.root.join(name), bytes);
+    }
+}
+
+/// Path of a tile in the layout of Galileo's file cache: the tile URL without the scheme in
+/// [`CACHE_DIR`].
+pub fn legacy_tile_path(url: &str) -> PathBuf {
+    let path = url
+        .strip_prefix("https://")
+        .or_else(|| url.strip_prefix("http://"))
+        .unwrap_or(url);
+    Path::new(CACHE_DIR).join(path)
+}
+
+fn write(path: PathBuf, bytes: &[u8]) {
+    if let Some(dir) = path.parent() {
+        if let Err(err) = std::fs::create_dir_all(dir) {
+            log::warn!("Failed to create tile cache directory {dir:?}: {err}");
+            return;
+        }
+    }
+
+    match std::fs::write(&path, bytes) {
+        Ok(()) => {
+            WRITTEN_BYTES.fetch_add(bytes.len() as u64, Ordering::Relaxed);
+        }
+        Err(err) => log::warn!("Failed to write to tile cache {path:?}: {err}"),
+    }
+}
+
+/// Number and size of the cached tiles.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TileCount {
+    pub tiles: usize,
+    pub bytes: u64,
+}
+
+impl TileCount {
+    fn add(&mut self, other: TileCount) {
+        self.tiles += other.tiles;
+        self.bytes += other.bytes;
+    }
+}
+
+/// Cached tiles of a source by zoom level.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SourceUsage {
+    /// Name of the cache directory of the source.
+    pub name: String,
+    pub zoom_levels: BTreeMap<u32, TileCount>,
+}
+
+impl SourceUsage {
+    pub fn total(&self) -> TileCount {
+        let mut total = TileCount::default();
+        for count in self.zoom_levels.values() {
+            total.add(*count);
+        }
+        total
+    }
+}
+
+/// Contents of the cache directory.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CacheUsage {
+    pub sources: Vec<SourceUsage>,
+}
+
+impl CacheUsage {
+    /// Counts the cached tiles in the directory with the caches of the sources.
+    pub fn scan(root: &Path) -> Self {
+        let mut sources: Vec<SourceUsage> = subdirectories(root)
+            .map(|(name, dir)| {
+                let mut usage = SourceUsage {
+                    name,
+                    zoom_levels: BTreeMap::new(),
+                };
+                for_each_tile(&dir, &mut |zoom, _, metadata| {
+                    usage.zoom_levels.entry(zoom).or_default().add(TileCount {
+                        tiles: 1,
+                        bytes: metadata.len(),
+                    })
+                });
+                usage
+            })
+            .collect();
+        sources.sort_by(|a, b| a.name.cmp(&b.name));
+
+        Self { sources }
+    }
+
+    pub fn total(&self) -> TileCount {
+        let mut total = TileCount::default();
+        for source in &self.sources {
+            total.add(source.total());
+        }
+        total
+    }
+}
+
+/// Removes the least recently used tiles of all sources until the tiles take at most
+/// `max_bytes`. Returns the number and the size of the removed tiles.
+pub fn evict_least_used(root: &Path, max_bytes: u64) -> TileCount {
+    let mut tiles = vec![];
+    for (_, source_dir) in subdirectories(root) {
+        for_each_tile(&source_dir, &mut |_, path, metadata| {
+            let used = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+            tiles.push((used, metadata.len(), path));
+        });
+    }
+
+    let mut size: u64 = tiles.iter().map(|(_, bytes, _)| bytes).sum();
+    let mut removed = TileCount::default();
+    if size <= max_bytes {
+        return removed;
+    }
+
+    tiles.sort_by_key(|(used, _, _)| *used);
+    for (_, bytes, path) in tiles {
+        if size <= max_bytes {
+            break;
+        }
+        match std::fs::remove_file(&path) {
+            Ok(()) => {
+                size -= bytes;
+                removed.add(TileCount { tiles: 1, bytes });
+            }
+            Err(err) => log::warn!("Failed to remove cached tile {path:?}: {err}"),
+        }
+    }
+
+    removed
+}
+
+/// Removes the cache of one source, or all caches if `source` is `None`.
+pub fn clear_cache(root: &Path, source: Option<&str>) {
+    let dir = match source {
+        Some(source) => root.join(source),
+        None => root.to_path_buf(),
+    };
+    if let Err(err) = std::fs::remove_dir_all(&dir) {
+        if err.kind() != std::io::ErrorKind::NotFound {
+            log::error!("Failed to remove tile cache {dir:?}: {err}");
+        }
+    }
+}
+
+/// Returns the names and the paths of the subdirectories.
+fn subdirectories(dir: &Path) -> impl Iterator<Item = (String, PathBuf)> {
+    std::fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_ok_and(|file_type| file_type.is_dir()))
+        .map(|entry| {
+            (
+                entry.file_name().to_string_lossy().into_owned(),
+                entry.path(),
+            )
+        })
+}
+
+/// Calls `f` with the zoom level, the path and the metadata of every tile file in the directory
+/// and its subdirectories. Tile files are the files in `{z}/{x}` directories, so the tiles of the
+/// Galileo cache layout, which have the URL path before `{z}`, are found as well.
+fn for_each_tile(dir: &Path, f: &mut impl FnMut(u32, PathBuf, std::fs::Metadata)) {
+    let entries = std::fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.ok());
+    for entry in entries {
+        let Ok(metadata) = entry.metadata() else {
+            continue;
+        };
+        let path = entry.path();
+        if metadata.is_dir() {
+            for_each_tile(&path, f);
+        } else if let Some(zoom) = tile_zoom(&path) {
+            f(zoom, path, metadata);
+        }
+    }
+}
+
+/// Returns the zoom level of a file at a `{z}/{x}/{y}` path.
+fn tile_zoom(path: &Path) -> Option<u32> {
+    let number = |path: &Path| path.file_name()?.to_str()?.parse::<u32>().ok();
+    let column_dir = path.parent()?;
+    number(column_dir)?;
+    number(column_dir.parent()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn scan_and_evict() {
+        let root = std::env::temp_dir().join(format!("tile-cache-test-{}", std::process::id()));
+        clear_cache(&root, None);
+
+        let first = FileCache::new(root.join("first"));
+        let second = FileCache::new(root.join("second"));
+        let tiles = [
+            TileIndex::new(0, 0, 0),
+            TileIndex::new(1, 0, 1),
+            TileIndex::new(1, 1, 1),
+        ];
+        for index in tiles {
+            first.put(index, &[0; 100]);
+        }
+        second.put(tiles[0], &[0; 50]);
+        first.put_file("tilejson.json", b"{}");
+
+        let usage = CacheUsage::scan(&root);
+        assert_eq!(usage.sources.len(), 2);
+        assert_eq!(usage.sources[0].name, "first");
+        assert_eq!(
+            usage.sources[0].zoom_levels.get(&1),
+            Some(&TileCount {
+                tiles: 2,
+                bytes: 200
+            })
+        );
+        assert_eq!(
+            usage.total(),
+            TileCount {
+                tiles: 4,
+                bytes: 350
+            }
+        );
+
+        // Make the tiles of the first source older than the tile of the second one, then use
+        // one of them
+        let old = SystemTime::now() - Duration::from_secs(3600);
+        for index in tiles {
+            let file = std::fs::File::options()
+                .write(true)
+                .open(first.tile_path(index))
+                .unwrap();
+            file.set_modified(old).unwrap();
+        }
+        assert!(first.get(tiles[2]).is_some());
+
+        let removed = evict_least_used(&root, 200);
+        assert_eq!(removed.tiles, 2);
+        assert!(first.get(tiles[2]).is_some());
+        assert!(second.get(tiles[0]).is_some());
+        assert_eq!(CacheUsage::scan(&root).total().bytes, 150);
+
+        clear_cache(&root, Some("first"));
+        assert_eq!(CacheUsage::scan(&root).sources.len(), 1);
+        assert!(!first.contains(tiles[2]));
+        assert!(second.contains(tiles[0]));
+        clear_cache(&root, None);
+        assert!(!root.exists());
+    }
+
+    #[test]
+    fn migrate_legacy_tiles() {
+        assert_eq!(
+            legacy_tile_path("https://api.maptiler.com/tiles/v3/1/0/1.pbf?key=abc"),
+            Path::new(CACHE_DIR).join("api.maptiler.com/tiles/v3/1/0/1.pbf?key=abc")
+        );
+
+        let root = std::env::temp_dir().join(format!("tile-cache-legacy-{}", std::process::id()));
+        clear_cache(&root, None);
+        let legacy_path = root.join("example.com/tiles/1/0/1.pbf");
+        write(legacy_path.clone(), &[1, 2, 3]);
+
+        let cache = FileCache::new(root.join("source"));
+        let index = TileIndex::new(1, 0, 1);
+        assert_eq!(
+            cache.take_legacy(index, &legacy_path),
+            Some(Bytes::from_static(&[1, 2, 3]))
+        );
+        assert!(!legacy_path.exists());
+        assert_eq!(cache.get(index), Some(Bytes::from_static(&[1, 2, 3])));
+        assert_eq!(cache.take_legacy(index, &legacy_path), None);
+
+        clear_cache(&root, None);
+    }
+
+    #[test]
+    fn scan_and_evict_legacy_tiles() {
+        let root =
+            std::env::temp_dir().join(format!("tile-cache-legacy-usage-{}", std::process::id()));
+        clear_cache(&root, None);
+        let legacy_path = root.join("api.maptiler.com/tiles/v3/2/1/3.pbf?key=abc");
+        write(legacy_path.clone(), &[0; 100]);
+        let cache = FileCache::new(root.join("source"));
+        cache.put(TileIndex::new(0, 0, 0), &[0; 50]);
+
+        let usage = CacheUsage::scan(&root);
+        assert_eq!(usage.sources[0].name, "api.maptiler.com");
+        assert_eq!(
+            usage.sources[0].zoom_levels.get(&2),
+            Some(&TileCount {
+                tiles: 1,
+                bytes: 100
+            })
+        );
+        assert_eq!(usage.total().tiles, 2);
+
+        // The legacy tile was never read, so it is the least recently used one
+        let old = SystemTime::now() - Duration::from_secs(3600);
+        let file = std::fs::File::options()
+            .write(true)
+            .open(&legacy_path)
+            .unwrap();
+        file.set_modified(old).unwrap();
+        assert_eq!(evict_least_used(&root, 50).tiles, 1);
+        assert!(!legacy_path.exists());
+        assert!(cache.contains(TileIndex::new(0, 0, 0)));
+
+        clear_cache(&root, None);
+    }
+}
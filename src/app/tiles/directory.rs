@@ -0,0 +1,115 @@
+//! Tiles read from a local `{z}/{x}/{y}.pbf` (or `.mvt`) directory tree.
+
+use std::path::{Path, PathBuf};
+
+use bytes::Bytes;
+use galileo::tile_schema::TileIndex;
+
+use super::{decompress, spawn_blocking, TileJson, TileSetInfo, TileSource, TileSourceError};
+
+/// File extensions of the tiles, in the order they are looked for.
+const EXTENSIONS: [&str; 2] = ["pbf", "mvt"];
+
+/// Source reading tiles from files in a directory.
+pub struct DirectorySource {
+    root: PathBuf,
+    info: TileSetInfo,
+}
+
+impl DirectorySource {
+    /// Opens the directory. The description of the tile set is read from `metadata.json` in the
+    /// directory, if there is one.
+    pub fn open(root: impl Into<PathBuf>) -> Result<Self, String> {
+        let root = root.into();
+        if !root.is_dir() {
+            return Err(format!("{} is not a directory", root.display()));
+        }
+
+        let metadata_path = root.join("metadata.json");
+        let info = match std::fs::read_to_string(&metadata_path) {
+            Ok(json) => TileJson::parse(&json)
+                .map(|tilejson| tilejson.info)
+                .unwrap_or_else(|err| {
+                    log::warn!("Failed to read {}: {err}", metadata_path.display());
+                    TileSetInfo::default()
+                }),
+            Err(_) => TileSetInfo::default(),
+        };
+
+        Ok(Self { root, info })
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+impl TileSource for DirectorySource {
+    async fn load(&self, index: TileIndex) -> Result<Bytes, TileSourceError> {
+        let dir = self
+            .root
+            .join(index.z.to_string())
+            .join(index.x.to_string());
+        spawn_blocking(move || read_tile(&dir, index.y)).await?
+    }
+
+    fn info(&self) -> TileSetInfo {
+        self.info.clone()
+    }
+}
+
+/// Reads the tile `y` from the directory of its column.
+fn read_tile(dir: &Path, y: i32) -> Result<Bytes, TileSourceError> {
+    for extension in EXTENSIONS {
+        let path = dir.join(format!("{y}.{extension}"));
+        match std::fs::read(&path) {
+            Ok(bytes) => return decompress(bytes.into()),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(err) => {
+                return Err(TileSourceError::Read(format!(
+                    "failed to read {}: {err}",
+                    path.display()
+                )))
+            }
+        }
+    }
+
+    Err(TileSourceError::NotFound)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+
+    #[test]
+    fn read_tile_files() {
+        let root = std::env::temp_dir().join(format!("tile-directory-test-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(root.join("1/0")).unwrap();
+        std::fs::create_dir_all(root.join("2/3")).unwrap();
+        std::fs::write(root.join("1/0/1.pbf"), b"plain").unwrap();
+        let mut encoder = flate2::write::GzEncoder::new(vec![], flate2::Compression::default());
+        encoder.write_all(b"compressed").unwrap();
+        std::fs::write(root.join("2/3/2.mvt"), encoder.finish().unwrap()).unwrap();
+        std::fs::write(
+            root.join("metadata.json"),
+            r#"{"name":"Local tiles","vector_layers":[{"id":"water"}]}"#,
+        )
+        .unwrap();
+
+        let source = DirectorySource::open(&root).unwrap();
+        assert_eq!(source.info().name.as_deref(), Some("Local tiles"));
+        assert_eq!(source.info().vector_layers[0].id, "water");
+
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        let load = |z, x, y| runtime.block_on(source.load(TileIndex::new(z, x, y)));
+        assert_eq!(load(1, 0, 1), Ok(Bytes::from_static(b"plain")));
+        assert_eq!(load(2, 3, 2), Ok(Bytes::from_static(b"compressed")));
+        assert_eq!(load(2, 3, 3), Err(TileSourceError::NotFound));
+
+        std::fs::remove_dir_all(&root).unwrap();
+        assert!(DirectorySource::open(&root).is_err());
+    }
+}
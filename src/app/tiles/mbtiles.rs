@@ -0,0 +1,190 @@
+//! Tiles read from an MBTiles file.
+//!
+//! MBTiles is an SQLite database with the tiles in the `tiles` table and the description of the
+//! tile set in the `metadata` table. Tile rows are numbered from the bottom (TMS scheme).
+
+use std::{collections::HashMap, path::Path, sync::Arc};
+
+use bytes::Bytes;
+use galileo::tile_schema::TileIndex;
+use parking_lot::Mutex;
+use rusqlite::{Connection, OpenFlags, OptionalExtension};
+
+use super::{decompress, spawn_blocking, TileSetInfo, TileSource, TileSourceError};
+
+/// Source reading tiles from an MBTiles file.
+pub struct MbTilesSource {
+    connection: Arc<Mutex<Connection>>,
+    info: TileSetInfo,
+}
+
+impl MbTilesSource {
+    /// Opens the file and reads its metadata.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, String> {
+        let path = path.as_ref();
+        let connection = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+            .map_err(|err| format!("Failed to open {}: {err}", path.display()))?;
+
+        Self::from_connection(connection).map_err(|err| format!("{}: {err}", path.display()))
+    }
+
+    fn from_connection(connection: Connection) -> Result<Self, String> {
+        let metadata =
+            read_metadata(&connection).map_err(|err| format!("Failed to read metadata: {err}"))?;
+        if let Some(format) = metadata.get("format") {
+            if format != "pbf" {
+                return Err(format!(
+                    "The file contains \"{format}\" tiles instead of vector tiles"
+                ));
+            }
+        }
+
+        Ok(Self {
+            connection: Arc::new(Mutex::new(connection)),
+            info: parse_metadata(&metadata),
+        })
+    }
+}
+
+fn read_tile(connection: &Connection, index: TileIndex) -> Result<Bytes, TileSourceError> {
+    let tms_row = (1i64 << index.z) - 1 - index.y as i64;
+    let data: Option<Vec<u8>> = connection
+        .query_row(
+            "SELECT tile_data FROM tiles \
+             WHERE zoom_level = ?1 AND tile_column = ?2 AND tile_row = ?3",
+            (index.z, index.x, tms_row),
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|err| TileSourceError::Read(err.to_string()))?;
+
+    match data {
+        Some(data) => decompress(data.into()),
+        None => Err(TileSourceError::NotFound),
+    }
+}
+
+fn read_metadata(connection: &Connection) -> rusqlite::Result<HashMap<String, String>> {
+    let mut statement = connection.prepare("SELECT name, value FROM metadata")?;
+    let rows = statement.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+    rows.collect()
+}
+
+fn parse_metadata(metadata: &HashMap<String, String>) -> TileSetInfo {
+    let numbers = |key: &str| -> Option<Vec<f64>> {
+        metadata
+            .get(key)?
+            .split(',')
+            .map(|v| v.trim().parse().ok())
+            .collect()
+    };
+
+    TileSetInfo {
+        name: metadata.get("name").cloned(),
+        attribution: metadata.get("attribution").cloned(),
+        min_zoom: metadata.get("minzoom").and_then(|v| v.parse().ok()),
+        max_zoom: metadata.get("maxzoom").and_then(|v| v.parse().ok()),
+        bounds: numbers("bounds").and_then(|v| v.try_into().ok()),
+        center: numbers("center").and_then(|v| v.try_into().ok()),
+        vector_layers: metadata
+            .get("json")
+            .map(|json| TileSetInfo::parse_vector_layers(json))
+            .unwrap_or_default(),
+        ..Default::default()
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+impl TileSource for MbTilesSource {
+    async fn load(&self, index: TileIndex) -> Result<Bytes, TileSourceError> {
+        let connection = self.connection.clone();
+        spawn_blocking(move || read_tile(&connection.lock(), index)).await?
+    }
+
+    fn info(&self) -> TileSetInfo {
+        self.info.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_tile_set_metadata() {
+        let metadata: HashMap<String, String> = [
+            ("name", "Test tiles"),
+            ("minzoom", "0"),
+            ("maxzoom", "14"),
+            ("bounds", "-180.0,-85,180,85"),
+            ("center", "37.6, 55.7, 10"),
+            (
+                "json",
+                r#"{"vector_layers":[{"id":"water","fields":{"class":"String"}},{"id":"poi"}]}"#,
+            ),
+        ]
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+
+        let info = parse_metadata(&metadata);
+        assert_eq!(info.name.as_deref(), Some("Test tiles"));
+        assert_eq!((info.min_zoom, info.max_zoom), (Some(0), Some(14)));
+        assert_eq!(info.bounds, Some([-180.0, -85.0, 180.0, 85.0]));
+        assert_eq!(info.center, Some([37.6, 55.7, 10.0]));
+        assert_eq!(info.vector_layers.len(), 2);
+        assert_eq!(info.vector_layers[0].id, "water");
+        assert_eq!(info.vector_layers[0].fields, vec!["class".to_string()]);
+        assert!(info.vector_layers[1].fields.is_empty());
+    }
+
+    #[test]
+    fn read_tms_rows_and_gzip() {
+        use std::io::Write;
+
+        let connection = Connection::open_in_memory().unwrap();
+        connection
+            .execute_batch(
+                "CREATE TABLE metadata (name TEXT, value TEXT);
+                 CREATE TABLE tiles (zoom_level INTEGER, tile_column INTEGER, tile_row INTEGER,
+                                     tile_data BLOB);
+                 INSERT INTO metadata VALUES ('format', 'pbf'), ('name', 'Test tiles');",
+            )
+            .unwrap();
+
+        let mut encoder = flate2::write::GzEncoder::new(vec![], flate2::Compression::default());
+        encoder.write_all(b"compressed").unwrap();
+        let compressed = encoder.finish().unwrap();
+        // Row 1 of zoom 2 counted from the top is row 2 counted from the bottom
+        connection
+            .execute(
+                "INSERT INTO tiles VALUES (2, 3, 2, ?1), (2, 3, 1, ?2)",
+                (compressed, b"plain".to_vec()),
+            )
+            .unwrap();
+
+        let source = MbTilesSource::from_connection(connection).unwrap();
+        assert_eq!(source.info().name.as_deref(), Some("Test tiles"));
+
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        let load = |z, x, y| runtime.block_on(source.load(TileIndex::new(z, x, y)));
+        assert_eq!(load(2, 3, 1), Ok(Bytes::from_static(b"compressed")));
+        assert_eq!(load(2, 3, 2), Ok(Bytes::from_static(b"plain")));
+        assert_eq!(load(2, 3, 0), Err(TileSourceError::NotFound));
+    }
+
+    #[test]
+    fn reject_raster_tiles() {
+        let connection = Connection::open_in_memory().unwrap();
+        connection
+            .execute_batch(
+                "CREATE TABLE metadata (name TEXT, value TEXT);
+                 INSERT INTO metadata VALUES ('format', 'png');",
+            )
+            .unwrap();
+        assert!(MbTilesSource::from_connection(connection).is_err());
+    }
+}
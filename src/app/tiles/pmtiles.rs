@@ -0,0 +1,701 @@
+//! Tiles read from a PMTiles v3 archive, either a local file or a file served over HTTP.
+//!
+//! The archive starts with a fixed size header pointing to the root directory, the metadata,
+//! the leaf directories and the tile data. Directories map tile ids to the tile data or to leaf
+//! directories. Tile ids number the tiles of each zoom level along a Hilbert curve.
+
+use std::{collections::HashMap, sync::Arc};
+
+use bytes::Bytes;
+use galileo::tile_schema::TileIndex;
+use parking_lot::Mutex;
+use reqwest::StatusCode;
+
+use super::{decompress, TileSetInfo, TileSource, TileSourceError};
+
+const HEADER_LENGTH: usize = 127;
+/// Length of the first request to the archive. The header and the root directory must fit in
+/// these bytes.
+const INITIAL_READ_LENGTH: u64 = 16_384;
+/// Maximum depth of the leaf directories.
+const MAX_DIRECTORY_DEPTH: usize = 4;
+/// Maximum number of leaf directories kept in memory.
+const MAX_CACHED_LEAVES: usize = 64;
+/// Tile type of vector tiles in the header.
+const TILE_TYPE_MVT: u8 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Compression {
+    Unknown,
+    None,
+    Gzip,
+    Brotli,
+    Zstd,
+}
+
+impl Compression {
+    fn from_byte(value: u8) -> Self {
+        match value {
+            1 => Compression::None,
+            2 => Compression::Gzip,
+            3 => Compression::Brotli,
+            4 => Compression::Zstd,
+            _ => Compression::Unknown,
+        }
+    }
+
+    fn decompress(self, bytes: Bytes) -> Result<Bytes, TileSourceError> {
+        match self {
+            Compression::None => Ok(bytes),
+            // Gzip data is recognized by its magic bytes
+            Compression::Gzip | Compression::Unknown => decompress(bytes),
+            Compression::Brotli | Compression::Zstd => Err(TileSourceError::Read(format!(
+                "{self:?} compression is not supported"
+            ))),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Header {
+    root_offset: u64,
+    root_length: u64,
+    metadata_offset: u64,
+    metadata_length: u64,
+    leaf_offset: u64,
+    data_offset: u64,
+    internal_compression: Compression,
+    tile_compression: Compression,
+    tile_type: u8,
+    min_zoom: u8,
+    max_zoom: u8,
+    bounds: [f64; 4],
+    center: [f64; 3],
+}
+
+impl Header {
+    fn parse(bytes: &[u8]) -> Result<Self, String> {
+        if bytes.len() < HEADER_LENGTH || &bytes[0..7] != b"PMTiles" {
+            return Err("not a PMTiles archive".to_string());
+        }
+        if bytes[7] != 3 {
+            return Err(format!("unsupported PMTiles version {}", bytes[7]));
+        }
+
+        let u64_at = |at: usize| u64::from_le_bytes(bytes[at..at + 8].try_into().unwrap());
+        let coord_at =
+            |at: usize| i32::from_le_bytes(bytes[at..at + 4].try_into().unwrap()) as f64 / 1e7;
+
+        Ok(Self {
+            root_offset: u64_at(8),
+            root_length: u64_at(16),
+            metadata_offset: u64_at(24),
+            metadata_length: u64_at(32),
+            leaf_offset: u64_at(40),
+            data_offset: u64_at(56),
+            internal_compression: Compression::from_byte(bytes[97]),
+            tile_compression: Compression::from_byte(bytes[98]),
+            tile_type: bytes[99],
+            min_zoom: bytes[100],
+            max_zoom: bytes[101],
+            bounds: [coord_at(102), coord_at(106), coord_at(110), coord_at(114)],
+            center: [coord_at(119), coord_at(123), bytes[118] as f64],
+        })
+    }
+}
+
+/// Directory entry. An entry with zero run length points to a leaf directory, otherwise to
+/// the data of `run_length` tiles with consecutive ids.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Entry {
+    tile_id: u64,
+    offset: u64,
+    length: u64,
+    run_length: u64,
+}
+
+fn read_varint(bytes: &[u8], position: &mut usize) -> Result<u64, String> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let byte = *bytes
+            .get(*position)
+            .ok_or_else(|| "unexpected end of directory".to_string())?;
+        *position += 1;
+        value |= ((byte & 0x7f) as u64) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+
+    Err("invalid varint in directory".to_string())
+}
+
+/// Parses a decompressed directory. The columns of the entries are stored one after another.
+fn parse_directory(bytes: &[u8]) -> Result<Vec<Entry>, String> {
+    let mut position = 0;
+    let count = read_varint(bytes, &mut position)? as usize;
+    if count > bytes.len() {
+        return Err("invalid number of directory entries".to_string());
+    }
+
+    let mut entries = vec![
+        Entry {
+            tile_id: 0,
+            offset: 0,
+            length: 0,
+            run_length: 0,
+        };
+        count
+    ];
+
+    let mut last_id = 0;
+    for entry in entries.iter_mut() {
+        last_id += read_varint(bytes, &mut position)?;
+        entry.tile_id = last_id;
+    }
+    for entry in entries.iter_mut() {
+        entry.run_length = read_varint(bytes, &mut position)?;
+    }
+    for entry in entries.iter_mut() {
+        entry.length = read_varint(bytes, &mut position)?;
+    }
+    let mut previous: Option<Entry> = None;
+    for entry in entries.iter_mut() {
+        let value = read_varint(bytes, &mut position)?;
+        entry.offset = match previous {
+            // The data follows the data of the previous entry
+            Some(previous) if value == 0 => previous.offset + previous.length,
+            _ => value.saturating_sub(1),
+        };
+        previous = Some(*entry);
+    }
+
+    Ok(entries)
+}
+
+/// Returns the entry containing the tile id or pointing to the leaf directory that may
+/// contain it.
+fn find_entry(entries: &[Entry], tile_id: u64) -> Option<&Entry> {
+    let index = entries.partition_point(|entry| entry.tile_id <= tile_id);
+    let entry = entries.get(index.checked_sub(1)?)?;
+    if entry.run_length == 0 || tile_id - entry.tile_id < entry.run_length {
+        Some(entry)
+    } else {
+        None
+    }
+}
+
+/// Number of tiles on the zoom levels below `z`, which is the id of the first tile of `z`.
+fn first_tile_id(z: u32) -> u64 {
+    ((1u64 << (2 * z)) - 1) / 3
+}
+
+/// Rotates the quadrant coordinates of the Hilbert curve.
+fn rotate(n: u64, x: &mut u64, y: &mut u64, rx: u64, ry: u64) {
+    if ry == 0 {
+        if rx == 1 {
+            *x = n - 1 - *x;
+            *y = n - 1 - *y;
+        }
+        std::mem::swap(x, y);
+    }
+}
+
+fn zxy_to_tile_id(z: u32, x: u64, y: u64) -> u64 {
+    let n = 1u64 << z;
+    let (mut x, mut y) = (x, y);
+    let mut d = 0;
+    let mut s = n / 2;
+    while s > 0 {
+        let rx = u64::from(x & s > 0);
+        let ry = u64::from(y & s > 0);
+        d += s * s * ((3 * rx) ^ ry);
+        rotate(n, &mut x, &mut y, rx, ry);
+        s /= 2;
+    }
+
+    first_tile_id(z) + d
+}
+
+#[cfg(test)]
+fn tile_id_to_zxy(tile_id: u64) -> (u32, u64, u64) {
+    let mut z = 0;
+    while first_tile_id(z + 1) <= tile_id {
+        z += 1;
+    }
+
+    let n = 1u64 << z;
+    let mut t = tile_id - first_tile_id(z);
+    let (mut x, mut y) = (0, 0);
+    let mut s = 1;
+    while s < n {
+        let rx = 1 & (t / 2);
+        let ry = 1 & (t ^ rx);
+        rotate(s, &mut x, &mut y, rx, ry);
+        x += s * rx;
+        y += s * ry;
+        t /= 4;
+        s *= 2;
+    }
+
+    (z, x, y)
+}
+
+/// Where the archive bytes are read from.
+enum Archive {
+    Memory(Bytes),
+    #[cfg(not(target_arch = "wasm32"))]
+    File(Mutex<std::fs::File>),
+    Http {
+        client: reqwest::Client,
+        url: String,
+    },
+}
+
+impl Archive {
+    /// Opens an archive served over HTTP. If the server does not support range requests, the
+    /// whole archive is downloaded and read from memory.
+    async fn open_http(url: &str) -> Result<Self, String> {
+        let client = reqwest::Client::new();
+        let response = client
+            .get(url)
+            .header(
+                reqwest::header::RANGE,
+                format!("bytes=0-{}", INITIAL_READ_LENGTH - 1),
+            )
+            .send()
+            .await
+            .map_err(|err| format!("Failed to read {url}: {err}"))?;
+
+        match response.status() {
+            StatusCode::PARTIAL_CONTENT => Ok(Archive::Http {
+                client,
+                url: url.to_string(),
+            }),
+            status if status.is_success() => {
+                log::info!("{url} does not support range requests, downloading the archive");
+                let bytes = response
+                    .bytes()
+                    .await
+                    .map_err(|err| format!("Failed to read {url}: {err}"))?;
+                Ok(Archive::Memory(bytes))
+            }
+            status => Err(format!("Failed to read {url}: HTTP status {status}")),
+        }
+    }
+
+    /// Reads `length` bytes from the `offset`. Fewer bytes are returned at the end of the
+    /// archive.
+    async fn read(&self, offset: u64, length: u64) -> Result<Bytes, TileSourceError> {
+        if length == 0 {
+            return Ok(Bytes::new());
+        }
+
+        match self {
+            Archive::Memory(bytes) => {
+                let start = (offset as usize).min(bytes.len());
+                let end = (offset + length).min(bytes.len() as u64) as usize;
+                Ok(bytes.slice(start..end))
+            }
+            #[cfg(not(target_arch = "wasm32"))]
+            Archive::File(file) => {
+                use std::io::{Read, Seek, SeekFrom};
+
+                let mut file = file.lock();
+                let mut bytes = vec![];
+                file.seek(SeekFrom::Start(offset))
+                    .and_then(|_| (&mut *file).take(length).read_to_end(&mut bytes))
+                    .map_err(|err| TileSourceError::Read(err.to_string()))?;
+                Ok(bytes.into())
+            }
+            Archive::Http { client, url } => {
+                let response = client
+                    .get(url)
+                    .header(
+                        reqwest::header::RANGE,
+                        format!("bytes={offset}-{}", offset + length - 1),
+                    )
+                    .send()
+                    .await
+                    .map_err(|err| TileSourceError::Read(err.to_string()))?;
+
+                // Servers ignoring the range would send the whole archive for every read.
+                // Such archives are downloaded once when opened, see `Archive::open_http`.
+                match response.status() {
+                    StatusCode::PARTIAL_CONTENT => response
+                        .bytes()
+                        .await
+                        .map_err(|err| TileSourceError::Read(err.to_string())),
+                    status if status.is_success() => Err(TileSourceError::Read(
+                        "the server does not support range requests".to_string(),
+                    )),
+                    status => Err(TileSourceError::Read(format!("HTTP status {status}"))),
+                }
+            }
+        }
+    }
+}
+
+/// Source reading tiles from a PMTiles archive.
+pub struct PmTilesSource {
+    archive: Archive,
+    header: Header,
+    root: Vec<Entry>,
+    leaves: Mutex<HashMap<(u64, u64), Arc<Vec<Entry>>>>,
+    info: TileSetInfo,
+}
+
+impl PmTilesSource {
+    /// Opens an archive from a local path or an `http(s)://` URL and reads its header, root
+    /// directory and metadata.
+    pub async fn open(location: &str) -> Result<Self, String> {
+        let archive = if location.starts_with("http://") || location.starts_with("https://") {
+            Archive::open_http(location).await?
+        } else {
+            #[cfg(not(target_arch = "wasm32"))]
+            {
+                let file = std::fs::File::open(location)
+                    .map_err(|err| format!("Failed to open {location}: {err}"))?;
+                Archive::File(Mutex::new(file))
+            }
+            #[cfg(target_arch = "wasm32")]
+            return Err("Only PMTiles archives served over HTTP can be opened".to_string());
+        };
+
+        Self::read_archive(archive, location).await
+    }
+
+    /// Opens an archive loaded into memory.
+    pub async fn from_bytes(bytes: Bytes, name: &str) -> Result<Self, String> {
+        Self::read_archive(Archive::Memory(bytes), name).await
+    }
+
+    async fn read_archive(archive: Archive, location: &str) -> Result<Self, String> {
+        let error = |err: String| format!("Failed to read {location}: {err}");
+        let start = archive
+            .read(0, INITIAL_READ_LENGTH)
+            .await
+            .map_err(|err| error(err.to_string()))?;
+        let header = Header::parse(&start).map_err(error)?;
+        if header.tile_type != TILE_TYPE_MVT {
+            return Err(format!("{location} does not contain vector tiles"));
+        }
+
+        let root_end = (header.root_offset + header.root_length) as usize;
+        let root = if root_end <= start.len() {
+            start.slice(header.root_offset as usize..root_end)
+        } else {
+            archive
+                .read(header.root_offset, header.root_length)
+                .await
+                .map_err(|err| error(err.to_string()))?
+        };
+        let root = header
+            .internal_compression
+            .decompress(root)
+            .map_err(|err| error(err.to_string()))?;
+        let root = parse_directory(&root).map_err(error)?;
+
+        let metadata = if header.metadata_length > 0 {
+            let bytes = archive
+                .read(header.metadata_offset, header.metadata_length)
+                .await
+                .and_then(|bytes| header.internal_compression.decompress(bytes))
+                .map_err(|err| error(err.to_string()))?;
+            String::from_utf8_lossy(&bytes).into_owned()
+        } else {
+            String::new()
+        };
+
+        let info = tile_set_info(&header, &metadata);
+        Ok(Self {
+            archive,
+            header,
+            root,
+            leaves: Mutex::new(HashMap::new()),
+            info,
+        })
+    }
+
+    async fn leaf_directory(
+        &self,
+        offset: u64,
+        length: u64,
+    ) -> Result<Arc<Vec<Entry>>, TileSourceError> {
+        if let Some(entries) = self.leaves.lock().get(&(offset, length)) {
+            return Ok(entries.clone());
+        }
+
+        let bytes = self
+            .archive
+            .read(self.header.leaf_offset + offset, length)
+            .await?;
+        let bytes = self.header.internal_compression.decompress(bytes)?;
+        let entries = Arc::new(parse_directory(&bytes).map_err(TileSourceError::Read)?);
+
+        let mut leaves = self.leaves.lock();
+        if leaves.len() >= MAX_CACHED_LEAVES {
+            leaves.clear();
+        }
+        leaves.insert((offset, length), entries.clone());
+
+        Ok(entries)
+    }
+}
+
+fn tile_set_info(header: &Header, metadata: &str) -> TileSetInfo {
+    let json: serde_json::Value = serde_json::from_str(metadata).unwrap_or_default();
+    let text = |key: &str| json[key].as_str().map(str::to_string);
+
+    TileSetInfo {
+        name: text("name"),
+        attribution: text("attribution"),
+        min_zoom: Some(header.min_zoom as u32),
+        max_zoom: Some(header.max_zoom as u32),
+        bounds: Some(header.bounds),
+        center: Some(header.center),
+        vector_layers: TileSetInfo::parse_vector_layers(metadata),
+        ..Default::default()
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+impl TileSource for PmTilesSource {
+    async fn load(&self, index: TileIndex) -> Result<Bytes, TileSourceError> {
+        let z = index.z;
+        if z < self.header.min_zoom as u32 || z > self.header.max_zoom as u32 {
+            return Err(TileSourceError::NotFound);
+        }
+        let (Ok(x), Ok(y)) = (u64::try_from(index.x), u64::try_from(index.y)) else {
+            return Err(TileSourceError::NotFound);
+        };
+        if x >= 1 << z || y >= 1 << z {
+            return Err(TileSourceError::NotFound);
+        }
+
+        let tile_id = zxy_to_tile_id(z, x, y);
+        let mut directory: Option<Arc<Vec<Entry>>> = None;
+        for _ in 0..MAX_DIRECTORY_DEPTH {
+            let entries = directory.as_deref().unwrap_or(&self.root);
+            let Some(entry) = find_entry(entries, tile_id).copied() else {
+                return Err(TileSourceError::NotFound);
+            };
+
+            if entry.run_length > 0 {
+                let bytes = self
+                    .archive
+                    .read(self.header.data_offset + entry.offset, entry.length)
+                    .await?;
+                return self.header.tile_compression.decompress(bytes);
+            }
+
+            directory = Some(self.leaf_directory(entry.offset, entry.length).await?);
+        }
+
+        Err(TileSourceError::Read(
+            "too many levels of leaf directories".to_string(),
+        ))
+    }
+
+    fn info(&self) -> TileSetInfo {
+        self.info.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{BufRead, BufReader, Write},
+        net::TcpListener,
+        sync::atomic::{AtomicUsize, Ordering},
+    };
+
+    use super::*;
+
+    const SAMPLE: &[u8] = include_bytes!("../../../assets/sample.pmtiles");
+
+    /// Serves the sample archive on a local port, answering range requests with the requested
+    /// bytes if `ranges` is set, and with the whole archive otherwise. Returns the URL of the
+    /// archive and the number of requests served.
+    fn serve_sample(ranges: bool) -> (String, Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/sample.pmtiles", listener.local_addr().unwrap());
+        let requests = Arc::new(AtomicUsize::new(0));
+        let served = requests.clone();
+
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(mut stream) = stream else {
+                    continue;
+                };
+                served.fetch_add(1, Ordering::SeqCst);
+
+                let mut range = None;
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                loop {
+                    let mut line = String::new();
+                    if reader.read_line(&mut line).unwrap_or(0) == 0 || line == "\r\n" {
+                        break;
+                    }
+                    let line = line.to_ascii_lowercase();
+                    if let Some((start, end)) = line
+                        .strip_prefix("range: bytes=")
+                        .and_then(|value| value.trim().split_once('-'))
+                    {
+                        range = Some((
+                            start.parse::<usize>().unwrap(),
+                            end.parse::<usize>().unwrap(),
+                        ));
+                    }
+                }
+
+                let (status, body, content_range) = match range.filter(|_| ranges) {
+                    Some((start, end)) => {
+                        let end = end.min(SAMPLE.len() - 1);
+                        (
+                            "206 Partial Content",
+                            &SAMPLE[start..=end],
+                            format!("Content-Range: bytes {start}-{end}/{}\r\n", SAMPLE.len()),
+                        )
+                    }
+                    None => ("200 OK", SAMPLE, String::new()),
+                };
+                let _ = write!(
+                    stream,
+                    "HTTP/1.1 {status}\r\n{content_range}Content-Length: {}\r\n\
+                     Connection: close\r\n\r\n",
+                    body.len()
+                );
+                let _ = stream.write_all(body);
+            }
+        });
+
+        (url, requests)
+    }
+
+    #[test]
+    fn read_archive_over_http() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+
+        for ranges in [true, false] {
+            let (url, requests) = serve_sample(ranges);
+            let source = runtime.block_on(PmTilesSource::open(&url)).unwrap();
+            assert_eq!(source.info().name.as_deref(), Some("Sample City"));
+
+            let opening_requests = requests.load(Ordering::SeqCst);
+            let tile = runtime
+                .block_on(source.load(TileIndex::new(0, 0, 0)))
+                .unwrap();
+            assert!(!tile.is_empty());
+
+            // Without range support the archive was downloaded when it was opened
+            let tile_requests = requests.load(Ordering::SeqCst) - opening_requests;
+            assert_eq!(tile_requests > 0, ranges, "ranges: {ranges}");
+            if !ranges {
+                assert_eq!(opening_requests, 1);
+            }
+        }
+
+        // Reads of an archive on a server ignoring the range fail instead of downloading it
+        let (url, _) = serve_sample(false);
+        let archive = Archive::Http {
+            client: reqwest::Client::new(),
+            url,
+        };
+        assert!(runtime.block_on(archive.read(0, 100)).is_err());
+    }
+
+    #[test]
+    fn read_sample_archive() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        let bytes = Bytes::from_static(include_bytes!("../../../assets/sample.pmtiles"));
+        let source = runtime
+            .block_on(PmTilesSource::from_bytes(bytes, "sample"))
+            .unwrap();
+
+        let info = source.info();
+        assert_eq!(info.name.as_deref(), Some("Sample City"));
+        assert_eq!((info.min_zoom, info.max_zoom), (Some(0), Some(14)));
+        assert!(info
+            .vector_layers
+            .iter()
+            .any(|layer| layer.id == "building"));
+
+        let tile = runtime
+            .block_on(source.load(TileIndex::new(0, 0, 0)))
+            .unwrap();
+        let tile = galileo_mvt::MvtTile::decode(tile, false).unwrap();
+        assert!(tile.layers.iter().any(|layer| layer.name == "place"));
+        assert_eq!(
+            runtime.block_on(source.load(TileIndex::new(1, 0, 0))),
+            Err(TileSourceError::NotFound)
+        );
+    }
+
+    #[test]
+    fn tile_ids() {
+        assert_eq!(zxy_to_tile_id(0, 0, 0), 0);
+        assert_eq!(zxy_to_tile_id(1, 0, 0), 1);
+        assert_eq!(zxy_to_tile_id(1, 0, 1), 2);
+        assert_eq!(zxy_to_tile_id(1, 1, 1), 3);
+        assert_eq!(zxy_to_tile_id(1, 1, 0), 4);
+        assert_eq!(zxy_to_tile_id(2, 0, 0), 5);
+
+        for tile_id in 0..2000 {
+            let (z, x, y) = tile_id_to_zxy(tile_id);
+            assert_eq!(zxy_to_tile_id(z, x, y), tile_id);
+        }
+    }
+
+    fn write_varint(bytes: &mut Vec<u8>, mut value: u64) {
+        while value >= 0x80 {
+            bytes.push((value & 0x7f) as u8 | 0x80);
+            value >>= 7;
+        }
+        bytes.push(value as u8);
+    }
+
+    #[test]
+    fn directory_entries() {
+        // Tiles 0 and 1 stored one after another, a leaf directory for the tiles from 5
+        let mut bytes = vec![];
+        for value in [3, 0, 1, 4, 1, 1, 0, 100, 50, 30, 1, 0, 11] {
+            write_varint(&mut bytes, value);
+        }
+
+        let entries = parse_directory(&bytes).unwrap();
+        assert_eq!(
+            entries,
+            vec![
+                Entry {
+                    tile_id: 0,
+                    offset: 0,
+                    length: 100,
+                    run_length: 1
+                },
+                Entry {
+                    tile_id: 1,
+                    offset: 100,
+                    length: 50,
+                    run_length: 1
+                },
+                Entry {
+                    tile_id: 5,
+                    offset: 10,
+                    length: 30,
+                    run_length: 0
+                },
+            ]
+        );
+
+        assert_eq!(find_entry(&entries, 1).unwrap().tile_id, 1);
+        assert!(find_entry(&entries, 3).is_none());
+        assert_eq!(find_entry(&entries, 1000).unwrap().tile_id, 5);
+    }
+}
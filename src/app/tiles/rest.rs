@@ -0,0 +1,317 @@
+//! Tiles fetched over HTTP using a `{z}/{x}/{y}` URL template. The template can also be taken
+//! from the TileJSON of the tile set.
+
+use bytes::Bytes;
+use galileo::tile_schema::TileIndex;
+#[cfg(not(target_arch = "wasm32"))]
+use itertools::Itertools;
+use reqwest::{
+    header::{HeaderMap, HeaderName, HeaderValue},
+    StatusCode,
+};
+
+#[cfg(not(target_arch = "wasm32"))]
+use super::{legacy_tile_path, spawn_blocking, FileCache};
+use super::{TileJson, TileSetInfo, TileSource, TileSourceError};
+
+/// Name of the cached TileJSON in the tile cache.
+#[cfg(not(target_arch = "wasm32"))]
+const TILEJSON_CACHE_FILE: &str = "tilejson.json";
+
+/// Source loading tiles from a REST tile server.
+pub struct RestSource {
+    url_template: String,
+    query: Vec<(String, String)>,
+    headers: HeaderMap,
+    client: reqwest::Client,
+    #[cfg(not(target_arch = "wasm32"))]
+    cache: Option<FileCache>,
+    /// Tiles that are not in the cache are not requested from the server.
+    offline: bool,
+    info: TileSetInfo,
+}
+
+impl RestSource {
+    /// Creates a source for a URL template with `{z}`, `{x}` and `{y}` placeholders.
+    pub fn new(url_template: impl Into<String>) -> Self {
+        Self {
+            url_template: url_template.into(),
+            query: vec![],
+            headers: HeaderMap::new(),
+            client: reqwest::Client::new(),
+            #[cfg(not(target_arch = "wasm32"))]
+            cache: None,
+            offline: false,
+            info: TileSetInfo::default(),
+        }
+    }
+
+    /// Adds the query parameters to every tile request.
+    pub fn with_query(mut self, query: Vec<(String, String)>) -> Self {
+        self.query = query;
+        self
+    }
+
+    /// Sends the headers with every tile request. Headers with invalid names or values are
+    /// skipped with a warning.
+    pub fn with_headers<'a>(mut self, headers: impl Iterator<Item = (&'a str, &'a str)>) -> Self {
+        for (name, value) in headers {
+            match (
+                HeaderName::from_bytes(name.as_bytes()),
+                HeaderValue::from_str(value),
+            ) {
+                (Ok(name), Ok(value)) => {
+                    self.headers.append(name, value);
+                }
+                _ => log::warn!("Skipping invalid tile request header {name:?}"),
+            }
+        }
+        self
+    }
+
+    /// Takes the tiles only from the cache, without any network requests.
+    pub fn with_offline(mut self, offline: bool) -> Self {
+        self.offline = offline;
+        self
+    }
+
+    /// Stores loaded tiles in the cache and takes them from there when available.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn with_cache(mut self, cache: FileCache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Loads the TileJSON from the URL and takes the URL template and the description of the tile
+    /// set from it. The query parameters and the headers of the source are sent with the
+    /// request, so they must be set before. In the offline mode the cached TileJSON is used.
+    pub async fn with_tilejson(mut self, url: &str) -> Result<Self, String> {
+        let json = self.load_tilejson(url).await?;
+        let tilejson = TileJson::parse(&String::from_utf8_lossy(&json))?;
+        let template = tilejson
+            .tiles
+            .first()
+            .ok_or_else(|| format!("The TileJSON of {url} has no tile URLs"))?;
+
+        self.url_template = resolve_url(url, template);
+        self.info = tilejson.info;
+        Ok(self)
+    }
+
+    async fn load_tilejson(&self, url: &str) -> Result<Bytes, String> {
+        #[cfg(not(target_arch = "wasm32"))]
+        let cached = self
+            .cache
+            .as_ref()
+            .and_then(|cache| cache.get_file(TILEJSON_CACHE_FILE));
+        #[cfg(target_arch = "wasm32")]
+        let cached: Option<Bytes> = None;
+
+        if self.offline {
+            return cached.ok_or_else(|| format!("The TileJSON of {url} is not in the cache"));
+        }
+
+        let response = self
+            .client
+            .get(url)
+            .query(&self.query)
+            .headers(self.headers.clone())
+            .send()
+            .await
+            .and_then(|response| response.error_for_status());
+        let bytes = match response {
+            Ok(response) => response.bytes().await,
+            Err(err) => Err(err),
+        };
+
+        match bytes {
+            Ok(bytes) => {
+                #[cfg(not(target_arch = "wasm32"))]
+                if let Some(cache) = &self.cache {
+                    cache.put_file(TILEJSON_CACHE_FILE, &bytes);
+                }
+                Ok(bytes)
+            }
+            Err(err) => match cached {
+                Some(cached) => {
+                    log::warn!("Failed to load {url}, using the cached TileJSON: {err}");
+                    Ok(cached)
+                }
+                None => Err(format!("Failed to load {url}: {err}")),
+            },
+        }
+    }
+
+    fn tile_url(&self, index: TileIndex) -> String {
+        self.url_template
+            .replace("{z}", &index.z.to_string())
+            .replace("{x}", &index.x.to_string())
+            .replace("{y}", &index.y.to_string())
+    }
+
+    /// Path of the tile in the cache of Galileo's REST provider, which used the URL with the
+    /// query parameters.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn legacy_cache_path(&self, index: TileIndex) -> std::path::PathBuf {
+        let mut url = self.tile_url(index);
+        if !self.query.is_empty() {
+            url.push('?');
+            url.push_str(
+                &self
+                    .query
+                    .iter()
+                    .map(|(key, value)| format!("{key}={value}"))
+                    .join("&"),
+            );
+        }
+        legacy_tile_path(&url)
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+impl TileSource for RestSource {
+    async fn load(&self, index: TileIndex) -> Result<Bytes, TileSourceError> {
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(cache) = self.cache.clone() {
+            let legacy_path = self.legacy_cache_path(index);
+            let cached = spawn_blocking(move || {
+                cache
+                    .get(index)
+                    .or_else(|| cache.take_legacy(index, &legacy_path))
+            })
+            .await?;
+            if let Some(bytes) = cached {
+                return Ok(bytes);
+            }
+        }
+        if self.offline {
+            return Err(TileSourceError::NotFound);
+        }
+
+        let response = self
+            .client
+            .get(self.tile_url(index))
+            .query(&self.query)
+            .headers(self.headers.clone())
+            .send()
+            .await
+            .map_err(|err| TileSourceError::Read(err.to_string()))?;
+
+        match response.status() {
+            StatusCode::NOT_FOUND | StatusCode::NO_CONTENT => {
+                return Err(TileSourceError::NotFound)
+            }
+            status if !status.is_success() => {
+                return Err(TileSourceError::Read(format!("HTTP status {status}")))
+            }
+            _ => {}
+        }
+
+        let bytes = response
+            .bytes()
+            .await
+            .map_err(|err| TileSourceError::Read(err.to_string()))?;
+
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(cache) = self.cache.clone() {
+            let bytes = bytes.clone();
+            spawn_blocking(move || cache.put(index, &bytes)).await?;
+        }
+
+        Ok(bytes)
+    }
+
+    fn info(&self) -> TileSetInfo {
+        self.info.clone()
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn is_cached(&self, index: TileIndex) -> bool {
+        self.cache
+            .as_ref()
+            .is_some_and(|cache| cache.contains(index) || self.legacy_cache_path(index).is_file())
+    }
+}
+
+/// Resolves a tile URL template of a TileJSON relative to the URL of the TileJSON.
+fn resolve_url(base: &str, template: &str) -> String {
+    if template.contains("://") {
+        return template.to_string();
+    }
+
+    match reqwest::Url::parse(base).and_then(|base| base.join(template)) {
+        Ok(url) => url.to_string().replace("%7B", "{").replace("%7D", "}"),
+        Err(_) => template.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        net::TcpListener,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+    };
+
+    use super::*;
+
+    #[test]
+    fn offline_source_makes_no_requests() {
+        // Counts the connections to a local port without answering them
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let template = format!(
+            "http://{}/{{z}}/{{x}}/{{y}}.pbf",
+            listener.local_addr().unwrap()
+        );
+        let connections = Arc::new(AtomicUsize::new(0));
+        let counted = connections.clone();
+        std::thread::spawn(move || {
+            for _ in listener.incoming() {
+                counted.fetch_add(1, Ordering::SeqCst);
+            }
+        });
+
+        let root = std::env::temp_dir().join(format!("rest-offline-test-{}", std::process::id()));
+        let cache = FileCache::new(&root);
+        cache.put(TileIndex::new(1, 0, 0), b"cached");
+        let source = RestSource::new(template)
+            .with_offline(true)
+            .with_cache(cache);
+
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        assert_eq!(
+            runtime.block_on(source.load(TileIndex::new(1, 0, 0))),
+            Ok(Bytes::from_static(b"cached"))
+        );
+        assert_eq!(
+            runtime.block_on(source.load(TileIndex::new(1, 1, 0))),
+            Err(TileSourceError::NotFound)
+        );
+        assert_eq!(connections.load(Ordering::SeqCst), 0);
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn resolve_tile_urls() {
+        let base = "https://example.com/tiles/tiles.json?key=1";
+        assert_eq!(
+            resolve_url(base, "https://cdn.example.com/{z}/{x}/{y}.pbf"),
+            "https://cdn.example.com/{z}/{x}/{y}.pbf"
+        );
+        assert_eq!(
+            resolve_url(base, "{z}/{x}/{y}.pbf"),
+            "https://example.com/tiles/{z}/{x}/{y}.pbf"
+        );
+        assert_eq!(
+            resolve_url(base, "/v3/{z}/{x}/{y}.pbf"),
+            "https://example.com/v3/{z}/{x}/{y}.pbf"
+        );
+    }
+}
@@ -0,0 +1,234 @@
+//! Tile schema of a source: the size of the tiles, their zoom levels and how they are numbered.
+//!
+//! Every setting can be given in the tile source profile. Settings that are not given are taken
+//! from the tile set metadata, or get the defaults of the web mercator tiles.
+
+use galileo::{tile_schema::VerticalDirection, Lod, TileSchema};
+use galileo_types::{
+    cartesian::{Point2, Rect},
+    geo::Crs,
+};
+use serde::{Deserialize, Serialize};
+
+use super::TileSetInfo;
+
+/// Half of the width of the web mercator world in meters.
+pub const WORLD_HALF_SIZE: f64 = 20037508.342787;
+/// Latitude where the web mercator world ends.
+pub const MAX_LATITUDE: f64 = 85.051128779806;
+const EARTH_RADIUS: f64 = 6378137.0;
+
+/// Tile size used when the profile does not give one. Tile set metadata has no tile size.
+pub const DEFAULT_TILE_SIZE: u32 = 1024;
+/// Deepest zoom level used when neither the profile nor the metadata give one.
+pub const DEFAULT_MAX_ZOOM: u32 = 15;
+/// Deepest zoom level that can be configured.
+pub const MAX_ZOOM: u32 = 30;
+
+/// Order of the tile rows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RowOrder {
+    /// Rows are numbered from the top, as in the `{z}/{x}/{y}` URLs of most tile servers.
+    Xyz,
+    /// Rows are numbered from the bottom (TMS scheme).
+    Tms,
+}
+
+impl std::fmt::Display for RowOrder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RowOrder::Xyz => write!(f, "XYZ (top to bottom)"),
+            RowOrder::Tms => write!(f, "TMS (bottom to top)"),
+        }
+    }
+}
+
+/// Tile schema settings of a tile source profile. `None` values are taken from the tile set
+/// metadata or the defaults.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TileSchemaSettings {
+    /// Size of the tiles in pixels.
+    pub tile_size: Option<u32>,
+    pub min_zoom: Option<u32>,
+    pub max_zoom: Option<u32>,
+    pub row_order: Option<RowOrder>,
+    /// Corner of the tile grid where the numbering starts, in EPSG:3857 meters. It is the top
+    /// left corner for the XYZ order and the bottom left one for the TMS order.
+    pub origin: Option<[f64; 2]>,
+    /// Area with the tiles as `[x_min, y_min, x_max, y_max]` in EPSG:3857 meters.
+    pub bounds: Option<[f64; 4]>,
+}
+
+impl TileSchemaSettings {
+    /// Returns a description of the first problem with the settings, if there is one.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.tile_size == Some(0) {
+            return Err("The tile size is zero".to_string());
+        }
+        if let (Some(min), Some(max)) = (self.min_zoom, self.max_zoom) {
+            if min > max {
+                return Err("The minimum zoom level is above the maximum one".to_string());
+            }
+        }
+        if let Some([x_min, y_min, x_max, y_max]) = self.bounds {
+            if x_min >= x_max || y_min >= y_max {
+                return Err("The tile bounds are empty".to_string());
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the tile schema of the tile set.
+    pub fn tile_schema(&self, info: &TileSetInfo) -> TileSchema {
+        let tile_size = self.tile_size.unwrap_or(DEFAULT_TILE_SIZE).max(1);
+        let min_zoom = self.min_zoom.or(info.min_zoom).unwrap_or(0).min(MAX_ZOOM);
+        let max_zoom = self
+            .max_zoom
+            .or(info.max_zoom)
+            .unwrap_or(DEFAULT_MAX_ZOOM)
+            .clamp(min_zoom, MAX_ZOOM);
+        let row_order = self.row_order.or(info.row_order).unwrap_or(RowOrder::Xyz);
+
+        let [origin_x, origin_y] = self.origin.unwrap_or(match row_order {
+            RowOrder::Xyz => [-WORLD_HALF_SIZE, WORLD_HALF_SIZE],
+            RowOrder::Tms => [-WORLD_HALF_SIZE, -WORLD_HALF_SIZE],
+        });
+        let [x_min, y_min, x_max, y_max] = self
+            .bounds
+            .or_else(|| info.bounds.map(projected_bounds))
+            .unwrap_or([
+                -WORLD_HALF_SIZE,
+                -WORLD_HALF_SIZE,
+                WORLD_HALF_SIZE,
+                WORLD_HALF_SIZE,
+            ]);
+
+        // A tile of the zoom level 0 covers the whole world
+        let top_resolution = 2.0 * WORLD_HALF_SIZE / tile_size as f64;
+        let lods: Vec<Lod> = (min_zoom..=max_zoom)
+            .map(|z| Lod::new(top_resolution / 2f64.powi(z as i32), z).unwrap())
+            .collect();
+
+        TileSchema {
+            origin: Point2::new(origin_x, origin_y),
+            bounds: Rect::new(x_min, y_min, x_max, y_max),
+            lods: lods.into_iter().collect(),
+            tile_width: tile_size,
+            tile_height: tile_size,
+            y_direction: match row_order {
+                RowOrder::Xyz => VerticalDirection::TopToBottom,
+                RowOrder::Tms => VerticalDirection::BottomToTop,
+            },
+            crs: Crs::EPSG3857,
+        }
+    }
+}
+
+/// Converts `[west, south, east, north]` bounds in degrees to EPSG:3857 meters.
+fn projected_bounds([west, south, east, north]: [f64; 4]) -> [f64; 4] {
+    let x = |lon: f64| EARTH_RADIUS * lon.clamp(-180.0, 180.0).to_radians();
+    let y = |lat: f64| {
+        let lat = lat.clamp(-MAX_LATITUDE, MAX_LATITUDE).to_radians();
+        EARTH_RADIUS * (std::f64::consts::FRAC_PI_4 + lat / 2.0).tan().ln()
+    };
+
+    [x(west), y(south), x(east), y(north)]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn zoom_levels(schema: &TileSchema) -> Vec<u32> {
+        schema.lods.iter().map(|lod| lod.z_index()).collect()
+    }
+
+    fn resolution(schema: &TileSchema, z: u32) -> f64 {
+        schema
+            .lods
+            .iter()
+            .find(|lod| lod.z_index() == z)
+            .map(|lod| lod.resolution())
+            .unwrap()
+    }
+
+    #[test]
+    fn defaults() {
+        let schema = TileSchemaSettings::default().tile_schema(&TileSetInfo::default());
+        assert_eq!(schema.tile_width, DEFAULT_TILE_SIZE);
+        assert_eq!(
+            zoom_levels(&schema),
+            (0..=DEFAULT_MAX_ZOOM).collect::<Vec<_>>()
+        );
+        assert!((resolution(&schema, 0) - 156543.03392800014 / 4.0).abs() < 1e-6);
+        assert!(matches!(schema.y_direction, VerticalDirection::TopToBottom));
+        assert_eq!(
+            schema.origin,
+            Point2::new(-WORLD_HALF_SIZE, WORLD_HALF_SIZE)
+        );
+    }
+
+    #[test]
+    fn metadata_and_settings() {
+        let info = TileSetInfo {
+            min_zoom: Some(2),
+            max_zoom: Some(14),
+            row_order: Some(RowOrder::Tms),
+            ..Default::default()
+        };
+
+        let schema = TileSchemaSettings::default().tile_schema(&info);
+        assert_eq!(zoom_levels(&schema), (2..=14).collect::<Vec<_>>());
+        assert!(matches!(schema.y_direction, VerticalDirection::BottomToTop));
+        assert_eq!(
+            schema.origin,
+            Point2::new(-WORLD_HALF_SIZE, -WORLD_HALF_SIZE)
+        );
+
+        let settings = TileSchemaSettings {
+            tile_size: Some(256),
+            max_zoom: Some(18),
+            row_order: Some(RowOrder::Xyz),
+            ..Default::default()
+        };
+        let schema = settings.tile_schema(&info);
+        assert_eq!(schema.tile_width, 256);
+        assert_eq!(zoom_levels(&schema), (2..=18).collect::<Vec<_>>());
+        assert!((resolution(&schema, 2) - 156543.03392800014 / 4.0).abs() < 1e-6);
+        assert!((resolution(&schema, 18) - 156543.03392800014 / 2f64.powi(18)).abs() < 1e-9);
+        assert!(matches!(schema.y_direction, VerticalDirection::TopToBottom));
+    }
+
+    #[test]
+    fn bounds_from_metadata() {
+        let [x_min, y_min, x_max, y_max] = projected_bounds([-180.0, -90.0, 180.0, 90.0]);
+        assert!((x_min + WORLD_HALF_SIZE).abs() < 1e-3);
+        assert!((y_min + WORLD_HALF_SIZE).abs() < 1e-3);
+        assert!((x_max - WORLD_HALF_SIZE).abs() < 1e-3);
+        assert!((y_max - WORLD_HALF_SIZE).abs() < 1e-3);
+
+        let [_, y_min, _, y_max] = projected_bounds([0.0, 0.0, 10.0, 10.0]);
+        assert_eq!(y_min, 0.0);
+        assert!((y_max - 1118889.97).abs() < 0.01);
+    }
+
+    #[test]
+    fn validate_settings() {
+        assert_eq!(TileSchemaSettings::default().validate(), Ok(()));
+
+        let settings = TileSchemaSettings {
+            min_zoom: Some(10),
+            max_zoom: Some(5),
+            ..Default::default()
+        };
+        assert!(settings.validate().is_err());
+
+        let settings = TileSchemaSettings {
+            bounds: Some([0.0, 0.0, 0.0, 10.0]),
+            ..Default::default()
+        };
+        assert!(settings.validate().is_err());
+    }
+}
@@ -0,0 +1,140 @@
+//! TileJSON documents describing tile sets.
+//!
+//! Tile servers publish them next to the tiles, and tile directories often have one as
+//! `metadata.json`. The MBTiles-like metadata written by tippecanoe, with the values as strings
+//! and the vector layers in the `json` field, is read as well.
+
+use serde_json::Value;
+
+use super::{RowOrder, TileSetInfo};
+
+/// Tile set described by a TileJSON document.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TileJson {
+    /// URL templates of the tiles.
+    pub tiles: Vec<String>,
+    pub info: TileSetInfo,
+}
+
+impl TileJson {
+    pub fn parse(json: &str) -> Result<Self, String> {
+        let value: Value =
+            serde_json::from_str(json).map_err(|err| format!("Invalid TileJSON: {err}"))?;
+        if !value.is_object() {
+            return Err("Invalid TileJSON: not an object".to_string());
+        }
+
+        let text = |key: &str| value[key].as_str().map(str::to_string);
+        let zoom = |key: &str| number(&value[key]).map(|zoom| zoom as u32);
+        let vector_layers = match value["json"].as_str() {
+            Some(json) => TileSetInfo::parse_vector_layers(json),
+            None => TileSetInfo::vector_layers_of(&value),
+        };
+
+        let min_zoom = zoom("minzoom");
+        let center = numbers(&value["center"]).and_then(|center| match center[..] {
+            [lon, lat, zoom] => Some([lon, lat, zoom]),
+            [lon, lat] => Some([lon, lat, min_zoom.unwrap_or(0) as f64]),
+            _ => None,
+        });
+
+        Ok(Self {
+            tiles: value["tiles"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(|url| url.as_str().map(str::to_string))
+                .collect(),
+            info: TileSetInfo {
+                name: text("name"),
+                attribution: text("attribution"),
+                min_zoom,
+                max_zoom: zoom("maxzoom"),
+                bounds: numbers(&value["bounds"]).and_then(|bounds| bounds.try_into().ok()),
+                center,
+                row_order: match value["scheme"].as_str() {
+                    Some("tms") => Some(RowOrder::Tms),
+                    Some("xyz") => Some(RowOrder::Xyz),
+                    _ => None,
+                },
+                vector_layers,
+            },
+        })
+    }
+}
+
+/// Reads a number given as a JSON number or a string.
+fn number(value: &Value) -> Option<f64> {
+    match value {
+        Value::Number(number) => number.as_f64(),
+        Value::String(text) => text.trim().parse().ok(),
+        _ => None,
+    }
+}
+
+/// Reads numbers given as a JSON array or a comma-separated string.
+fn numbers(value: &Value) -> Option<Vec<f64>> {
+    match value {
+        Value::Array(values) => values.iter().map(number).collect(),
+        Value::String(text) => text.split(',').map(|v| v.trim().parse().ok()).collect(),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_tilejson() {
+        let json = r#"{
+            "tilejson": "3.0.0",
+            "name": "Test tiles",
+            "tiles": ["https://example.com/{z}/{x}/{y}.pbf"],
+            "minzoom": 0,
+            "maxzoom": 18,
+            "bounds": [-180, -85, 180, 85],
+            "center": [37.6, 55.7],
+            "scheme": "tms",
+            "vector_layers": [{"id": "water", "fields": {"class": "String"}}]
+        }"#;
+
+        let tilejson = TileJson::parse(json).unwrap();
+        assert_eq!(tilejson.tiles, vec!["https://example.com/{z}/{x}/{y}.pbf"]);
+        let info = tilejson.info;
+        assert_eq!(info.name.as_deref(), Some("Test tiles"));
+        assert_eq!((info.min_zoom, info.max_zoom), (Some(0), Some(18)));
+        assert_eq!(info.bounds, Some([-180.0, -85.0, 180.0, 85.0]));
+        assert_eq!(info.center, Some([37.6, 55.7, 0.0]));
+        assert_eq!(info.row_order, Some(RowOrder::Tms));
+        assert_eq!(info.vector_layers.len(), 1);
+        assert_eq!(info.vector_layers[0].fields, vec!["class".to_string()]);
+    }
+
+    #[test]
+    fn parse_tippecanoe_metadata() {
+        let json = r#"{
+            "name": "out.mbtiles",
+            "minzoom": "2",
+            "maxzoom": "14",
+            "bounds": "-10.5,35,20,60.25",
+            "center": "5,47,14",
+            "json": "{\"vector_layers\":[{\"id\":\"roads\",\"fields\":{}}]}"
+        }"#;
+
+        let tilejson = TileJson::parse(json).unwrap();
+        assert!(tilejson.tiles.is_empty());
+        let info = tilejson.info;
+        assert_eq!((info.min_zoom, info.max_zoom), (Some(2), Some(14)));
+        assert_eq!(info.bounds, Some([-10.5, 35.0, 20.0, 60.25]));
+        assert_eq!(info.center, Some([5.0, 47.0, 14.0]));
+        assert_eq!(info.row_order, None);
+        assert_eq!(info.vector_layers[0].id, "roads");
+    }
+
+    #[test]
+    fn invalid_tilejson() {
+        assert!(TileJson::parse("not json").is_err());
+        assert!(TileJson::parse("[1, 2]").is_err());
+    }
+}
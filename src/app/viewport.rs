@@ -0,0 +1,227 @@
+//! Saved positions of the map: the last viewport and the named bookmarks.
+
+use galileo::MapView;
+use galileo_types::{geo::GeoPoint, latlon};
+use serde::{Deserialize, Serialize};
+
+use crate::maptiler_style::StyleView;
+
+/// Resolution of the zoom level 0 of MapLibre styles, with the world 512 pixels wide.
+const TOP_RESOLUTION: f64 = 78271.51696402048;
+
+/// Position, scale and rotation of the map.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Viewport {
+    pub lat: f64,
+    pub lon: f64,
+    /// Meters per pixel.
+    pub resolution: f64,
+    /// Tilt of the map in radians.
+    #[serde(default)]
+    pub rotation_x: f64,
+    /// Rotation of the map around the vertical axis in radians.
+    #[serde(default)]
+    pub rotation_z: f64,
+}
+
+impl Viewport {
+    /// Viewport at a style zoom level, without rotation.
+    pub fn at(lat: f64, lon: f64, zoom: f64) -> Self {
+        Self {
+            lat,
+            lon,
+            resolution: zoom_resolution(zoom),
+            rotation_x: 0.0,
+            rotation_z: 0.0,
+        }
+    }
+
+    /// Initial viewport defined by a style.
+    pub fn from_style(view: &StyleView) -> Self {
+        let [lon, lat] = view.center;
+        Self {
+            rotation_x: view.pitch.to_radians(),
+            // The bearing is the direction that is up, so the map turns the other way
+            rotation_z: -view.bearing.to_radians(),
+            ..Self::at(lat, lon, view.zoom)
+        }
+    }
+
+    /// Returns the viewport of the map view, if the view has a position.
+    pub fn from_view(view: &MapView) -> Option<Self> {
+        let position = view.position()?;
+        Some(Self {
+            lat: position.lat(),
+            lon: position.lon(),
+            resolution: view.resolution(),
+            rotation_x: view.rotation_x(),
+            rotation_z: view.rotation_z(),
+        })
+    }
+
+    pub fn to_view(self) -> MapView {
+        MapView::new(&latlon!(self.lat, self.lon), self.resolution)
+            .with_rotation(self.rotation_x, self.rotation_z)
+    }
+
+    /// Style zoom level of the viewport.
+    pub fn zoom(&self) -> f64 {
+        resolution_zoom(self.resolution)
+    }
+}
+
+/// Returns the resolution of a style zoom level.
+pub fn zoom_resolution(zoom: f64) -> f64 {
+    TOP_RESOLUTION / 2f64.powf(zoom)
+}
+
+/// Returns the style zoom level of a resolution.
+pub fn resolution_zoom(resolution: f64) -> f64 {
+    (TOP_RESOLUTION / resolution).log2()
+}
+
+/// Named viewport to return to.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Bookmark {
+    pub name: String,
+    pub viewport: Viewport,
+}
+
+/// Bookmarks of places with different kinds of features, given to new users.
+pub fn default_bookmarks() -> Vec<Bookmark> {
+    [
+        ("Sample City", 55.75, 37.62, 14.0),
+        ("Dense city: Paris", 48.8566, 2.3522, 15.0),
+        ("Coast: Dubrovnik", 42.6410, 18.1080, 13.0),
+        ("Mountains: Zermatt", 46.0207, 7.7491, 12.0),
+    ]
+    .into_iter()
+    .map(|(name, lat, lon, zoom)| Bookmark {
+        name: name.to_string(),
+        viewport: Viewport::at(lat, lon, zoom),
+    })
+    .collect()
+}
+
+/// Window listing the bookmarks.
+#[derive(Debug, Default)]
+pub struct BookmarksWindow {
+    pub open: bool,
+    new_name: String,
+}
+
+impl BookmarksWindow {
+    /// Shows the window. Returns the viewport of the bookmark to go to, if one was chosen.
+    /// `current` is the viewport of the map, which can be bookmarked.
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        bookmarks: &mut Vec<Bookmark>,
+        current: Option<Viewport>,
+    ) -> Option<Viewport> {
+        let mut open = self.open;
+        let mut go_to = None;
+        egui::Window::new("Bookmarks")
+            .open(&mut open)
+            .default_width(250.0)
+            .show(ctx, |ui| go_to = self.ui(ui, bookmarks, current));
+        self.open = open;
+
+        go_to
+    }
+
+    fn ui(
+        &mut self,
+        ui: &mut egui::Ui,
+        bookmarks: &mut Vec<Bookmark>,
+        current: Option<Viewport>,
+    ) -> Option<Viewport> {
+        let mut go_to = None;
+        let mut remove = None;
+        for (index, bookmark) in bookmarks.iter_mut().enumerate() {
+            ui.horizontal(|ui| {
+                if ui
+                    .button(bookmark.name.as_str())
+                    .on_hover_text(format!(
+                        "{:.4}, {:.4}, zoom {:.1}",
+                        bookmark.viewport.lat,
+                        bookmark.viewport.lon,
+                        bookmark.viewport.zoom()
+                    ))
+                    .clicked()
+                {
+                    go_to = Some(bookmark.viewport);
+                }
+                if let Some(current) = current {
+                    if ui
+                        .small_button("Update")
+                        .on_hover_text("Set the bookmark to the current view")
+                        .clicked()
+                    {
+                        bookmark.viewport = current;
+                    }
+                }
+                if ui.small_button("Del").clicked() {
+                    remove = Some(index);
+                }
+            });
+        }
+        if let Some(index) = remove {
+            bookmarks.remove(index);
+        }
+
+        ui.separator();
+        ui.horizontal(|ui| {
+            ui.add(egui::TextEdit::singleline(&mut self.new_name).hint_text("name"));
+            let name = self.new_name.trim();
+            if ui
+                .add_enabled(
+                    !name.is_empty() && current.is_some(),
+                    egui::Button::new("Add current view"),
+                )
+                .clicked()
+            {
+                if let Some(viewport) = current {
+                    bookmarks.push(Bookmark {
+                        name: name.to_string(),
+                        viewport,
+                    });
+                    self.new_name.clear();
+                }
+            }
+        });
+
+        go_to
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn style_zoom_levels() {
+        let viewport = Viewport::at(0.0, 0.0, 0.0);
+        assert!((viewport.resolution * 512.0 - 2.0 * 20037508.342789244).abs() < 1e-3);
+
+        let viewport = Viewport::at(55.75, 37.62, 14.5);
+        assert!((viewport.zoom() - 14.5).abs() < 1e-9);
+
+        // A 256 pixel tile of zoom 3 covers the same area as a 512 pixel tile of style zoom 2
+        assert!((resolution_zoom(156543.03392800014 / 8.0) - 2.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn viewport_from_style() {
+        let viewport = Viewport::from_style(&StyleView {
+            center: [37.62, 55.75],
+            zoom: 10.0,
+            bearing: 90.0,
+            pitch: 30.0,
+        });
+        assert_eq!((viewport.lat, viewport.lon), (55.75, 37.62));
+        assert!((viewport.zoom() - 10.0).abs() < 1e-9);
+        assert!((viewport.rotation_z + std::f64::consts::FRAC_PI_2).abs() < 1e-9);
+        assert!((viewport.rotation_x - std::f64::consts::FRAC_PI_6).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,106 @@
+//! X-ray mode: a generated style drawing every source layer in its own color.
+
+use egui::{ecolor::Hsva, Color32};
+use galileo::{
+    layer::vector_tile_layer::style::{
+        StyleRule, VectorTileLineSymbol, VectorTilePointSymbol, VectorTilePolygonSymbol,
+        VectorTileStyle, VectorTileSymbol,
+    },
+    Color,
+};
+
+use super::{catalog::TileCatalog, inspector::GeometryType};
+
+/// Opacity of the polygon fill. Polygon symbols have no outline, so the fill is translucent to
+/// keep overlapping features visible.
+const POLYGON_ALPHA: u8 = 80;
+
+/// Returns the x-ray color of the layer with the given index. Hues are spread by the golden
+/// ratio, so that layers next to each other in the list get clearly different colors.
+fn layer_color(index: usize) -> Color32 {
+    let hue = (index as f32 * 0.618_034).fract();
+    Hsva::new(hue, 0.8, 0.95, 1.0).into()
+}
+
+fn to_galileo_color(color: Color32) -> Color {
+    Color::rgba(color.r(), color.g(), color.b(), color.a())
+}
+
+/// Builds a style drawing all features of every layer in the catalog in the layer color with thin
+/// lines, small points and translucent polygons. A rule applies to all geometries of a layer, so
+/// the symbol is chosen by the geometry type most features of the layer have.
+pub fn xray_style(catalog: &TileCatalog) -> VectorTileStyle {
+    let rules = catalog
+        .layers()
+        .enumerate()
+        .filter_map(|(index, (name, info))| {
+            let color = layer_color(index);
+            let symbol = match info.main_geometry_type()? {
+                GeometryType::Point => VectorTileSymbol::Point(VectorTilePointSymbol {
+                    size: 4.0,
+                    color: to_galileo_color(color),
+                }),
+                GeometryType::Line => VectorTileSymbol::Line(VectorTileLineSymbol {
+                    width: 1.0,
+                    stroke_color: to_galileo_color(color),
+                }),
+                GeometryType::Polygon => VectorTileSymbol::Polygon(VectorTilePolygonSymbol {
+                    fill_color: to_galileo_color(Color32::from_rgba_unmultiplied(
+                        color.r(),
+                        color.g(),
+                        color.b(),
+                        POLYGON_ALPHA,
+                    )),
+                }),
+            };
+
+            Some(StyleRule {
+                layer_name: Some(name.to_string()),
+                properties: vec![],
+                symbol,
+            })
+        })
+        .collect();
+
+    VectorTileStyle {
+        rules,
+        background: Color::rgba(20, 20, 20, 255),
+    }
+}
+
+/// Shows the colors of the layers in the x-ray style.
+pub fn legend_ui(ui: &mut egui::Ui, catalog: &TileCatalog) {
+    if catalog.is_empty() {
+        ui.label("No tiles decoded yet.");
+        return;
+    }
+
+    ui.weak(
+        "Every layer is drawn with one symbol for the geometry type most of its features have. \
+         Features of other types are not drawn, and polygons have no outline.",
+    );
+    ui.separator();
+
+    for (index, (name, info)) in catalog.layers().enumerate() {
+        ui.horizontal(|ui| {
+            let (rect, _) = ui.allocate_exact_size(egui::vec2(12.0, 12.0), egui::Sense::hover());
+            ui.painter().rect_filled(rect, 2.0, layer_color(index));
+            ui.label(name);
+            let Some(main_type) = info.main_geometry_type() else {
+                return;
+            };
+            ui.weak(main_type.to_string());
+
+            let hidden: Vec<String> = info
+                .geometry_types
+                .iter()
+                .filter(|(geometry_type, _)| **geometry_type != main_type)
+                .map(|(geometry_type, count)| format!("{geometry_type}: {count}"))
+                .collect();
+            if !hidden.is_empty() {
+                ui.colored_label(ui.visuals().warn_fg_color, "⚠")
+                    .on_hover_text(format!("Not drawn: {}", hidden.join(", ")));
+            }
+        });
+    }
+}
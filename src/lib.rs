@@ -4,4 +4,3 @@ mod app;
 pub(crate) mod maptiler_style;
 
 pub use app::GalileoApp;
-
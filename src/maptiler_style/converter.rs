@@ -64,7 +64,8 @@
 //! - Tile URLs and attribution are ignored
 //!
 //! ## Metadata
-//! - Layer groups and organization
+//! - Layer groups are not part of the converted style. They are returned alongside the rules
+//!   (see [`ConvertedStyle::groups`]) so that the editor can organize rules the same way
 //! - Copyright and attribution information
 //! - Custom metadata fields
 //!
@@ -77,6 +78,7 @@
 //! - **IN filters**: `["in", "property", "value1", "value2", ...]` (converted to multiple rules)
 //! - **Combined filters**: `["all", ...]` with simple equality filters only
 //! - **Background color**: Extracted from background layer
+//! - **Initial view**: `center`, `zoom`, `bearing` and `pitch` (see [`ConvertedStyle::view`])
 
 use galileo::{
     layer::vector_tile_layer::style::{
@@ -88,26 +90,104 @@ use galileo::{
 };
 use serde_json::Value;
 
-use super::{Layer, LayerType, Style};
+use super::{Layer, LayerGroup, LayerType, Style};
+
+/// Result of a MapTiler style conversion that keeps track of where every rule came from
+#[derive(Debug, Clone)]
+pub struct ConvertedStyle {
+    /// Converted rules in the order of the source layers
+    pub rules: Vec<ConvertedRule>,
+    /// Background color of the style
+    pub background: Color,
+    /// Layer groups defined in the style metadata
+    pub groups: Vec<LayerGroup>,
+    /// Initial view of the map, if the style has a center
+    pub view: Option<StyleView>,
+}
+
+/// Initial view of the map defined by a style
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StyleView {
+    /// Longitude and latitude of the center in degrees
+    pub center: [f64; 2],
+    /// Zoom level, with the whole world 512 pixels wide at the zoom level 0
+    pub zoom: f64,
+    /// Compass direction that is up, in degrees
+    pub bearing: f64,
+    /// Tilt of the map from the vertical, in degrees
+    pub pitch: f64,
+}
+
+/// A Galileo style rule together with the MapTiler layer it was converted from
+#[derive(Debug, Clone)]
+pub struct ConvertedRule {
+    /// Id of the source MapTiler layer
+    pub layer_id: String,
+    pub rule: StyleRule,
+}
+
+impl ConvertedStyle {
+    /// Returns the group the given layer belongs to, if any
+    pub fn layer_group(&self, layer_id: &str) -> Option<&LayerGroup> {
+        self.groups
+            .iter()
+            .find(|group| group.layers.iter().any(|id| id == layer_id))
+    }
+}
 
-/// Convert a MapTiler style to a Galileo VectorTileStyle
-pub fn convert_maptiler_to_galileo(maptiler_style: &Style) -> VectorTileStyle {
+impl From<ConvertedStyle> for VectorTileStyle {
+    fn from(converted: ConvertedStyle) -> Self {
+        VectorTileStyle {
+            rules: converted.rules.into_iter().map(|r| r.rule).collect(),
+            background: converted.background,
+        }
+    }
+}
+
+/// Convert a MapTiler style to a Galileo VectorTileStyle, keeping the source layer ids and
+/// layer groups of the converted rules
+pub fn convert_maptiler_style(maptiler_style: &Style) -> ConvertedStyle {
     let mut rules = Vec::new();
 
     for layer in &maptiler_style.layers {
         // Convert each layer to one or more style rules
-        let layer_rules = convert_layer(layer);
+        let layer_rules = convert_layer(layer).map(|rule| ConvertedRule {
+            layer_id: layer.id.clone(),
+            rule,
+        });
         rules.extend(layer_rules);
     }
 
     // Extract background color if present
     let background = extract_background_color(maptiler_style);
 
-    VectorTileStyle { rules, background }
+    let groups = maptiler_style
+        .metadata
+        .as_ref()
+        .and_then(|metadata| metadata.maptiler.as_ref())
+        .and_then(|maptiler| maptiler.groups.clone())
+        .unwrap_or_default();
+
+    let view = match maptiler_style.center.as_deref() {
+        Some(&[lon, lat]) => Some(StyleView {
+            center: [lon, lat],
+            zoom: maptiler_style.zoom.unwrap_or(0.0),
+            bearing: maptiler_style.bearing.unwrap_or(0.0),
+            pitch: maptiler_style.pitch.unwrap_or(0.0),
+        }),
+        _ => None,
+    };
+
+    ConvertedStyle {
+        rules,
+        background,
+        groups,
+        view,
+    }
 }
 
 /// Convert a single MapTiler layer to one or more Galileo style rules
-fn convert_layer(layer: &Layer) -> Option<StyleRule> {
+pub fn convert_layer(layer: &Layer) -> Option<StyleRule> {
     // Skip layers without source-layer (like background)
     let layer_name = match &layer.source_layer {
         Some(name) => name.clone(),
@@ -614,7 +694,7 @@ mod tests {
             serde_json::from_str(&json_content).expect("Failed to parse maptiler.json");
 
         // Convert to Galileo style
-        let galileo_style = convert_maptiler_to_galileo(&maptiler_style);
+        let galileo_style: VectorTileStyle = convert_maptiler_style(&maptiler_style).into();
 
         // Verify we have some rules
         assert!(
@@ -644,4 +724,49 @@ mod tests {
         let background = parse_color("hsl(47,79%,94%)").unwrap();
         assert_eq!(galileo_style.background, background);
     }
+
+    #[test]
+    fn test_convert_keeps_layer_groups() {
+        let json_content = include_str!("tests/maptiler.json");
+        let maptiler_style: Style =
+            serde_json::from_str(json_content).expect("Failed to parse maptiler.json");
+
+        let converted = convert_maptiler_style(&maptiler_style);
+        assert!(!converted.groups.is_empty(), "Should have layer groups");
+
+        let meadow = converted
+            .rules
+            .iter()
+            .find(|r| r.layer_id == "Meadow")
+            .expect("Should have a rule for 'Meadow' layer");
+        assert_eq!(meadow.rule.layer_name.as_deref(), Some("globallandcover"));
+
+        let grouped = converted
+            .rules
+            .iter()
+            .filter(|r| converted.layer_group(&r.layer_id).is_some())
+            .count();
+        assert!(grouped > 0, "Some rules should belong to a layer group");
+    }
+
+    #[test]
+    fn test_convert_keeps_view() {
+        let json_content = include_str!("tests/maptiler.json");
+        let mut maptiler_style: Style =
+            serde_json::from_str(json_content).expect("Failed to parse maptiler.json");
+
+        let view = convert_maptiler_style(&maptiler_style).view;
+        assert_eq!(
+            view,
+            Some(StyleView {
+                center: [0.0, 0.0],
+                zoom: 1.0,
+                bearing: 0.0,
+                pitch: 0.0,
+            })
+        );
+
+        maptiler_style.center = None;
+        assert_eq!(convert_maptiler_style(&maptiler_style).view, None);
+    }
 }
@@ -3,7 +3,9 @@ use std::collections::HashMap;
 
 pub mod converter;
 
-pub use converter::convert_maptiler_to_galileo;
+pub use converter::{
+    convert_layer, convert_maptiler_style, ConvertedRule, ConvertedStyle, StyleView,
+};
 
 /// MapTiler Style root structure
 #[derive(Debug, Clone, Serialize, Deserialize)]